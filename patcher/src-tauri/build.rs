@@ -1,3 +1,4 @@
+use sha2::{Digest, Sha256};
 use std::{
     collections::HashSet,
     env,
@@ -18,7 +19,7 @@ fn main() {
     collect_patch_files(&patches_dir, &patches_dir, &exclude, &mut files);
     files.sort();
 
-    let output = render_embedded_list(&files);
+    let output = render_embedded_list(&files, &payload_checksum(&patches_dir, &files));
     let out_dir = PathBuf::from(env::var("OUT_DIR").expect("Missing OUT_DIR"));
     let out_path = out_dir.join("embedded_patches.rs");
     fs::write(&out_path, output).expect("Failed to write embedded patches list");
@@ -97,7 +98,25 @@ fn is_excluded(path: &str, exclude: &HashSet<String>) -> bool {
     })
 }
 
-fn render_embedded_list(files: &[(String, String)]) -> String {
+// Must match embedded::compute_checksum: for each file in order, key + NUL + content + NUL.
+fn payload_checksum(patches_dir: &Path, files: &[(String, String)]) -> String {
+    let mut hasher = Sha256::new();
+    for (key, source) in files {
+        let content = fs::read(patches_dir.join(source))
+            .unwrap_or_else(|e| panic!("Failed to read patch file {}: {}", source, e));
+        hasher.update(key.as_bytes());
+        hasher.update([0]);
+        hasher.update(&content);
+        hasher.update([0]);
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn render_embedded_list(files: &[(String, String)], checksum: &str) -> String {
     let mut output = String::new();
     output.push_str("// Auto-generated by build.rs. Do not edit manually.\n");
 
@@ -117,7 +136,10 @@ fn render_embedded_list(files: &[(String, String)]) -> String {
             output.push_str(&format!("    (\"{}\", \"{}\"),\n", key, source));
         }
     }
-    output.push_str("];\n");
+    output.push_str("];\n\n");
+
+    // SHA-256 of the embedded bytes, checked at runtime to catch a corrupted build.
+    output.push_str(&format!("pub const EMBEDDED_CHECKSUM: &str = \"{}\";\n", checksum));
 
     output
 }
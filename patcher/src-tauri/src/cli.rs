@@ -0,0 +1,165 @@
+// 命令行模式
+// 带子命令启动时不打开窗口, 直接调用与前端相同的命令函数; 结果以 JSON 输出到 stdout, 进度输出到 stderr
+// 退出码: 0 成功, 1 操作失败, 2 参数错误
+
+use serde::Serialize;
+use std::path::Path;
+use tauri::ipc::{Channel, InvokeResponseBody};
+
+use crate::commands::{
+    check_patch_status, detect_antigravity_installs, first_antigravity_path, init_logging,
+    install_patch, installed_features, uninstall_patch, DetectionCache, InstallOutcome, PatchError,
+};
+
+const USAGE: &str = "用法: Antigravity-Power-Pro [--headless] <命令> [选项]
+
+命令:
+  detect                                 列出检测到的 Antigravity 安装
+  status    [--path <目录>]              查看补丁状态
+  install   [--path <目录>] [--force]    安装补丁, 功能开关沿用已安装的配置
+  uninstall [--path <目录>] [--force] [--use-markers]
+                                         卸载补丁
+
+选项:
+  --path <目录>    安装目录, 不指定时使用检测到的第一个安装
+  --force          编辑器运行时仍继续
+  --use-markers    不用备份还原, 只剥离补丁标记";
+
+/// 参数错误
+const EXIT_USAGE: i32 = 2;
+
+/// 可选的前缀参数, 不带子命令时也显式要求命令行模式
+const HEADLESS_FLAG: &str = "--headless";
+
+const SUBCOMMANDS: &[&str] = &["detect", "status", "install", "uninstall", "help", "--help", "-h"];
+
+/// 解析后的命令行选项
+#[derive(Default)]
+struct Options {
+    path: Option<String>,
+    force: bool,
+    use_markers: bool,
+}
+
+/// 按命令行参数执行子命令并返回退出码; 没有子命令时返回 None, 照常启动界面
+pub fn run(args: Vec<String>) -> Option<i32> {
+    let headless = args.first().is_some_and(|arg| arg == HEADLESS_FLAG);
+    let args = if headless { &args[1..] } else { &args[..] };
+    match args.first() {
+        Some(command) if SUBCOMMANDS.contains(&command.as_str()) => {}
+        // 未知参数 (如 macOS 访达附带的 -psn_ 参数) 照常启动界面
+        _ if !headless => return None,
+        _ => return Some(usage_error("缺少命令")),
+    }
+
+    attach_console();
+    init_logging();
+    let command = args[0].as_str();
+    let options = match parse_options(command, &args[1..]) {
+        Ok(options) => options,
+        Err(message) => return Some(usage_error(&message)),
+    };
+    Some(tauri::async_runtime::block_on(execute(command, options)))
+}
+
+fn parse_options(command: &str, args: &[String]) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match (command, arg.as_str()) {
+            ("status" | "install" | "uninstall", "--path") => {
+                options.path = Some(args.next().ok_or("--path 缺少目录")?.clone());
+            }
+            ("install" | "uninstall", "--force") => options.force = true,
+            ("uninstall", "--use-markers") => options.use_markers = true,
+            _ => return Err(format!("{} 不支持参数 {}", command, arg)),
+        }
+    }
+    Ok(options)
+}
+
+async fn execute(command: &str, options: Options) -> i32 {
+    if matches!(command, "help" | "--help" | "-h") {
+        println!("{}", USAGE);
+        return 0;
+    }
+    if command == "detect" {
+        let installs = detect_antigravity_installs(None).await;
+        return print_json(&installs, !installs.is_empty());
+    }
+
+    let Some(path) = install_path(options.path).await else {
+        return print_json(&PatchError::PathNotFound, false);
+    };
+    let force = Some(options.force);
+    match command {
+        "status" => match check_patch_status(path) {
+            Ok(status) => print_json(&status, true),
+            Err(error) => print_json(&error, false),
+        },
+        "install" => {
+            let (features, manager_features) = installed_features(Path::new(&path));
+            let outcome =
+                install_patch(path, features, manager_features, progress_channel(), force, None);
+            let succeeded =
+                matches!(outcome, InstallOutcome::Applied { .. } | InstallOutcome::AlreadyPatched);
+            print_json(&outcome, succeeded)
+        }
+        _ => match uninstall_patch(path, force, Some(options.use_markers)) {
+            Ok(report) => print_json(&report, true),
+            Err(error) => print_json(&error, false),
+        },
+    }
+}
+
+/// 指定的安装目录, 未指定时使用检测到的第一个安装
+/// 命令行每次只检测一次, 不需要与界面共享的检测缓存
+async fn install_path(path: Option<String>) -> Option<String> {
+    match path {
+        Some(path) => Some(path),
+        None => first_antigravity_path(&DetectionCache::default()).await,
+    }
+}
+
+/// 进度逐条以 JSON 输出到 stderr, 不干扰 stdout 上的结果
+fn progress_channel<T>() -> Channel<T>
+where
+    T: tauri::ipc::IpcResponse,
+{
+    Channel::new(|body| {
+        if let InvokeResponseBody::Json(json) = body {
+            eprintln!("{}", json);
+        }
+        Ok(())
+    })
+}
+
+fn print_json(value: &impl Serialize, succeeded: bool) -> i32 {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => println!("{}", json),
+        Err(e) => eprintln!("序列化结果失败: {}", e),
+    }
+    if succeeded {
+        0
+    } else {
+        1
+    }
+}
+
+fn usage_error(message: &str) -> i32 {
+    eprintln!("{}\n\n{}", message, USAGE);
+    EXIT_USAGE
+}
+
+/// release 构建使用 windows 子系统, 从终端启动时没有控制台, 需连接到父进程的控制台才能输出
+#[cfg(target_os = "windows")]
+fn attach_console() {
+    use windows_sys::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+    // SAFETY: AttachConsole 没有指针参数, 已有控制台时调用失败不影响输出
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn attach_console() {}
@@ -0,0 +1,224 @@
+// 补丁改动审计
+// 逐个文件比较写入前后的内容, 给出每处改动的字节偏移与长度, 以及改动周围上下文的短摘要, 审计时可据此重建差异
+// 先按行做 Myers 差异, 再在每处改动内去掉首尾相同的字节, 行内注入 (如在 </head> 前插入标签) 也能精确到字节
+
+use serde::Serialize;
+use std::ops::Range;
+
+use super::integrity::sha256_hex;
+
+/// 上下文摘要取改动前后各多少字节
+const CONTEXT_BYTES: usize = 32;
+
+/// 短摘要保留的十六进制字符数
+const SHORT_HASH_LEN: usize = 16;
+
+/// 按行差异最多计算的编辑步数, 超出时 (文件被整体替换) 把首尾相同部分之间视为一处改动
+const MAX_EDIT_DISTANCE: usize = 1000;
+
+/// 单处改动: 原内容 [oldOffset, oldOffset + removedLength) 被替换为新内容 [offset, offset + length)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChangedBlock {
+    pub offset: u64,
+    pub length: u64,
+    #[serde(rename = "oldOffset")]
+    pub old_offset: u64,
+    #[serde(rename = "removedLength")]
+    pub removed_length: u64,
+    /// 新内容中改动前后各 32 字节的 SHA-256 前 16 位, 用于确认改动位置
+    #[serde(rename = "contextHash")]
+    pub context_hash: String,
+}
+
+/// 单个文件的改动
+#[derive(Debug, Serialize)]
+pub struct FileChanges {
+    pub path: String,
+    /// 写入前的 SHA-256, 文件原本不存在时为空
+    #[serde(rename = "sha256Before")]
+    pub sha256_before: Option<String>,
+    #[serde(rename = "sha256After")]
+    pub sha256_after: String,
+    pub blocks: Vec<ChangedBlock>,
+}
+
+impl FileChanges {
+    pub fn new(path: String, before: Option<&[u8]>, after: &[u8]) -> Self {
+        Self {
+            path,
+            sha256_before: before.map(sha256_hex),
+            sha256_after: sha256_hex(after),
+            blocks: changed_blocks(before.unwrap_or_default(), after),
+        }
+    }
+}
+
+/// 比较前后内容, 返回按位置排序的各处改动; 内容相同时为空
+pub fn changed_blocks(old: &[u8], new: &[u8]) -> Vec<ChangedBlock> {
+    let old_lines = split_lines(old);
+    let new_lines = split_lines(new);
+    let hunks = line_hunks(&old_lines, &new_lines)
+        .unwrap_or_else(|| vec![(0..old_lines.len(), 0..new_lines.len())]);
+
+    let old_offsets = line_offsets(&old_lines);
+    let new_offsets = line_offsets(&new_lines);
+    hunks
+        .into_iter()
+        .filter_map(|(old_range, new_range)| {
+            let old_bytes = old_offsets[old_range.start]..old_offsets[old_range.end];
+            let new_bytes = new_offsets[new_range.start]..new_offsets[new_range.end];
+            trimmed_block(old, new, old_bytes, new_bytes)
+        })
+        .collect()
+}
+
+/// 去掉一处改动首尾与原内容相同的字节, 去掉后没有差异时返回 None
+fn trimmed_block(
+    old: &[u8],
+    new: &[u8],
+    mut old_bytes: Range<usize>,
+    mut new_bytes: Range<usize>,
+) -> Option<ChangedBlock> {
+    let both_non_empty = |old_bytes: &Range<usize>, new_bytes: &Range<usize>| {
+        !old_bytes.is_empty() && !new_bytes.is_empty()
+    };
+    while both_non_empty(&old_bytes, &new_bytes) && old[old_bytes.start] == new[new_bytes.start] {
+        old_bytes.start += 1;
+        new_bytes.start += 1;
+    }
+    while both_non_empty(&old_bytes, &new_bytes) && old[old_bytes.end - 1] == new[new_bytes.end - 1] {
+        old_bytes.end -= 1;
+        new_bytes.end -= 1;
+    }
+    if old_bytes.is_empty() && new_bytes.is_empty() {
+        return None;
+    }
+
+    let before = &new[new_bytes.start.saturating_sub(CONTEXT_BYTES)..new_bytes.start];
+    let after = &new[new_bytes.end..(new_bytes.end + CONTEXT_BYTES).min(new.len())];
+    let mut context_hash = sha256_hex(&[before, after].concat());
+    context_hash.truncate(SHORT_HASH_LEN);
+
+    Some(ChangedBlock {
+        offset: new_bytes.start as u64,
+        length: new_bytes.len() as u64,
+        old_offset: old_bytes.start as u64,
+        removed_length: old_bytes.len() as u64,
+        context_hash,
+    })
+}
+
+/// 按行切分, 每行保留行尾的换行符
+fn split_lines(content: &[u8]) -> Vec<&[u8]> {
+    content.split_inclusive(|byte| *byte == b'\n').collect()
+}
+
+/// 各行在内容中的起始偏移, 末尾多一项为内容总长度
+fn line_offsets(lines: &[&[u8]]) -> Vec<usize> {
+    let mut offsets = Vec::with_capacity(lines.len() + 1);
+    let mut offset = 0;
+    offsets.push(offset);
+    for line in lines {
+        offset += line.len();
+        offsets.push(offset);
+    }
+    offsets
+}
+
+/// 差异中的单步
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Myers 差异, 返回不相同的行区间 (原内容行, 新内容行); 编辑步数超出上限时返回 None
+fn line_hunks(old: &[&[u8]], new: &[&[u8]]) -> Option<Vec<(Range<usize>, Range<usize>)>> {
+    let steps = diff_steps(old, new)?;
+
+    let mut hunks = Vec::new();
+    let (mut old_index, mut new_index) = (0, 0);
+    let mut current: Option<(Range<usize>, Range<usize>)> = None;
+    for step in steps {
+        match step {
+            Step::Equal => {
+                hunks.extend(current.take());
+                old_index += 1;
+                new_index += 1;
+            }
+            Step::Delete | Step::Insert => {
+                let hunk = current.get_or_insert((old_index..old_index, new_index..new_index));
+                if step == Step::Delete {
+                    old_index += 1;
+                    hunk.0.end = old_index;
+                } else {
+                    new_index += 1;
+                    hunk.1.end = new_index;
+                }
+            }
+        }
+    }
+    hunks.extend(current);
+    Some(hunks)
+}
+
+/// 从原内容到新内容的最短编辑步骤
+fn diff_steps(old: &[&[u8]], new: &[&[u8]]) -> Option<Vec<Step>> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max_d = (old.len() + new.len()).min(MAX_EDIT_DISTANCE) as isize;
+    let offset = max_d + 1;
+    let mut v = vec![0isize; (2 * offset + 1) as usize];
+    let index = |k: isize| (k + offset) as usize;
+
+    // 每一步开始前 k ∈ [-(d+1), d+1] 范围内的 v, 回溯时使用
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    for d in 0..=max_d {
+        trace.push(v[index(-d - 1)..=index(d + 1)].to_vec());
+        let mut k = -d;
+        while k <= d {
+            let mut x = if k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]) {
+                v[index(k + 1)]
+            } else {
+                v[index(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index(k)] = x;
+            if x >= n && y >= m {
+                return Some(backtrack(&trace, n, m));
+            }
+            k += 2;
+        }
+    }
+    None
+}
+
+fn backtrack(trace: &[Vec<isize>], n: isize, m: isize) -> Vec<Step> {
+    let mut steps = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let at = |k: isize| v[(k + d + 1) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) { k + 1 } else { k - 1 };
+        let prev_x = at(prev_k);
+        let prev_y = prev_x - prev_k;
+
+        while x > prev_x && y > prev_y {
+            steps.push(Step::Equal);
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            steps.push(if x == prev_x { Step::Insert } else { Step::Delete });
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    steps.reverse();
+    steps
+}
@@ -0,0 +1,724 @@
+// 补丁备份模块
+// 安装补丁前把将被覆盖的原始文件复制到应用数据目录, 并记录备份清单
+// 另外安装前把原版文件存为一组快照 (备份集), 每个安装只保留最近几组且总大小有上限, 可回滚到指定的一组
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::config::{app_data_dir, read_config};
+use super::detect::path_key;
+use super::integrity::{sha256_hex, verify_file_hashes, FileIntegrity};
+use super::editor::Editor;
+use super::error::PatchError;
+use super::lock::PatchLock;
+use super::marker::{is_patched_content, strip_patch_markers};
+use super::filesystem::{DiskFs, FileSystem};
+use super::plan::PatchPlan;
+use super::version::read_app_version;
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// 备份集中存放文件的子目录
+const BACKUP_SET_FILES_DIR: &str = "files";
+
+/// 所有编辑器的备份集合计大小上限, 超出时从最旧的开始删除 (每个安装至少保留最新的一组)
+const MAX_BACKUP_SETS_SIZE: u64 = 512 * 1024 * 1024;
+
+/// 备份清单 (每个安装目录一份)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupManifest {
+    /// 备份对应的编辑器安装目录
+    #[serde(rename = "installPath")]
+    pub install_path: String,
+    /// 备份时的编辑器版本, 旧版本清单或读取不到版本时为空
+    #[serde(rename = "editorVersion")]
+    pub editor_version: Option<String>,
+    pub files: Vec<BackupEntry>,
+}
+
+/// 单个已备份文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupEntry {
+    /// 相对安装目录的路径, 统一使用 `/` 分隔
+    #[serde(rename = "relativePath")]
+    pub relative_path: String,
+    /// 备份时间 (Unix 秒)
+    #[serde(rename = "createdAt")]
+    pub created_at: u64,
+}
+
+/// 安装目录对应的备份目录: <应用数据目录>/backups/<安装路径转义>
+pub fn backup_dir(install_path: &Path) -> PathBuf {
+    let key: String = install_path
+        .to_string_lossy()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect();
+    app_data_dir().join("backups").join(key)
+}
+
+/// 把 `a/b/c` 形式的相对路径拼到根目录上
+pub fn resolve_relative(root: &Path, relative_path: &str) -> PathBuf {
+    relative_path
+        .split('/')
+        .fold(root.to_path_buf(), |path, part| path.join(part))
+}
+
+/// 读取安装目录的备份清单, 不存在或损坏时返回 None
+fn read_manifest(file_system: &impl FileSystem, install_path: &Path) -> Option<BackupManifest> {
+    let content = file_system.read(&backup_dir(install_path).join(MANIFEST_FILE)).ok()?;
+    serde_json::from_slice(&content).ok()
+}
+
+fn write_manifest(
+    file_system: &impl FileSystem,
+    install_path: &Path,
+    manifest: &BackupManifest,
+) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(manifest)
+        .map_err(|e| format!("序列化备份清单失败: {}", e))?;
+    file_system
+        .write(&backup_dir(install_path).join(MANIFEST_FILE), content.as_bytes())
+        .map_err(|e| format!("写入备份清单失败: {}", e))
+}
+
+/// 复制文件, 按需创建目标所在目录
+fn copy_file(file_system: &impl FileSystem, from: &Path, to: &Path) -> std::io::Result<()> {
+    let content = file_system.read(from)?;
+    if let Some(parent) = to.parent() {
+        file_system.create_dir_all(parent)?;
+    }
+    file_system.write(to, &content)
+}
+
+/// 备份即将被补丁覆盖的文件
+///
+/// 已有有效备份的文件直接跳过, 避免用已打过补丁的文件覆盖干净的原版。
+/// 旧版本留下的同级 `.bak` 文件视为原版, 优先从它复制。
+pub fn backup_files(
+    file_system: &impl FileSystem,
+    install_path: &Path,
+    relative_paths: &[&str],
+) -> Result<(), String> {
+    let dir = backup_dir(install_path);
+    let mut manifest = read_manifest(file_system, install_path).unwrap_or_else(|| BackupManifest {
+        install_path: install_path.to_string_lossy().to_string(),
+        editor_version: current_version(install_path),
+        files: Vec::new(),
+    });
+
+    let mut changed = false;
+    for relative_path in relative_paths {
+        let target = resolve_relative(install_path, relative_path);
+        if !file_system.exists(&target) {
+            continue;
+        }
+
+        let backup_path = resolve_relative(&dir, relative_path);
+        let has_valid_backup = manifest
+            .files
+            .iter()
+            .any(|entry| entry.relative_path == *relative_path)
+            && file_system.exists(&backup_path);
+        if has_valid_backup {
+            continue;
+        }
+
+        let legacy_backup = PathBuf::from(format!("{}.bak", target.to_string_lossy()));
+        let source = if file_system.exists(&legacy_backup) {
+            legacy_backup
+        } else {
+            // 当前文件已被打过补丁且没有原版可用时, 不把它当作原版备份
+            let content = file_system.read(&target).unwrap_or_default();
+            if is_patched_content(&content) {
+                continue;
+            }
+            target
+        };
+
+        copy_file(file_system, &source, &backup_path)
+            .map_err(|e| format!("备份 {} 失败: {}", relative_path, e))?;
+
+        manifest.files.retain(|entry| entry.relative_path != *relative_path);
+        manifest.files.push(BackupEntry {
+            relative_path: relative_path.to_string(),
+            created_at: unix_now(),
+        });
+        changed = true;
+    }
+
+    if changed {
+        write_manifest(file_system, install_path, &manifest)?;
+    }
+
+    Ok(())
+}
+
+/// 备份清单记录的编辑器版本与当前安装不一致时返回 BackupVersionMismatch
+///
+/// 任一版本未知 (旧版本的清单, 读取不到 package.json) 时不视为不一致
+pub fn check_backup_version(
+    file_system: &impl FileSystem,
+    install_path: &Path,
+) -> Result<(), PatchError> {
+    let backup_version =
+        read_manifest(file_system, install_path).and_then(|manifest| manifest.editor_version);
+    ensure_same_version(install_path, backup_version)
+}
+
+fn ensure_same_version(
+    install_path: &Path,
+    backup_version: Option<String>,
+) -> Result<(), PatchError> {
+    match (backup_version, current_version(install_path)) {
+        (Some(backup_version), Some(current_version)) if backup_version != current_version => {
+            Err(PatchError::BackupVersionMismatch { backup_version, current_version })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// 检查备份清单与备份文件一致: 清单可以解析且不为空, 版本与当前安装一致, 每个条目的备份文件都存在
+///
+/// 一致时返回清单中的文件数
+pub fn verify_manifest(install_path: &Path) -> Result<usize, String> {
+    let manifest = read_manifest(&DiskFs, install_path)
+        .ok_or_else(|| "未找到备份清单或清单已损坏".to_string())?;
+    if manifest.files.is_empty() {
+        return Err("备份清单中没有文件, 卸载时只能剥离补丁标记".to_string());
+    }
+    if let (Some(backup_version), Some(current_version)) =
+        (&manifest.editor_version, current_version(install_path))
+    {
+        if *backup_version != current_version {
+            return Err(format!(
+                "备份来自编辑器版本 {}, 当前为 {}",
+                backup_version, current_version
+            ));
+        }
+    }
+
+    let dir = backup_dir(install_path);
+    let missing: Vec<&str> = manifest
+        .files
+        .iter()
+        .map(|entry| entry.relative_path.as_str())
+        .filter(|relative_path| !resolve_relative(&dir, relative_path).exists())
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("备份文件缺失: {}", missing.join(", ")));
+    }
+    Ok(manifest.files.len())
+}
+
+fn current_version(install_path: &Path) -> Option<String> {
+    read_app_version(install_path).map(|version| version.to_string())
+}
+
+/// 还原单个文件时采用的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RestoreStrategy {
+    /// 从应用数据目录中的备份还原
+    Backup,
+    /// 从旧版本留下的同级 `.bak` 文件还原
+    LegacyBackup,
+    /// 没有备份, 从当前文件中剥离补丁注入的内容
+    MarkerRemoval,
+    /// 文件不存在或未被补丁修改, 无需处理
+    Unchanged,
+}
+
+/// 单个文件的还原结果
+#[derive(Debug, Serialize)]
+pub struct RestoredFile {
+    pub path: String,
+    pub strategy: RestoreStrategy,
+}
+
+/// 卸载报告
+#[derive(Debug, Default, Serialize)]
+pub struct UninstallReport {
+    pub files: Vec<RestoredFile>,
+    /// 补丁配置已丢失, 部分文件只能按补丁标记尽力清除 (被补丁整体覆盖过的文件可能无法完全还原)
+    #[serde(rename = "bestEffort")]
+    pub best_effort: bool,
+}
+
+impl UninstallReport {
+    /// 补丁配置不存在时, 有文件靠剥离补丁标记还原就标记为尽力清除
+    pub fn mark_best_effort(&mut self, has_patch_config: bool) {
+        self.best_effort = !has_patch_config
+            && self
+                .files
+                .iter()
+                .any(|file| file.strategy == RestoreStrategy::MarkerRemoval);
+        if self.best_effort {
+            log::warn!("补丁配置不存在, 按补丁标记尽力清除");
+        }
+    }
+}
+
+/// 计划还原一个被补丁覆盖的文件
+///
+/// 依次尝试: 备份清单中的原版, 同级 `.bak`, 剥离补丁标记。
+/// `use_backups` 为 false 时跳过两种备份, 只剥离补丁标记 (备份与当前版本不一致时)
+pub fn plan_restore<F: FileSystem>(
+    plan: &mut PatchPlan<F>,
+    install_path: &Path,
+    relative_path: &str,
+    use_backups: bool,
+) -> Result<RestoredFile, PatchError> {
+    let target = resolve_relative(install_path, relative_path);
+    let backup_path = resolve_relative(&backup_dir(install_path), relative_path);
+    let legacy_backup = PathBuf::from(format!("{}.bak", target.to_string_lossy()));
+
+    let file_system = plan.file_system();
+    let in_manifest = read_manifest(file_system, install_path).is_some_and(|manifest| {
+        manifest
+            .files
+            .iter()
+            .any(|entry| entry.relative_path == relative_path)
+    });
+
+    let strategy = if use_backups && in_manifest && file_system.exists(&backup_path) {
+        let original = file_system
+            .read(&backup_path)
+            .map_err(|e| PatchError::io(&backup_path, "读取备份失败", e))?;
+        plan.write(target.clone(), original);
+        RestoreStrategy::Backup
+    } else if use_backups && file_system.exists(&legacy_backup) {
+        let original = file_system
+            .read(&legacy_backup)
+            .map_err(|e| PatchError::io(&legacy_backup, "读取备份失败", e))?;
+        plan.write(target.clone(), original);
+        RestoreStrategy::LegacyBackup
+    } else {
+        let stripped = plan
+            .content_of(&target)?
+            .and_then(|content| strip_patch_markers(&content));
+        match stripped {
+            Some(content) => {
+                plan.write(target.clone(), content);
+                RestoreStrategy::MarkerRemoval
+            }
+            None => RestoreStrategy::Unchanged,
+        }
+    };
+
+    Ok(RestoredFile {
+        path: target.to_string_lossy().to_string(),
+        strategy,
+    })
+}
+
+/// 一组备份 (某次安装前的文件快照)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupSet {
+    /// 备份集 ID (创建时间, Unix 毫秒)
+    pub id: String,
+    pub editor: Editor,
+    #[serde(rename = "installPath")]
+    pub install_path: String,
+    /// 创建时间 (Unix 秒)
+    #[serde(rename = "createdAt")]
+    pub created_at: u64,
+    /// 创建时的编辑器版本, 旧版本清单或读取不到版本时为空
+    #[serde(default, rename = "editorVersion")]
+    pub editor_version: Option<String>,
+    /// 相对安装目录的路径, 只包含创建时存在的文件
+    pub files: Vec<String>,
+    /// 手动创建的备份集的标签, 安装前自动创建的为空
+    #[serde(default)]
+    pub label: Option<String>,
+    /// 各备份文件的 SHA-256, 以相对路径为键; 旧版本清单中没有
+    #[serde(default, rename = "fileHashes")]
+    pub file_hashes: BTreeMap<String, String>,
+    /// 备份文件的总字节数; 旧版本清单中没有, 按 0 计入大小上限
+    #[serde(default)]
+    pub size: u64,
+}
+
+/// 备份集的完整性校验结果
+#[derive(Debug, Serialize)]
+pub struct BackupIntegrity {
+    pub id: String,
+    /// 所有记录了摘要的文件都存在且与清单一致
+    pub intact: bool,
+    /// 记录了摘要的文件的校验结果, 文件丢失时 actual 为空
+    pub files: Vec<FileIntegrity>,
+    /// 清单中没有摘要 (旧版本创建) 的文件, 只能确认仍然存在
+    pub unverified: Vec<String>,
+    /// 清单中没有摘要且已丢失的文件
+    pub missing: Vec<String>,
+}
+
+/// 编辑器的备份集目录: <应用数据目录>/backups/sets/<编辑器>
+fn backup_sets_dir(editor: Editor) -> PathBuf {
+    app_data_dir()
+        .join("backups")
+        .join("sets")
+        .join(editor.name().to_lowercase())
+}
+
+/// 把安装目录中的文件存为一组新的备份, 并清理超出保留数量或总大小的旧备份集, 返回备份集 ID
+///
+/// 内容与该安装最近一组备份完全相同时不再重复创建, 返回那一组的 ID; 没有可备份的文件时返回 None.
+/// 安装前自动创建 (`label` 为空) 时只保存原版: 文件已带补丁标记 (重复安装) 时原版已在更早的备份集中,
+/// 同样返回 None, 避免已打补丁的快照把原版挤出保留数量
+pub fn create_backup_set(
+    file_system: &impl FileSystem,
+    editor: Editor,
+    install_path: &Path,
+    relative_paths: &[&str],
+    label: Option<&str>,
+) -> Result<Option<String>, String> {
+    let existing: Vec<&str> = relative_paths
+        .iter()
+        .copied()
+        .filter(|relative_path| {
+            let path = resolve_relative(install_path, relative_path);
+            file_system.exists(&path) && !file_system.is_dir(&path)
+        })
+        .collect();
+    if existing.is_empty() {
+        return Ok(None);
+    }
+    let patched = |relative_path: &&str| {
+        file_system
+            .read(&resolve_relative(install_path, relative_path))
+            .is_ok_and(|content| is_patched_content(&content))
+    };
+    if label.is_none() && existing.iter().any(patched) {
+        return Ok(None);
+    }
+
+    let install = install_path.to_string_lossy().to_string();
+    let latest = list_backup_sets(file_system, editor)
+        .into_iter()
+        .find(|set| set.install_path == install);
+    let unchanged = |set: &BackupSet| same_as_backup_set(file_system, editor, set, install_path, &existing);
+    if let Some(set) = latest.filter(unchanged) {
+        return Ok(Some(set.id));
+    }
+
+    let id = unix_now_millis().to_string();
+    let set_dir = backup_sets_dir(editor).join(&id);
+    let files_dir = set_dir.join(BACKUP_SET_FILES_DIR);
+    let mut file_hashes = BTreeMap::new();
+    let mut size = 0;
+    for relative_path in &existing {
+        let backup_path = resolve_relative(&files_dir, relative_path);
+        copy_file(file_system, &resolve_relative(install_path, relative_path), &backup_path)
+            .map_err(|e| format!("备份 {} 失败: {}", relative_path, e))?;
+        // 摘要按写入后的备份文件计算, 记录的是实际保存下来的内容
+        let content = file_system
+            .read(&backup_path)
+            .map_err(|e| format!("读取备份 {} 失败: {}", relative_path, e))?;
+        file_hashes.insert(relative_path.to_string(), sha256_hex(&content));
+        size += content.len() as u64;
+    }
+
+    let set = BackupSet {
+        id,
+        editor,
+        install_path: install,
+        created_at: unix_now(),
+        editor_version: current_version(install_path),
+        files: existing.iter().map(|path| path.to_string()).collect(),
+        label: label.map(str::to_string),
+        file_hashes,
+        size,
+    };
+    let content = serde_json::to_string_pretty(&set)
+        .map_err(|e| format!("序列化备份清单失败: {}", e))?;
+    file_system
+        .write(&set_dir.join(MANIFEST_FILE), content.as_bytes())
+        .map_err(|e| format!("写入备份清单失败: {}", e))?;
+
+    prune_backup_sets(file_system);
+    Ok(Some(set.id))
+}
+
+/// 备份集中的文件与安装目录当前的文件是否完全一致
+fn same_as_backup_set(
+    file_system: &impl FileSystem,
+    editor: Editor,
+    set: &BackupSet,
+    install_path: &Path,
+    files: &[&str],
+) -> bool {
+    let files_dir = backup_sets_dir(editor).join(&set.id).join(BACKUP_SET_FILES_DIR);
+    set.files.len() == files.len()
+        && files.iter().all(|relative_path| {
+            set.files.iter().any(|file| file == relative_path)
+                && match (
+                    file_system.read(&resolve_relative(&files_dir, relative_path)),
+                    file_system.read(&resolve_relative(install_path, relative_path)),
+                ) {
+                    (Ok(backup), Ok(current)) => backup == current,
+                    _ => false,
+                }
+        })
+}
+
+/// 删除安装目录的备份与属于它的备份集, 返回删除的目录; 用于编辑器已卸载后清理
+pub fn remove_install_backups(editor: Editor, install_path: &Path) -> Result<Vec<String>, String> {
+    let key = path_key(&install_path.to_string_lossy());
+    let mut dirs = vec![backup_dir(install_path)];
+    dirs.extend(
+        list_backup_sets(&DiskFs, editor)
+            .into_iter()
+            .filter(|set| path_key(&set.install_path) == key)
+            .map(|set| backup_sets_dir(editor).join(set.id)),
+    );
+
+    let mut removed = Vec::new();
+    for dir in dirs.into_iter().filter(|dir| dir.exists()) {
+        fs::remove_dir_all(&dir).map_err(|e| format!("删除备份 {:?} 失败: {}", dir, e))?;
+        removed.push(dir.to_string_lossy().to_string());
+    }
+    Ok(removed)
+}
+
+/// 删除所有编辑器中超出保留数量 (配置项 backupRetention) 或总大小上限的最旧备份集
+fn prune_backup_sets(file_system: &impl FileSystem) {
+    let retention = read_config().backup_retention as usize;
+    let sets = Editor::ALL
+        .into_iter()
+        .flat_map(|editor| list_backup_sets(file_system, editor))
+        .collect();
+    for set in backup_sets_to_prune(sets, retention, MAX_BACKUP_SETS_SIZE) {
+        let dir = backup_sets_dir(set.editor).join(&set.id);
+        match file_system.remove_dir_all(&dir) {
+            Ok(()) => log::info!("清理旧备份集: {:?}", dir),
+            Err(e) => log::warn!("清理旧备份集失败: {:?}: {}", dir, e),
+        }
+    }
+}
+
+/// 需要删除的备份集: 每个安装只保留最近 `retention` 组,
+/// 保留下来的合计仍超过 `max_size` 时再从最旧的开始删除, 但每个安装最新的一组不删
+fn backup_sets_to_prune(mut sets: Vec<BackupSet>, retention: usize, max_size: u64) -> Vec<BackupSet> {
+    sort_newest_first(&mut sets);
+    let install_key = |set: &BackupSet| (set.editor, path_key(&set.install_path));
+
+    let mut kept_per_install: BTreeMap<(&str, String), usize> = BTreeMap::new();
+    let (mut kept, mut pruned) = (Vec::new(), Vec::new());
+    for set in sets {
+        let (editor, key) = install_key(&set);
+        let kept_count = kept_per_install.entry((editor.name(), key)).or_default();
+        if *kept_count < retention {
+            *kept_count += 1;
+            kept.push(set);
+        } else {
+            pruned.push(set);
+        }
+    }
+
+    let mut total: u64 = kept.iter().map(|set| set.size).sum();
+    let mut index = kept.len();
+    while total > max_size && index > 0 {
+        index -= 1;
+        let has_newer = kept[..index].iter().any(|newer| install_key(newer) == install_key(&kept[index]));
+        if has_newer {
+            total -= kept[index].size;
+            pruned.push(kept.remove(index));
+        }
+    }
+    pruned
+}
+
+/// 按创建时间排序, 最新的在前 (ID 为毫秒时间戳, 按数值比较)
+fn sort_newest_first(sets: &mut [BackupSet]) {
+    sets.sort_by_key(|set| std::cmp::Reverse(set.id.parse::<u128>().unwrap_or(0)));
+}
+
+/// 编辑器的所有备份集, 最新的在前; 清单缺失或损坏的目录忽略
+pub fn list_backup_sets(file_system: &impl FileSystem, editor: Editor) -> Vec<BackupSet> {
+    let Ok(entries) = file_system.read_dir(&backup_sets_dir(editor)) else {
+        return Vec::new();
+    };
+
+    let mut sets: Vec<BackupSet> = entries
+        .iter()
+        .filter_map(|dir| file_system.read(&dir.join(MANIFEST_FILE)).ok())
+        .filter_map(|content| serde_json::from_slice(&content).ok())
+        .collect();
+    sort_newest_first(&mut sets);
+    sets
+}
+
+/// 列出编辑器的备份集, 最新的在前
+#[tauri::command]
+pub fn list_backups(editor: Editor) -> Vec<BackupSet> {
+    list_backup_sets(&DiskFs, editor)
+}
+
+/// 校验备份集: 重新计算备份文件的摘要并与清单比对, 找出损坏或丢失的文件; 回滚前建议先运行
+#[tauri::command]
+pub fn verify_backup(editor: Editor, backup_id: String) -> Result<BackupIntegrity, PatchError> {
+    let set = list_backup_sets(&DiskFs, editor)
+        .into_iter()
+        .find(|set| set.id == backup_id)
+        .ok_or_else(|| PatchError::BackupNotFound { id: backup_id.clone() })?;
+    let files_dir = backup_sets_dir(editor).join(&set.id).join(BACKUP_SET_FILES_DIR);
+
+    let files = verify_file_hashes(&files_dir, &set.file_hashes);
+    let (unverified, missing): (Vec<String>, Vec<String>) = set
+        .files
+        .iter()
+        .filter(|relative_path| !set.file_hashes.contains_key(*relative_path))
+        .cloned()
+        .partition(|relative_path| resolve_relative(&files_dir, relative_path).is_file());
+    let intact = missing.is_empty() && files.iter().all(|file| file.matches);
+
+    if !intact {
+        let damaged: Vec<&str> = files
+            .iter()
+            .filter(|file| !file.matches)
+            .map(|file| file.path.as_str())
+            .chain(missing.iter().map(String::as_str))
+            .collect();
+        log::warn!("备份集 {} {} 已损坏: {:?}", editor.name(), set.id, damaged);
+    }
+    Ok(BackupIntegrity {
+        id: set.id,
+        intact,
+        files,
+        unverified,
+        missing,
+    })
+}
+
+/// 把备份集中的文件写回对应的安装目录, 写入失败时撤销已写入的文件
+///
+/// 备份集来自另一个编辑器版本时返回 BackupVersionMismatch, `allow_version_mismatch` 为 true 时仍然还原
+#[tauri::command]
+pub fn restore_backup(
+    editor: Editor,
+    backup_id: String,
+    allow_version_mismatch: Option<bool>,
+) -> Result<(), PatchError> {
+    let result = PatchLock::acquire().and_then(|_lock| {
+        restore_backup_set(&DiskFs, editor, &backup_id, allow_version_mismatch.unwrap_or(false))
+    });
+    match &result {
+        Ok(()) => log::info!("回滚到备份集成功: {} {}", editor.name(), backup_id),
+        Err(e) => log::error!("回滚到备份集失败: {} {}: {:?}", editor.name(), backup_id, e),
+    }
+    result
+}
+
+/// 还原备份集 (调用方已持有补丁操作锁)
+fn restore_backup_set(
+    file_system: &impl FileSystem,
+    editor: Editor,
+    backup_id: &str,
+    allow_version_mismatch: bool,
+) -> Result<(), PatchError> {
+    let set = list_backup_sets(file_system, editor)
+        .into_iter()
+        .find(|set| set.id == backup_id)
+        .ok_or_else(|| PatchError::BackupNotFound { id: backup_id.to_string() })?;
+    let install_path = PathBuf::from(&set.install_path);
+    if !file_system.is_dir(&install_path) {
+        return Err(PatchError::PathNotFound);
+    }
+    if !allow_version_mismatch {
+        ensure_same_version(&install_path, set.editor_version.clone())?;
+    }
+
+    let files_dir = backup_sets_dir(editor).join(&set.id).join(BACKUP_SET_FILES_DIR);
+    let mut plan = PatchPlan::with_file_system(file_system);
+    for relative_path in &set.files {
+        let content = file_system
+            .read(&resolve_relative(&files_dir, relative_path))
+            .map_err(|e| PatchError::BackupFailed(format!("读取备份 {} 失败: {}", relative_path, e)))?;
+        plan.write(resolve_relative(&install_path, relative_path), content);
+    }
+    plan.apply()?;
+    Ok(())
+}
+
+fn unix_now_millis() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::filesystem::memory::MemoryFs;
+
+    fn backup_set(id: u128, install_path: &str, size: u64) -> BackupSet {
+        BackupSet {
+            id: id.to_string(),
+            editor: Editor::Antigravity,
+            install_path: install_path.to_string(),
+            created_at: 0,
+            editor_version: None,
+            files: Vec::new(),
+            label: None,
+            file_hashes: BTreeMap::new(),
+            size,
+        }
+    }
+
+    fn ids(sets: Vec<BackupSet>) -> Vec<String> {
+        let mut ids: Vec<String> = sets.into_iter().map(|set| set.id).collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn retention_counts_each_install_separately() {
+        let sets = vec![
+            backup_set(1, "/a", 0),
+            backup_set(2, "/b", 0),
+            backup_set(3, "/a", 0),
+            backup_set(4, "/a", 0),
+        ];
+        assert_eq!(ids(backup_sets_to_prune(sets, 2, u64::MAX)), ["1"]);
+    }
+
+    #[test]
+    fn size_cap_prunes_oldest_but_keeps_the_latest_of_each_install() {
+        let sets = vec![
+            backup_set(1, "/a", 40),
+            backup_set(2, "/b", 40),
+            backup_set(3, "/a", 40),
+            backup_set(4, "/a", 40),
+        ];
+        // 160 字节超过上限 70: 依次删除 /a 最旧的两组, /b 唯一的一组保留
+        assert_eq!(ids(backup_sets_to_prune(sets, 3, 70)), ["1", "3"]);
+    }
+
+    #[test]
+    fn restoring_a_backup_set_writes_back_its_files() {
+        let install_path = Path::new("/fixture/Windsurf");
+        let relative_path = "resources/app/product.json";
+        let product_json = resolve_relative(install_path, relative_path);
+        let file_system = MemoryFs::with_files([(product_json.clone(), "original")]);
+        let id = create_backup_set(&file_system, Editor::Windsurf, install_path, &[relative_path], Some("手动"))
+            .unwrap()
+            .unwrap();
+
+        file_system.write(&product_json, b"changed").unwrap();
+        restore_backup_set(&file_system, Editor::Windsurf, &id, true).unwrap();
+        assert_eq!(file_system.file(&product_json).unwrap(), b"original");
+    }
+}
@@ -0,0 +1,123 @@
+// 按字节编辑文件内容
+// 注入目标不一定是合法的 UTF-8, 转成 String 会把非法字节替换为 U+FFFD 并写回磁盘;
+// 锚点与补丁标记都是 ASCII, 直接在字节上查找和插入, 其余字节原样保留
+// 文件仍整体读入内存 (补丁计划要比较, 摘要与回滚); 注入目标只是几十 KB 的 HTML 入口文件, 不做流式处理
+
+use memchr::memmem;
+
+/// 第一次出现的位置
+pub fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    memmem::find(haystack, needle)
+}
+
+/// 最后一次出现的位置
+pub fn rfind(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    memmem::rfind(haystack, needle)
+}
+
+pub fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    find(haystack, needle).is_some()
+}
+
+/// 删除所有出现的 pattern, 没有出现时不重新分配
+pub fn remove_all(content: &mut Vec<u8>, pattern: &[u8]) {
+    if !contains(content, pattern) {
+        return;
+    }
+    let mut result = Vec::with_capacity(content.len());
+    let mut last = 0;
+    for index in memmem::find_iter(content, pattern) {
+        // find_iter 不返回重叠的匹配
+        result.extend_from_slice(&content[last..index]);
+        last = index + pattern.len();
+    }
+    result.extend_from_slice(&content[last..]);
+    *content = result;
+}
+
+/// 去掉末尾的空格与制表符
+pub fn trim_indent_end(content: &[u8]) -> &[u8] {
+    let end = content
+        .iter()
+        .rposition(|byte| !matches!(byte, b' ' | b'\t'))
+        .map_or(0, |index| index + 1);
+    &content[..end]
+}
+
+/// 内容中占多数的换行符, CRLF 与 LF 一样多或没有换行时为 LF
+pub fn dominant_line_ending(content: &[u8]) -> &'static [u8] {
+    let crlf = memmem::find_iter(content, b"\r\n").count();
+    let lf = memchr::memchr_iter(b'\n', content).count() - crlf;
+    if crlf > lf {
+        b"\r\n"
+    } else {
+        b"\n"
+    }
+}
+
+/// 把 text 中单独的 LF 换成 ending, 已有的 CRLF 保持不变
+pub fn with_line_ending(text: &[u8], ending: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(text.len() + text.len() / 32);
+    extend_with_line_ending(&mut result, text, ending);
+    result
+}
+
+/// 把 text 追加到 out, 单独的 LF 换成 ending
+fn extend_with_line_ending(out: &mut Vec<u8>, text: &[u8], ending: &[u8]) {
+    if ending == b"\n" {
+        out.extend_from_slice(text);
+        return;
+    }
+    for (index, byte) in text.iter().enumerate() {
+        if *byte == b'\n' && (index == 0 || text[index - 1] != b'\r') {
+            out.extend_from_slice(&ending[..ending.len() - 1]);
+        }
+        out.push(*byte);
+    }
+}
+
+/// 在 index 处插入文本, 插入内容的换行符改为与所在文件的主要换行符一致, 避免 CRLF 文件中混入 LF
+/// 文件原有的字节 (包括有意混用的换行符) 不做任何改动
+///
+/// 依次复制锚点前的内容、插入内容与其余内容到一块按最终大小分配的缓冲区,
+/// 大文件只多占用一份结果的内存, 不会因原地插入扩容而翻倍
+pub fn insert_text(content: &mut Vec<u8>, index: usize, text: &str) {
+    let ending = dominant_line_ending(content);
+    let mut result = Vec::with_capacity(content.len() + text.len() + text.len() / 32);
+    result.extend_from_slice(&content[..index]);
+    extend_with_line_ending(&mut result, text.as_bytes(), ending);
+    result.extend_from_slice(&content[index..]);
+    *content = result;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inserted_text_follows_crlf_and_keeps_lone_lf() {
+        let mut content = b"<html>\r\n<head>\r\n<!-- lone -->\n</head>\r\n</html>\r\n".to_vec();
+        let index = find(&content, b"</head>").unwrap();
+
+        insert_text(&mut content, index, "<link>\n\t");
+
+        assert_eq!(
+            content,
+            b"<html>\r\n<head>\r\n<!-- lone -->\n<link>\r\n\t</head>\r\n</html>\r\n".to_vec()
+        );
+    }
+
+    #[test]
+    fn lf_files_are_left_alone() {
+        assert_eq!(dominant_line_ending(b"a\nb\r\nc\n"), b"\n");
+        assert_eq!(with_line_ending(b"a\nb", b"\n"), b"a\nb".to_vec());
+        assert_eq!(with_line_ending(b"a\r\nb\n", b"\r\n"), b"a\r\nb\r\n".to_vec());
+    }
+
+    #[test]
+    fn remove_all_drops_every_match() {
+        let mut content = b"x<tag>y<tag>z".to_vec();
+        remove_all(&mut content, b"<tag>");
+        assert_eq!(content, b"xyz".to_vec());
+    }
+}
@@ -0,0 +1,43 @@
+// 取消长时间操作
+// 前端调用 cancel_operation 后, 检测扫描与补丁写入在下一个检查点停止; 写入中途取消时回滚已写入的文件
+// 取消请求只在有操作进行时生效, 最后一个操作结束时清除, 不会误伤之后开始的操作
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// 正在进行的可取消操作数
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+static CANCEL_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// 可取消操作的作用域, 离开作用域时结束
+pub struct Operation(());
+
+/// 开始一个可取消的操作 (检测扫描, 持有补丁操作锁的安装/卸载)
+pub fn begin() -> Operation {
+    ACTIVE.fetch_add(1, Ordering::SeqCst);
+    Operation(())
+}
+
+impl Drop for Operation {
+    fn drop(&mut self) {
+        if ACTIVE.fetch_sub(1, Ordering::SeqCst) == 1 {
+            CANCEL_REQUESTED.store(false, Ordering::SeqCst);
+        }
+    }
+}
+
+/// 是否已请求取消当前操作
+pub fn is_cancelled() -> bool {
+    CANCEL_REQUESTED.load(Ordering::SeqCst)
+}
+
+/// 请求取消正在进行的检测或补丁操作, 没有操作进行时返回 false
+#[tauri::command]
+pub fn cancel_operation() -> bool {
+    if ACTIVE.load(Ordering::SeqCst) == 0 {
+        return false;
+    }
+    CANCEL_REQUESTED.store(true, Ordering::SeqCst);
+    log::info!("请求取消当前操作");
+    true
+}
@@ -0,0 +1,55 @@
+// macOS 代码签名
+// 修改 .app 内的文件会使原签名失效, Gatekeeper 可能拒绝启动, 需要提示用户重新做 ad-hoc 签名
+
+use serde::Serialize;
+use std::path::Path;
+
+/// 补丁使签名失效的提示
+#[derive(Debug, Serialize)]
+pub struct SignatureWarning {
+    pub message: String,
+    /// 建议执行的重新签名命令
+    pub command: String,
+}
+
+/// 检查应用包是否带有代码签名 (仅 macOS, 其他平台始终为 false)
+#[tauri::command]
+pub fn is_bundle_signed(path: String) -> bool {
+    bundle_signed(Path::new(&path))
+}
+
+/// 已签名的应用包在打补丁后需要重新签名, 未签名或非 macOS 时返回 None
+pub fn signature_warning(bundle_path: &Path) -> Option<SignatureWarning> {
+    if !bundle_signed(bundle_path) {
+        return None;
+    }
+
+    Some(SignatureWarning {
+        message: "补丁修改了应用包内的文件, 原代码签名已失效, 如无法启动请在终端执行以下命令重新签名"
+            .to_string(),
+        command: format!(
+            "codesign --force --deep --sign - \"{}\"",
+            bundle_path.to_string_lossy()
+        ),
+    })
+}
+
+#[cfg(target_os = "macos")]
+fn bundle_signed(bundle_path: &Path) -> bool {
+    use std::process::{Command, Stdio};
+
+    // 未签名的包 codesign -dv 返回非 0 ("code object is not signed at all")
+    Command::new("codesign")
+        .arg("-dv")
+        .arg(bundle_path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+#[cfg(not(target_os = "macos"))]
+fn bundle_signed(_bundle_path: &Path) -> bool {
+    false
+}
@@ -0,0 +1,99 @@
+// 多安装对比
+// 汇总每个检测到的安装的版本, 补丁状态, 补丁内容版本与提示词增强接口地址, 供前端并排比较
+
+use serde::Serialize;
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+
+use super::detect::{detect_in_background, DetectionCache};
+use super::editor::Editor;
+use super::patch::{check_patch_status, is_patch_present, read_panel_config, read_patch_config};
+use super::status::PatchState;
+use super::version::read_app_version;
+
+/// 单个安装的摘要
+#[derive(Debug, Serialize)]
+pub struct InstallSummary {
+    pub editor: Editor,
+    pub path: String,
+    /// 编辑器版本, 读取不到 package.json 时为空
+    pub version: Option<String>,
+    /// 补丁状态; 面板补丁 (Windsurf / Cursor) 只区分是否安装
+    #[serde(rename = "patchState")]
+    pub patch_state: PatchState,
+    /// 安装时的补丁内容版本, 未安装或旧版本安装时为空
+    #[serde(rename = "payloadVersion")]
+    pub payload_version: Option<String>,
+    /// 安装补丁的补丁器版本, 未安装或旧版本安装时为空
+    #[serde(rename = "patcherVersion")]
+    pub patcher_version: Option<String>,
+    /// 补丁配置中的提示词增强接口地址, 未安装或未填写时为空
+    #[serde(rename = "apiBase")]
+    pub api_base: Option<String>,
+}
+
+/// 汇总所有编辑器检测到的安装
+#[tauri::command]
+pub async fn compare_installs(app: AppHandle) -> Vec<InstallSummary> {
+    let cache = app.state::<DetectionCache>();
+    let mut installs = Vec::new();
+    for editor in Editor::ALL {
+        for path in detect_in_background(&cache, editor).await {
+            installs.push((editor, path));
+        }
+    }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        installs
+            .into_iter()
+            .map(|(editor, path)| summarize(editor, path))
+            .collect()
+    })
+    .await
+    .unwrap_or_default()
+}
+
+fn summarize(editor: Editor, path: String) -> InstallSummary {
+    let install_path = Path::new(&path);
+    let version = read_app_version(install_path).map(|version| version.to_string());
+    let non_empty = |value: String| Some(value).filter(|value| !value.is_empty());
+
+    let (patch_state, payload_version, patcher_version, api_base) = match editor {
+        Editor::Antigravity => {
+            let patch_state = check_patch_status(path.clone())
+                .map(|status| status.overall)
+                .unwrap_or(PatchState::NotPatched);
+            match read_patch_config(path.clone()).ok().flatten() {
+                Some(config) => (
+                    patch_state,
+                    non_empty(config.payload_version),
+                    non_empty(config.patcher_version),
+                    non_empty(config.prompt_enhance.api_base),
+                ),
+                None => (patch_state, None, None, None),
+            }
+        }
+        Editor::Windsurf | Editor::Cursor => {
+            let patch_state = if is_patch_present(editor, install_path) {
+                PatchState::Patched
+            } else {
+                PatchState::NotPatched
+            };
+            let api_base = read_panel_config(editor, &path)
+                .ok()
+                .flatten()
+                .and_then(|config| non_empty(config.prompt_enhance.api_base));
+            (patch_state, None, None, api_base)
+        }
+    };
+
+    InstallSummary {
+        editor,
+        path,
+        version,
+        patch_state,
+        payload_version,
+        patcher_version,
+        api_base,
+    }
+}
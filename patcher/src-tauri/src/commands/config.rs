@@ -1,19 +1,197 @@
 // 配置管理模块
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use super::detect::{is_valid_install, path_key, DetectionCache};
+use super::editor::Editor;
+use super::error::PatchError;
+use super::json_edit::strip_bom;
+use super::patch::PromptEnhanceConfig;
+use super::logging::log_result;
+use super::plan::write_replacing;
+use super::prompt::normalize_api_base;
+use super::secret;
+
+/// 当前配置结构版本, 结构变化时递增并在 MIGRATIONS 末尾追加迁移函数
+pub const CONFIG_SCHEMA_VERSION: u32 = 8;
+
+/// 默认配置方案名, 始终存在且不可删除
+const DEFAULT_PROFILE: &str = "default";
+
+/// 字体大小允许范围 (与前端输入框一致)
+const FONT_SIZE_RANGE: std::ops::RangeInclusive<f32> = 10.0..=40.0;
+
+/// 每个编辑器默认保留的备份集数量
+const DEFAULT_BACKUP_RETENTION: u32 = 3;
+
+/// 备份集保留数量允许范围
+const BACKUP_RETENTION_RANGE: std::ops::RangeInclusive<u32> = 1..=20;
+
+/// 配置迁移链, 第 i 项把 v(i + 1) 升级到 v(i + 2)
+const MIGRATIONS: &[fn(&mut serde_json::Map<String, serde_json::Value>)] = &[
+    migrate_v1_to_v2,
+    migrate_v2_to_v3,
+    migrate_v3_to_v4,
+    migrate_v4_to_v5,
+    migrate_v5_to_v6,
+    migrate_v6_to_v7,
+    migrate_v7_to_v8,
+];
+
+/// 配置文件中指向系统钥匙串条目的引用前缀, 如 keychain:profiles/default/apiKey
+const SECRET_REF_PREFIX: &str = "keychain:";
+
+/// 默认界面语言 (与前端一致)
+const DEFAULT_LANGUAGE: &str = "zh";
 
 /// 应用配置
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(default)]
 pub struct AppConfig {
+    /// 配置结构版本, 旧版本写出的配置中没有该字段 (视为 v1)
+    #[serde(rename = "schemaVersion")]
+    pub schema_version: u32,
+
     /// Antigravity 安装路径
     #[serde(rename = "antigravityPath")]
     pub antigravity_path: Option<String>,
+
+    /// 手动指定的 Antigravity 安装路径 (v4 新增), 设置且有效时检测直接返回它
+    #[serde(rename = "manualAntigravityPath")]
+    pub manual_antigravity_path: Option<String>,
     
     /// 功能开关
     pub features: FeatureFlags,
+
+    /// Windsurf 设置 (v2 新增)
+    pub windsurf: WindsurfSettings,
+
+    /// 命名配置方案 (v3 新增), 如公司代理与个人两套提示词增强接口
+    pub profiles: BTreeMap<String, Profile>,
+
+    /// 当前生效的配置方案
+    #[serde(rename = "activeProfile")]
+    pub active_profile: String,
+
+    /// 每个安装保留的备份集数量 (v5 新增), 超出时删除最旧的
+    #[serde(rename = "backupRetention")]
+    pub backup_retention: u32,
+
+    /// 各编辑器的补丁开关 (v6 新增), 关闭时 update_config 写入关闭全部功能的配置, 注入保留
+    #[serde(rename = "editorsEnabled")]
+    pub editors_enabled: EditorsEnabled,
+
+    /// 把 API Key 等敏感字段存入系统钥匙串 (v7 新增), 配置文件中只保留引用
+    #[serde(rename = "encryptSecrets")]
+    pub encrypt_secrets: bool,
+
+    /// 界面语言代码 (v8 新增), 如 zh, en; 命令返回的说明按它生成, 不支持的语言使用英文
+    pub language: String,
+}
+
+/// 默认配置, 首次启动, 读取失败与 reset_config 共用
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            schema_version: CONFIG_SCHEMA_VERSION,
+            antigravity_path: None,
+            manual_antigravity_path: None,
+            features: FeatureFlags::default(),
+            windsurf: WindsurfSettings::default(),
+            profiles: BTreeMap::from([(DEFAULT_PROFILE.to_string(), Profile::default())]),
+            active_profile: DEFAULT_PROFILE.to_string(),
+            backup_retention: DEFAULT_BACKUP_RETENTION,
+            editors_enabled: EditorsEnabled::default(),
+            encrypt_secrets: false,
+            language: DEFAULT_LANGUAGE.to_string(),
+        }
+    }
+}
+
+/// 配置方案
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Profile {
+    /// 提示词增强设置, update_config 时覆盖前端传入的设置; 为空时不覆盖
+    #[serde(rename = "promptEnhance")]
+    pub prompt_enhance: Option<PromptEnhanceConfig>,
+}
+
+/// 配置方案列表
+#[derive(Debug, Serialize)]
+pub struct ProfileList {
+    pub names: Vec<String>,
+    pub active: String,
+}
+
+/// Windsurf 设置
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct WindsurfSettings {
+    /// Windsurf 安装路径
+    pub path: Option<String>,
+    /// 手动指定的 Windsurf 安装路径 (v4 新增)
+    #[serde(rename = "manualPath")]
+    pub manual_path: Option<String>,
+}
+
+/// 各编辑器的补丁开关
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EditorsEnabled {
+    pub antigravity: bool,
+    pub windsurf: bool,
+    pub cursor: bool,
+}
+
+impl Default for EditorsEnabled {
+    fn default() -> Self {
+        Self {
+            antigravity: true,
+            windsurf: true,
+            cursor: true,
+        }
+    }
+}
+
+impl EditorsEnabled {
+    pub fn get(&self, editor: Editor) -> bool {
+        match editor {
+            Editor::Antigravity => self.antigravity,
+            Editor::Windsurf => self.windsurf,
+            Editor::Cursor => self.cursor,
+        }
+    }
+}
+
+/// get_config 的返回值, 版本不同时前端可提示配置已升级
+#[derive(Debug, Serialize)]
+pub struct LoadedConfig {
+    pub config: AppConfig,
+    /// 读取到的配置版本
+    #[serde(rename = "previousVersion")]
+    pub previous_version: u32,
+    /// 迁移后的配置版本
+    #[serde(rename = "currentVersion")]
+    pub current_version: u32,
+    /// 要求加密敏感字段但系统钥匙串不可用 (已退回明文保存), 或无法从钥匙串读取时的提示
+    #[serde(rename = "secretWarning")]
+    pub secret_warning: Option<String>,
+}
+
+impl Default for LoadedConfig {
+    fn default() -> Self {
+        Self {
+            config: AppConfig::default(),
+            previous_version: CONFIG_SCHEMA_VERSION,
+            current_version: CONFIG_SCHEMA_VERSION,
+            secret_warning: None,
+        }
+    }
 }
 
 /// 功能开关
@@ -35,46 +213,766 @@ impl Default for FeatureFlags {
     }
 }
 
-/// 获取配置文件路径
-fn get_config_path() -> PathBuf {
+/// 配置命令错误
+/// 序列化为 { kind, detail }, 与补丁命令的错误格式一致
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", content = "detail", rename_all = "camelCase")]
+pub enum ConfigError {
+    /// 配置未通过校验, 列出所有不合法的字段
+    Invalid(Vec<ConfigFieldError>),
+    /// 导入内容不是合法的配置 JSON
+    Malformed(String),
+    /// 配置方案不存在
+    ProfileNotFound(String),
+    /// 默认配置方案不可删除
+    DefaultProfileProtected,
+    /// 文件读写错误
+    Io(String),
+}
+
+impl From<String> for ConfigError {
+    fn from(message: String) -> Self {
+        ConfigError::Io(message)
+    }
+}
+
+/// 单个字段的校验错误
+#[derive(Debug, Serialize)]
+pub struct ConfigFieldError {
+    /// 字段路径 (与配置文件中的键名一致), 如 features.fontSize
+    pub field: String,
+    pub message: String,
+}
+
+impl AppConfig {
+    /// 规范化各配置方案中的接口地址, 返回无法规范化的字段
+    pub fn normalize(&mut self) -> Vec<ConfigFieldError> {
+        let mut errors = Vec::new();
+        for (name, profile) in &mut self.profiles {
+            let Some(prompt_enhance) = profile.prompt_enhance.as_mut() else {
+                continue;
+            };
+            match normalize_api_base(&prompt_enhance.api_base) {
+                Ok(api_base) => prompt_enhance.api_base = api_base,
+                Err(message) => errors.push(ConfigFieldError {
+                    field: format!("profiles.{}.promptEnhance.apiBase", name),
+                    message,
+                }),
+            }
+        }
+        errors
+    }
+
+    /// 校验配置, 返回所有不合法的字段
+    pub fn validate(&self) -> Vec<ConfigFieldError> {
+        let mut errors = Vec::new();
+        let mut invalid = |field: &str, message: String| {
+            errors.push(ConfigFieldError {
+                field: field.to_string(),
+                message,
+            })
+        };
+
+        if !(1..=CONFIG_SCHEMA_VERSION).contains(&self.schema_version) {
+            invalid(
+                "schemaVersion",
+                format!("不支持的配置版本 {}", self.schema_version),
+            );
+        }
+
+        let font_size = self.features.font_size;
+        if !FONT_SIZE_RANGE.contains(&font_size) {
+            invalid(
+                "features.fontSize",
+                format!(
+                    "字体大小需在 {} - {} 之间",
+                    FONT_SIZE_RANGE.start(),
+                    FONT_SIZE_RANGE.end()
+                ),
+            );
+        }
+
+        if !self.profiles.contains_key(DEFAULT_PROFILE) {
+            invalid("profiles", format!("缺少默认配置方案 {}", DEFAULT_PROFILE));
+        }
+        if !self.profiles.contains_key(&self.active_profile) {
+            invalid(
+                "activeProfile",
+                format!("配置方案不存在: {}", self.active_profile),
+            );
+        }
+
+        if !BACKUP_RETENTION_RANGE.contains(&self.backup_retention) {
+            invalid(
+                "backupRetention",
+                format!(
+                    "备份保留数量需在 {} - {} 之间",
+                    BACKUP_RETENTION_RANGE.start(),
+                    BACKUP_RETENTION_RANGE.end()
+                ),
+            );
+        }
+
+        for (field, path) in [
+            ("antigravityPath", &self.antigravity_path),
+            ("manualAntigravityPath", &self.manual_antigravity_path),
+            ("windsurf.path", &self.windsurf.path),
+            ("windsurf.manualPath", &self.windsurf.manual_path),
+        ] {
+            if let Some(message) = path.as_deref().and_then(invalid_install_path) {
+                invalid(field, message);
+            }
+        }
+
+        errors
+    }
+}
+
+/// 安装路径可以不填, 填写时必须是绝对路径
+fn invalid_install_path(path: &str) -> Option<String> {
+    if path.trim().is_empty() {
+        Some("路径不能为空".to_string())
+    } else if !Path::new(path).is_absolute() {
+        Some(format!("需要绝对路径: {}", path))
+    } else {
+        None
+    }
+}
+
+/// 应用数据目录名
+const APP_DIR_NAME: &str = "Antigravity-Power-Pro";
+
+/// 配置文件名
+const CONFIG_FILE: &str = "config.json";
+
+/// 获取应用数据目录 (配置, 备份等均存放于此)
+///
+/// 遵循各平台约定: Windows 为 %APPDATA%, macOS 为 ~/Library/Application Support,
+/// Linux 为 $XDG_CONFIG_HOME (未设置时 ~/.config)
+pub fn app_data_dir() -> PathBuf {
     dirs::config_dir()
         .unwrap_or_else(|| PathBuf::from("."))
-        .join("Antigravity-Power-Pro")
-        .join("config.json")
+        .join(APP_DIR_NAME)
+}
+
+/// 获取配置文件路径, 所有读写配置的地方都经由这里
+pub fn config_path() -> PathBuf {
+    app_data_dir().join(CONFIG_FILE)
+}
+
+/// 检查配置文件能否读取并解析; 文件不存在 (首次启动) 视为正常
+pub fn check_config_readable() -> Result<(), String> {
+    let config_path = config_path();
+    if !config_path.exists() {
+        return Ok(());
+    }
+
+    let content = read_config_file(&config_path).map_err(|e| format!("读取配置失败: {}", e))?;
+    let value: serde_json::Value =
+        serde_json::from_str(&content).map_err(|e| format!("解析配置失败: {}", e))?;
+    if !value.is_object() {
+        return Err("配置必须是 JSON 对象".to_string());
+    }
+    Ok(())
 }
 
 /// 读取配置, 失败时回退到默认值
+/// 旧版本配置会逐级迁移到当前结构并写回文件
 #[tauri::command]
-pub fn get_config() -> AppConfig {
-    let config_path = get_config_path();
-    
-    if config_path.exists() {
-        if let Ok(content) = fs::read_to_string(&config_path) {
-            if let Ok(config) = serde_json::from_str(&content) {
-                return config;
-            }
+pub fn get_config() -> LoadedConfig {
+    load_config(&config_path())
+}
+
+/// 读取指定位置的配置 (get_config 的实现)
+fn load_config(config_path: &Path) -> LoadedConfig {
+    let Some((mut raw, previous_version)) = read_migrated_config(config_path) else {
+        return LoadedConfig::default();
+    };
+
+    // 先于读取钥匙串统计明文, 从钥匙串读出的密钥不算明文保存
+    let plaintext = plaintext_secret_profiles(&mut raw);
+    let unresolved = resolve_secrets(&mut raw);
+    // 解析失败时不写回, 避免用默认值覆盖用户的配置文件
+    let Some(config) = parse_config(config_path, raw) else {
+        return LoadedConfig::default();
+    };
+
+    // 迁移结果写回, 失败时下次启动会重新迁移
+    if previous_version < config.schema_version {
+        log::info!("配置从 v{} 迁移到 v{}", previous_version, config.schema_version);
+        if let Err(e) = write_config_at(config_path, &config) {
+            log::warn!("写回迁移后的配置失败: {:?}", e);
+        }
+    }
+
+    let secret_warning = if !unresolved.is_empty() {
+        Some(format!(
+            "无法从系统钥匙串读取以下配置方案的 API Key, 请重新填写: {}",
+            unresolved.join(", ")
+        ))
+    } else if config.encrypt_secrets && !plaintext.is_empty() {
+        Some(format!(
+            "系统钥匙串不可用, 以下配置方案的 API Key 仍以明文保存: {}",
+            plaintext.join(", ")
+        ))
+    } else {
+        None
+    };
+
+    LoadedConfig {
+        current_version: config.schema_version,
+        previous_version,
+        config,
+        secret_warning,
+    }
+}
+
+/// 读取配置中的普通字段, 不访问钥匙串 (API Key 保留引用), 迁移结果也不写回
+/// 供检测, 状态检查, 备份等频繁读取单个字段的地方使用, 失败时回退到默认值
+pub fn read_config() -> AppConfig {
+    let config_path = config_path();
+    read_migrated_config(&config_path)
+        .and_then(|(raw, _)| parse_config(&config_path, raw))
+        .unwrap_or_default()
+}
+
+/// 读取配置文件并迁移到当前结构, 返回原始配置与迁移前的版本; 文件不存在或不是 JSON 对象时为 None
+fn read_migrated_config(
+    config_path: &Path,
+) -> Option<(serde_json::Map<String, serde_json::Value>, u32)> {
+    let content = read_config_file(config_path).ok()?;
+    let serde_json::Value::Object(mut raw) = serde_json::from_str(&content).ok()? else {
+        return None;
+    };
+    let previous_version = migrate_config(&mut raw);
+    Some((raw, previous_version))
+}
+
+/// 把迁移后的原始配置解析为 AppConfig 并补齐默认方案
+fn parse_config(
+    config_path: &Path,
+    raw: serde_json::Map<String, serde_json::Value>,
+) -> Option<AppConfig> {
+    match serde_json::from_value::<AppConfig>(serde_json::Value::Object(raw)) {
+        Ok(mut config) => {
+            ensure_default_profile(&mut config);
+            Some(config)
+        }
+        Err(e) => {
+            log::warn!("解析配置失败, 使用默认配置: {:?}: {}", config_path, e);
+            None
         }
     }
-    
-    AppConfig::default()
 }
 
 /// 保存配置
+/// 校验失败时返回全部错误字段, 不改动已有的配置文件
 #[tauri::command]
-pub fn save_config(config: AppConfig) -> Result<(), String> {
-    let config_path = get_config_path();
-    
+pub fn save_config(mut config: AppConfig) -> Result<(), ConfigError> {
+    let mut errors = config.normalize();
+    errors.extend(config.validate());
+    if !errors.is_empty() {
+        log::warn!("配置校验失败: {:?}", errors);
+        return Err(ConfigError::Invalid(errors));
+    }
+
+    let result = write_config(&config).map_err(ConfigError::from);
+    log_result("保存配置", &config_path().to_string_lossy(), &result);
+    result
+}
+
+/// 导出配置为 JSON, 便于重装或在多台机器间共享
+/// 安装路径与本机相关, 导出时去掉
+#[tauri::command]
+pub fn export_config() -> Result<String, ConfigError> {
+    let mut config = get_config().config;
+    config.antigravity_path = None;
+    config.manual_antigravity_path = None;
+    config.windsurf.path = None;
+    config.windsurf.manual_path = None;
+
+    let content = serde_json::to_string_pretty(&config)
+        .map_err(|e| format!("序列化配置失败: {}", e))?;
+    Ok(content)
+}
+
+/// 导入 export_config 导出的配置, 校验后保存
+/// 导入内容没有安装路径时保留本机当前的路径
+#[tauri::command]
+pub fn import_config(json: String) -> Result<(), ConfigError> {
+    let raw = serde_json::from_str::<serde_json::Value>(strip_bom(&json))
+        .map_err(|e| ConfigError::Malformed(format!("解析配置失败: {}", e)))?;
+    let serde_json::Value::Object(mut raw) = raw else {
+        return Err(ConfigError::Malformed("配置必须是 JSON 对象".to_string()));
+    };
+
+    // 旧版本导出的配置同样先迁移
+    migrate_config(&mut raw);
+    let mut config: AppConfig = serde_json::from_value(serde_json::Value::Object(raw))
+        .map_err(|e| ConfigError::Malformed(format!("解析配置失败: {}", e)))?;
+
+    let current = get_config().config;
+    if config.antigravity_path.is_none() {
+        config.antigravity_path = current.antigravity_path;
+    }
+    if config.windsurf.path.is_none() {
+        config.windsurf.path = current.windsurf.path;
+    }
+    if config.manual_antigravity_path.is_none() {
+        config.manual_antigravity_path = current.manual_antigravity_path;
+    }
+    if config.windsurf.manual_path.is_none() {
+        config.windsurf.manual_path = current.windsurf.manual_path;
+    }
+
+    save_config(config)
+}
+
+/// 列出所有配置方案
+#[tauri::command]
+pub fn list_profiles() -> ProfileList {
+    let config = get_config().config;
+    ProfileList {
+        names: config.profiles.keys().cloned().collect(),
+        active: config.active_profile,
+    }
+}
+
+/// 保存配置方案 (已存在时覆盖), 并设为当前方案
+#[tauri::command]
+pub fn save_profile(
+    name: String,
+    prompt_enhance: Option<PromptEnhanceConfig>,
+) -> Result<(), ConfigError> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(ConfigError::Invalid(vec![ConfigFieldError {
+            field: "name".to_string(),
+            message: "配置方案名不能为空".to_string(),
+        }]));
+    }
+
+    let mut config = get_config().config;
+    config.profiles.insert(name.clone(), Profile { prompt_enhance });
+    config.active_profile = name;
+    save_config(config)
+}
+
+/// 切换到指定配置方案, 返回该方案
+#[tauri::command]
+pub fn load_profile(name: String) -> Result<Profile, ConfigError> {
+    let mut config = get_config().config;
+    let profile = config
+        .profiles
+        .get(&name)
+        .cloned()
+        .ok_or_else(|| ConfigError::ProfileNotFound(name.clone()))?;
+
+    config.active_profile = name;
+    save_config(config)?;
+    Ok(profile)
+}
+
+/// 删除配置方案, 删除当前方案时切回默认方案
+#[tauri::command]
+pub fn delete_profile(name: String) -> Result<(), ConfigError> {
+    if name == DEFAULT_PROFILE {
+        return Err(ConfigError::DefaultProfileProtected);
+    }
+
+    let mut config = get_config().config;
+    if config.profiles.remove(&name).is_none() {
+        return Err(ConfigError::ProfileNotFound(name));
+    }
+    if config.active_profile == name {
+        config.active_profile = DEFAULT_PROFILE.to_string();
+    }
+    save_config(config)
+}
+
+/// 手动指定安装路径, 保存前确认是编辑器的有效安装; 传入 None 时清除
+/// 目前只有 Antigravity 与 Windsurf 支持
+#[tauri::command]
+pub fn set_manual_path(
+    cache: State<'_, DetectionCache>,
+    editor: Editor,
+    path: Option<String>,
+) -> Result<(), ConfigError> {
+    let field = match editor {
+        Editor::Antigravity => "manualAntigravityPath",
+        Editor::Windsurf => "windsurf.manualPath",
+        Editor::Cursor => {
+            return Err(ConfigError::Invalid(vec![ConfigFieldError {
+                field: "editor".to_string(),
+                message: format!("{} 不支持手动指定路径", editor.name()),
+            }]))
+        }
+    };
+
+    let path = path.map(|path| path.trim().to_string());
+    if let Some(path) = &path {
+        if !is_valid_install(editor, Path::new(path)) {
+            return Err(ConfigError::Invalid(vec![ConfigFieldError {
+                field: field.to_string(),
+                message: format!("不是有效的 {} 安装目录: {}", editor.name(), path),
+            }]));
+        }
+    }
+
+    let mut config = get_config().config;
+    match editor {
+        Editor::Antigravity => config.manual_antigravity_path = path,
+        _ => config.windsurf.manual_path = path,
+    }
+    save_config(config)?;
+    // 手动路径排在检测结果最前, 缓存的结果已过时
+    cache.invalidate();
+    Ok(())
+}
+
+/// 配置中手动指定的安装路径
+pub fn manual_install_path(editor: Editor) -> Option<String> {
+    let config = read_config();
+    match editor {
+        Editor::Antigravity => config.manual_antigravity_path,
+        Editor::Windsurf => config.windsurf.manual_path,
+        Editor::Cursor => None,
+    }
+}
+
+/// 配置中该编辑器的补丁是否启用
+pub fn editor_enabled(editor: Editor) -> bool {
+    read_config().editors_enabled.get(editor)
+}
+
+/// 清除配置中指向该安装目录的路径 (检测到的与手动指定的), 返回清除的字段
+pub fn forget_install_path(
+    cache: &DetectionCache,
+    editor: Editor,
+    path: &str,
+) -> Result<Vec<String>, String> {
+    let mut config = get_config().config;
+    let key = path_key(path);
+    let fields = match editor {
+        Editor::Antigravity => vec![
+            ("antigravityPath", &mut config.antigravity_path),
+            ("manualAntigravityPath", &mut config.manual_antigravity_path),
+        ],
+        Editor::Windsurf => vec![
+            ("windsurf.path", &mut config.windsurf.path),
+            ("windsurf.manualPath", &mut config.windsurf.manual_path),
+        ],
+        Editor::Cursor => Vec::new(),
+    };
+
+    let mut cleared = Vec::new();
+    for (name, field) in fields {
+        if field.as_deref().is_some_and(|value| path_key(value) == key) {
+            *field = None;
+            cleared.push(name.to_string());
+        }
+    }
+    if !cleared.is_empty() {
+        write_config(&config)?;
+        cache.invalidate();
+    }
+    Ok(cleared)
+}
+
+/// 当前配置方案的提示词增强设置, 只从钥匙串读取该方案的 API Key
+pub fn active_prompt_enhance() -> Option<PromptEnhanceConfig> {
+    let mut config = read_config();
+    let mut prompt_enhance = config.profiles.remove(&config.active_profile)?.prompt_enhance?;
+    if let Some(account) = prompt_enhance.api_key.strip_prefix(SECRET_REF_PREFIX) {
+        // 读取失败时不把引用当作密钥写入编辑器
+        prompt_enhance.api_key = match secret::load(account) {
+            Ok(secret) => secret.unwrap_or_default(),
+            Err(e) => {
+                log::warn!("{}", e);
+                String::new()
+            }
+        };
+    }
+    Some(prompt_enhance)
+}
+
+/// 补齐默认方案, 当前方案不存在时回退到默认方案 (配置文件被手动修改过)
+fn ensure_default_profile(config: &mut AppConfig) {
+    config.profiles.entry(DEFAULT_PROFILE.to_string()).or_default();
+    if !config.profiles.contains_key(&config.active_profile) {
+        config.active_profile = DEFAULT_PROFILE.to_string();
+    }
+}
+
+/// 恢复默认配置, 原配置文件备份为 config.bak
+#[tauri::command]
+pub fn reset_config() -> Result<AppConfig, PatchError> {
+    let config_path = config_path();
+    if config_path.exists() {
+        fs::copy(&config_path, config_path.with_extension("bak"))
+            .map_err(|e| PatchError::io(&config_path, "备份配置失败", e))?;
+    }
+
+    // config.bak 仍引用钥匙串中的密钥, 不清理旧条目, 以便从备份恢复
+    let config = AppConfig::default();
+    write_config_file(&config_path, &config).map_err(PatchError::Io)?;
+    Ok(config)
+}
+
+/// 用系统默认程序打开补丁器配置文件以便手动编辑, 文件不存在时先写入默认配置, 返回文件路径
+#[tauri::command]
+pub fn open_config_file() -> Result<String, String> {
+    let config_path = config_path();
+    if !config_path.exists() {
+        write_config(&AppConfig::default())?;
+    }
+    tauri_plugin_opener::open_path(&config_path, None::<&str>)
+        .map_err(|e| format!("打开配置文件失败: {}", e))?;
+    Ok(config_path.to_string_lossy().to_string())
+}
+
+/// 读取配置文件文本, 去掉 UTF-8 BOM
+fn read_config_file(path: &Path) -> std::io::Result<String> {
+    fs::read_to_string(path).map(|content| strip_bom(&content).to_string())
+}
+
+/// 写入配置文件, 并删除新配置不再引用的钥匙串条目
+fn write_config(config: &AppConfig) -> Result<(), String> {
+    write_config_at(&config_path(), config)
+}
+
+/// 写入指定位置的配置 (write_config 的实现)
+fn write_config_at(config_path: &Path, config: &AppConfig) -> Result<(), String> {
+    let previous_refs = stored_secret_refs(config_path);
+    let mut raw = write_config_file(config_path, config)?;
+    remove_stale_secrets(&mut raw, previous_refs);
+    Ok(())
+}
+
+/// 写入配置文件, 不带 BOM, 返回写入的内容
+fn write_config_file(
+    config_path: &Path,
+    config: &AppConfig,
+) -> Result<serde_json::Map<String, serde_json::Value>, String> {
     // 确保配置目录存在
     if let Some(parent) = config_path.parent() {
         fs::create_dir_all(parent)
             .map_err(|e| format!("创建配置目录失败: {}", e))?;
     }
     
-    let content = serde_json::to_string_pretty(&config)
+    let value = serde_json::to_value(config).map_err(|e| format!("序列化配置失败: {}", e))?;
+    let serde_json::Value::Object(mut raw) = value else {
+        return Err("序列化配置失败: 不是 JSON 对象".to_string());
+    };
+    if config.encrypt_secrets {
+        protect_secrets(&mut raw);
+    }
+    let content = serde_json::to_string_pretty(&raw)
         .map_err(|e| format!("序列化配置失败: {}", e))?;
     
-    fs::write(&config_path, content)
+    // 原子替换, 写入中途崩溃时保留旧配置
+    write_replacing(config_path, content.as_bytes())
         .map_err(|e| format!("保存配置失败: {}", e))?;
-    
-    Ok(())
+    Ok(raw)
+}
+
+/// 配置方案 API Key 在钥匙串中的条目名
+fn secret_account(profile: &str) -> String {
+    format!("profiles/{}/apiKey", profile)
+}
+
+/// 各配置方案的 API Key 字段 (方案名, 字段值)
+fn api_key_fields(
+    raw: &mut serde_json::Map<String, serde_json::Value>,
+) -> Vec<(String, &mut serde_json::Value)> {
+    let Some(profiles) = raw.get_mut("profiles").and_then(|value| value.as_object_mut()) else {
+        return Vec::new();
+    };
+    profiles
+        .iter_mut()
+        .filter_map(|(name, profile)| {
+            let api_key = profile.get_mut("promptEnhance")?.get_mut("apiKey")?;
+            Some((name.clone(), api_key))
+        })
+        .collect()
+}
+
+/// 把钥匙串引用替换为密钥, 返回读取失败的配置方案; 这些字段保留引用, 写回时不会丢失
+fn resolve_secrets(raw: &mut serde_json::Map<String, serde_json::Value>) -> Vec<String> {
+    let mut unresolved = Vec::new();
+    for (name, api_key) in api_key_fields(raw) {
+        let Some(account) = api_key.as_str().and_then(|key| key.strip_prefix(SECRET_REF_PREFIX))
+        else {
+            continue;
+        };
+        match secret::load(account) {
+            Ok(Some(secret)) => *api_key = secret.into(),
+            result => {
+                if let Err(e) = result {
+                    log::warn!("{}", e);
+                }
+                unresolved.push(name);
+            }
+        }
+    }
+    unresolved
+}
+
+/// API Key 以明文保存的配置方案
+fn plaintext_secret_profiles(raw: &mut serde_json::Map<String, serde_json::Value>) -> Vec<String> {
+    api_key_fields(raw)
+        .into_iter()
+        .filter(|(_, api_key)| {
+            api_key
+                .as_str()
+                .is_some_and(|key| !key.is_empty() && !key.starts_with(SECRET_REF_PREFIX))
+        })
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// 把 API Key 存入钥匙串并替换为引用; 钥匙串不可用时保留明文
+fn protect_secrets(raw: &mut serde_json::Map<String, serde_json::Value>) {
+    for (name, api_key) in api_key_fields(raw) {
+        // 读取失败而保留下来的引用原样写回
+        let Some(key) = api_key
+            .as_str()
+            .filter(|key| !key.is_empty() && !key.starts_with(SECRET_REF_PREFIX))
+        else {
+            continue;
+        };
+        let account = secret_account(&name);
+        match secret::store(&account, key) {
+            Ok(()) => *api_key = format!("{}{}", SECRET_REF_PREFIX, account).into(),
+            Err(e) => log::warn!("{}, 配置方案 {} 的 API Key 以明文保存", e, name),
+        }
+    }
+}
+
+/// 配置文件中现有的钥匙串引用 (方案名, 条目名)
+fn stored_secret_refs(config_path: &Path) -> Vec<(String, String)> {
+    let raw = read_config_file(config_path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok());
+    let Some(serde_json::Value::Object(mut raw)) = raw else {
+        return Vec::new();
+    };
+    api_key_fields(&mut raw)
+        .into_iter()
+        .filter_map(|(name, api_key)| {
+            let account = api_key.as_str()?.strip_prefix(SECRET_REF_PREFIX)?.to_string();
+            Some((name, account))
+        })
+        .collect()
+}
+
+/// 删除不再被引用的钥匙串条目: 配置方案已删除, 或密钥已改为明文保存
+/// 字段为空时可能是读取钥匙串失败, 保留条目以免丢失密钥
+fn remove_stale_secrets(
+    raw: &mut serde_json::Map<String, serde_json::Value>,
+    previous_refs: Vec<(String, String)>,
+) {
+    let fields: BTreeMap<String, String> = api_key_fields(raw)
+        .into_iter()
+        .map(|(name, api_key)| (name, api_key.as_str().unwrap_or_default().to_string()))
+        .collect();
+    for (name, account) in previous_refs {
+        let stale = match fields.get(&name) {
+            None => true,
+            Some(key) => !key.is_empty() && !key.starts_with(SECRET_REF_PREFIX),
+        };
+        if stale {
+            if let Err(e) = secret::delete(&account) {
+                log::warn!("{}", e);
+            }
+        }
+    }
+}
+
+/// 把原始配置升级到当前版本, 返回迁移前的版本
+/// 比当前版本更新的配置 (由新版补丁器写出) 原样保留
+fn migrate_config(raw: &mut serde_json::Map<String, serde_json::Value>) -> u32 {
+    let version = raw
+        .get("schemaVersion")
+        .and_then(|value| value.as_u64())
+        .map(|value| value as u32)
+        .unwrap_or(1);
+
+    for migration in MIGRATIONS.iter().skip(version.saturating_sub(1) as usize) {
+        migration(raw);
+    }
+    if version < CONFIG_SCHEMA_VERSION {
+        raw.insert("schemaVersion".to_string(), CONFIG_SCHEMA_VERSION.into());
+    }
+
+    version
+}
+
+/// v1 -> v2: 新增 windsurf 设置
+fn migrate_v1_to_v2(raw: &mut serde_json::Map<String, serde_json::Value>) {
+    raw.entry("windsurf")
+        .or_insert_with(|| serde_json::json!({ "path": null }));
+}
+
+/// v2 -> v3: 新增配置方案, 原有设置归入默认方案
+fn migrate_v2_to_v3(raw: &mut serde_json::Map<String, serde_json::Value>) {
+    raw.entry("profiles")
+        .or_insert_with(|| serde_json::json!({ DEFAULT_PROFILE: {} }));
+    raw.entry("activeProfile")
+        .or_insert_with(|| DEFAULT_PROFILE.into());
+}
+
+/// v3 -> v4: 新增手动指定的安装路径
+fn migrate_v3_to_v4(raw: &mut serde_json::Map<String, serde_json::Value>) {
+    raw.entry("manualAntigravityPath")
+        .or_insert(serde_json::Value::Null);
+    if let Some(windsurf) = raw.get_mut("windsurf").and_then(|value| value.as_object_mut()) {
+        windsurf
+            .entry("manualPath")
+            .or_insert(serde_json::Value::Null);
+    }
+}
+
+/// v4 -> v5: 新增备份集保留数量
+fn migrate_v4_to_v5(raw: &mut serde_json::Map<String, serde_json::Value>) {
+    raw.entry("backupRetention")
+        .or_insert_with(|| DEFAULT_BACKUP_RETENTION.into());
+}
+
+/// v5 -> v6: 新增各编辑器的补丁开关, 默认全部启用
+fn migrate_v5_to_v6(raw: &mut serde_json::Map<String, serde_json::Value>) {
+    raw.entry("editorsEnabled").or_insert_with(|| {
+        serde_json::to_value(EditorsEnabled::default()).unwrap_or(serde_json::Value::Null)
+    });
+}
+
+/// v6 -> v7: 新增敏感字段加密开关, 默认关闭 (沿用明文保存)
+fn migrate_v6_to_v7(raw: &mut serde_json::Map<String, serde_json::Value>) {
+    raw.entry("encryptSecrets").or_insert(false.into());
+}
+
+/// v7 -> v8: 新增界面语言, 默认中文 (与之前的提示一致)
+fn migrate_v7_to_v8(raw: &mut serde_json::Map<String, serde_json::Value>) {
+    raw.entry("language").or_insert_with(|| DEFAULT_LANGUAGE.into());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bom_prefixed_config_is_read_and_saved_without_bom() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join(CONFIG_FILE);
+        fs::write(&config_path, "\u{feff}{\"schemaVersion\":8,\"language\":\"en\"}").unwrap();
+
+        let loaded = load_config(&config_path);
+        assert_eq!(loaded.previous_version, 8);
+        assert_eq!(loaded.config.language, "en");
+
+        write_config_at(&config_path, &loaded.config).unwrap();
+        let saved = fs::read(&config_path).unwrap();
+        assert!(!saved.starts_with("\u{feff}".as_bytes()));
+        assert_eq!(load_config(&config_path).config.language, "en");
+    }
 }
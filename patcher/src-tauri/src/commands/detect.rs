@@ -1,272 +1,1516 @@
 // 路径检测模块
 // Windows: 注册表查询 + 常见路径扫描
-// macOS: 标准路径探测, 未命中时返回 None
+// macOS: Applications 目录 (含两层子文件夹) + Spotlight
+// Linux: 环境变量 + 常见安装路径 + AppImage 挂载点 + Flatpak / Snap
 
-use std::path::PathBuf;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+use super::cancel::{self, is_cancelled};
+use super::config::manual_install_path;
+use super::editor::Editor;
+use super::json_edit::strip_bom;
+use super::network::{is_network_path, is_reachable};
+use super::version::read_app_version;
 
 // 平台特定实现直接内联, 避免子模块路径问题
 
+/// 注册表扫描默认超时, 可通过 ANTIGRAVITY_REGISTRY_TIMEOUT_MS 调整
+#[cfg(target_os = "windows")]
+const DEFAULT_REGISTRY_TIMEOUT_MS: u64 = 2000;
+
+// 检测命令均为 async: 注册表与磁盘扫描在后台线程执行, 不阻塞 IPC 线程
+// async 命令的引用参数要求返回 Result, 因此通过 AppHandle 取检测缓存 (app.state), 同步命令直接使用 State
+
 /// 检测 Antigravity 安装路径
 /// 返回找到的第一个有效路径, 或 None
 #[tauri::command]
-pub fn detect_antigravity_path() -> Option<String> {
+pub async fn detect_antigravity_path(app: AppHandle) -> Option<String> {
+    first_antigravity_path(&app.state::<DetectionCache>()).await
+}
+
+/// 手动指定的路径或检测到的第一个 Antigravity 安装
+pub async fn first_antigravity_path(cache: &DetectionCache) -> Option<String> {
+    if let Some(path) = valid_manual_path(Editor::Antigravity) {
+        return Some(path);
+    }
+    detect_in_background(cache, Editor::Antigravity).await.into_iter().next()
+}
+
+/// 检测所有 Antigravity 安装路径 (如稳定版与 Nightly 并存)
+/// 按优先级排序, 同一安装的不同写法只保留第一个
+#[tauri::command]
+pub async fn detect_all_antigravity_paths(app: AppHandle) -> Vec<String> {
+    detect_in_background(&app.state::<DetectionCache>(), Editor::Antigravity).await
+}
+
+/// 检测到的安装
+#[derive(Debug, Serialize)]
+pub struct DetectedInstall {
+    /// 解析符号链接后的真实路径, 补丁实际写入的位置
+    pub path: String,
+    /// 安装位于只读文件系统 (如 Snap 的 squashfs), 无法打补丁
+    #[serde(rename = "readOnly")]
+    pub read_only: bool,
+    /// 检测到的路径经过符号链接或 junction, 文件会写到链接指向的位置
+    #[serde(rename = "isLink")]
+    pub is_link: bool,
+    /// 经过链接时检测到的原路径
+    #[serde(rename = "linkPath")]
+    pub link_path: Option<String>,
+}
+
+/// 检测所有 Antigravity 安装路径, 并标注无法打补丁的只读安装与经过符号链接的安装
+///
+/// `follow_symlinks` 为 false 时排除经过符号链接或 junction 找到的安装, 默认 true
+#[tauri::command]
+pub async fn detect_antigravity_installs(follow_symlinks: Option<bool>) -> Vec<DetectedInstall> {
+    let follow_symlinks = follow_symlinks.unwrap_or(true);
+    let found = tauri::async_runtime::spawn_blocking(|| {
+        dedup_with_original(detect_editor_candidates(Editor::Antigravity))
+    })
+    .await
+    .unwrap_or_default();
+
+    found
+        .into_iter()
+        .filter_map(|(original, path)| {
+            let is_link = has_link_component(Path::new(&original));
+            if is_link && !follow_symlinks {
+                log::info!("跳过经过符号链接的安装: {} -> {}", original, path);
+                return None;
+            }
+            Some(DetectedInstall {
+                read_only: is_read_only_install(Path::new(&path)),
+                is_link,
+                link_path: is_link.then_some(original),
+                path,
+            })
+        })
+        .collect()
+}
+
+/// 路径本身或任一上级目录是符号链接 (Windows 上也包括 junction)
+fn has_link_component(path: &Path) -> bool {
+    path.ancestors().any(|ancestor| {
+        std::fs::symlink_metadata(ancestor).is_ok_and(|meta| meta.file_type().is_symlink())
+    })
+}
+
+/// 安装目录是否位于只读文件系统: Snap 包以只读 squashfs 挂载在 /snap 下
+pub fn is_read_only_install(path: &Path) -> bool {
+    cfg!(target_os = "linux") && path.starts_with("/snap")
+}
+
+/// 检测 Windsurf 安装路径
+#[tauri::command]
+pub async fn detect_windsurf_path(app: AppHandle) -> Option<String> {
+    if let Some(path) = valid_manual_path(Editor::Windsurf) {
+        return Some(path);
+    }
+    detect_in_background(&app.state::<DetectionCache>(), Editor::Windsurf).await.into_iter().next()
+}
+
+/// 检测 Cursor 安装路径
+#[tauri::command]
+pub async fn detect_cursor_path(app: AppHandle) -> Option<String> {
+    detect_in_background(&app.state::<DetectionCache>(), Editor::Cursor).await.into_iter().next()
+}
+
+/// 补丁器支持的编辑器
+#[derive(Debug, Serialize)]
+pub struct EditorInfo {
+    pub id: Editor,
+    pub name: &'static str,
+    /// 校验安装目录时查找的 hook 文件 (相对安装目录)
+    #[serde(rename = "hookFile")]
+    pub hook_file: &'static str,
+    /// 本机检测到了该编辑器的安装
+    pub detected: bool,
+}
+
+/// 列出补丁器支持的全部编辑器及其检测结果, 前端据此显示编辑器列表而不必自己维护
+#[tauri::command]
+pub async fn supported_editors(app: AppHandle) -> Vec<EditorInfo> {
+    let cache = app.state::<DetectionCache>();
+    let mut editors = Vec::new();
+    for editor in Editor::ALL {
+        editors.push(EditorInfo {
+            id: editor,
+            name: editor.name(),
+            hook_file: editor.hook_file(),
+            detected: !detect_in_background(&cache, editor).await.is_empty(),
+        });
+    }
+    editors
+}
+
+/// Windsurf 发布渠道
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WindsurfChannel {
+    Stable,
+    /// Windsurf Next (预览版), 可与稳定版并存
+    Next,
+}
+
+/// 检测到的 Windsurf 安装及其发布渠道
+#[derive(Debug, Serialize)]
+pub struct WindsurfInstall {
+    pub path: String,
+    pub channel: WindsurfChannel,
+}
+
+/// 检测所有 Windsurf 安装 (稳定版与 Next 可能并存), 由用户选择要打补丁的一个
+#[tauri::command]
+pub async fn detect_windsurf_with_channel(app: AppHandle) -> Vec<WindsurfInstall> {
+    detect_in_background(&app.state::<DetectionCache>(), Editor::Windsurf)
+        .await
+        .into_iter()
+        .map(|path| WindsurfInstall {
+            channel: windsurf_channel(Path::new(&path)),
+            path,
+        })
+        .collect()
+}
+
+/// 按安装内 product.json 记录的产品名 (如 "Windsurf - Next") 判断渠道, 读不到时看安装目录名
+fn windsurf_channel(install_path: &Path) -> WindsurfChannel {
+    let product_name = std::fs::read_to_string(
+        install_path.join("resources").join("app").join("product.json"),
+    )
+    .ok()
+    .and_then(|content| {
+        serde_json::from_str::<serde_json::Value>(strip_bom(&content)).ok()
+    })
+    .and_then(|product| {
+        product
+            .get("nameLong")
+            .or_else(|| product.get("nameShort"))
+            .and_then(|name| name.as_str())
+            .map(String::from)
+    });
+
+    let name = product_name.unwrap_or_else(|| {
+        install_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    });
+    if name.to_lowercase().contains("next") {
+        WindsurfChannel::Next
+    } else {
+        WindsurfChannel::Stable
+    }
+}
+
+/// 在阻塞任务线程中检测编辑器安装路径
+/// 检测可被 cancel_operation 取消, 取消时返回已找到的路径
+pub async fn detect_in_background(cache: &DetectionCache, editor: Editor) -> Vec<String> {
+    let _operation = cancel::begin();
+    let cache = cache.clone();
+    let found = tauri::async_runtime::spawn_blocking(move || detect_editor_paths(&cache, editor))
+        .await
+        .unwrap_or_default();
+    log::info!("检测 {} 安装路径: {:?}", editor.name(), found);
+    found
+}
+
+/// 检测结果缓存的有效期, 界面反复调用检测命令时不必每次都扫描注册表与磁盘
+const DETECTION_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// 各编辑器最近一次成功 (至少找到一个安装) 的检测结果
+/// 由 run() 通过 app.manage 注册; 克隆的句柄共享同一份缓存, 可以移入后台线程
+/// 命令行模式不启动界面, 单次运行使用自己的缓存
+#[derive(Clone, Default)]
+pub struct DetectionCache(Arc<Mutex<Vec<CachedDetection>>>);
+
+struct CachedDetection {
+    editor: Editor,
+    detected_at: Instant,
+    paths: Vec<String>,
+}
+
+impl DetectionCache {
+    /// 有效期内的缓存结果, 其中已失效的安装 (被卸载或移动) 会被剔除
+    fn paths(&self, editor: Editor) -> Option<Vec<String>> {
+        let cache = self.0.lock().ok()?;
+        let entry = cache.iter().find(|entry| {
+            entry.editor == editor && entry.detected_at.elapsed() < DETECTION_CACHE_TTL
+        })?;
+        let paths: Vec<String> = entry
+            .paths
+            .iter()
+            .filter(|path| is_valid_install(editor, Path::new(path)))
+            .cloned()
+            .collect();
+        (!paths.is_empty()).then_some(paths)
+    }
+
+    fn store(&self, editor: Editor, paths: &[String]) {
+        if let Ok(mut cache) = self.0.lock() {
+            cache.retain(|entry| entry.editor != editor);
+            cache.push(CachedDetection {
+                editor,
+                detected_at: Instant::now(),
+                paths: paths.to_vec(),
+            });
+        }
+    }
+
+    /// 清空检测缓存, 下次检测时重新扫描 (手动指定的路径改变时调用)
+    pub fn invalidate(&self) {
+        if let Ok(mut cache) = self.0.lock() {
+            cache.clear();
+        }
+    }
+}
+
+/// 检测编辑器的所有安装路径, 按优先级排序并去重
+/// 有效期内的缓存结果直接返回
+fn detect_editor_paths(cache: &DetectionCache, editor: Editor) -> Vec<String> {
+    if let Some(paths) = cache.paths(editor) {
+        return paths;
+    }
+
+    let paths = dedup_paths(detect_editor_candidates(editor));
+    // 取消时的结果不完整, 不缓存
+    if is_cancelled() {
+        log::info!("检测 {} 已取消", editor.name());
+    } else if !paths.is_empty() {
+        cache.store(editor, &paths);
+    }
+    paths
+}
+
+/// 一个编辑器检测到的安装路径
+#[derive(Debug, Serialize)]
+pub struct EditorPaths {
+    pub editor: Editor,
+    pub paths: Vec<String>,
+}
+
+/// 清空检测缓存并重新检测所有编辑器
+#[tauri::command]
+pub async fn refresh_detection(app: AppHandle) -> Vec<EditorPaths> {
+    let cache = app.state::<DetectionCache>();
+    cache.invalidate();
+    let mut found = Vec::new();
+    for editor in Editor::ALL {
+        found.push(EditorPaths {
+            editor,
+            paths: detect_in_background(&cache, editor).await,
+        });
+    }
+    found
+}
+
+/// 各检测方式找到的安装路径, 按优先级排序, 未去重也未规范化
+fn detect_editor_candidates(editor: Editor) -> Vec<String> {
+    // 手动指定的路径排在最前
+    #[allow(unused_mut)]
+    let mut found: Vec<String> = valid_manual_path(editor).into_iter().collect();
+
+    // 各检测方式之间检查取消请求, 已取消时返回已找到的路径
+    #[cfg(target_os = "windows")]
+    {
+        // 方式 1: 遍历所有可能盘符的常见路径
+        found.extend(try_common_paths_windows(editor));
+        if is_cancelled() {
+            return found;
+        }
+        // 方式 2: 尝试从注册表读取 (超时只返回已找到的结果, 常见路径已在方式 1 扫描)
+        found.extend(try_registry(editor));
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        found.extend(try_applications_macos(editor));
+        if editor == Editor::Antigravity && !is_cancelled() {
+            // 兜底: 通过 Spotlight 查找放在非标准位置 (子目录, 外置卷) 的 .app
+            found.extend(try_spotlight());
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        if editor == Editor::Antigravity {
+            found.extend(detect_linux());
+        }
+        if !is_cancelled() {
+            found.extend(try_sandboxed_linux(editor));
+        }
+    }
+
+    found
+}
+
+/// 候选路径的来源, 各平台只会用到其中一部分
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CandidateSource {
+    /// 配置中手动指定的路径
+    Manual,
+    /// 注册表卸载项
+    Registry,
+    /// 系统常见安装位置
+    CommonPath,
+    /// 用户目录下的安装位置
+    UserLocal,
+    /// 其他用户目录下的安装位置 (仅以管理员身份运行时扫描)
+    OtherProfile,
+    /// 环境变量 (ANTIGRAVITY_HOME) 指定的目录
+    Environment,
+    /// Applications 目录
+    Applications,
+    /// Spotlight 搜索结果
+    Spotlight,
+    /// AppImage 运行时挂载点
+    AppImage,
+    /// Flatpak / Snap 包
+    Sandbox,
+    /// Scoop / Chocolatey 包管理器
+    PackageManager,
+}
+
+/// 候选路径的检查结果
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum CandidateResult {
+    /// 有效安装
+    Found,
+    /// 路径不存在
+    NotExist,
+    /// 目录存在, 但没有编辑器的 hook 文件
+    MissingHookFile,
+    /// 找到了部分文件, 但缺少必需文件
+    Incomplete { missing: Vec<String> },
+    /// 已安装, 但应用代码打包在 app.asar 中, 无法打补丁
+    PackagedAsAsar,
+    /// 网络路径所在的共享不可达或响应超时
+    NetworkUnreachable,
+    /// 卸载键不存在或其中没有匹配的卸载项 (仅 Windows)
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    RegistryKeyAbsent,
+}
+
+/// 检测时检查过的单个候选路径
+#[derive(Debug, Serialize)]
+pub struct DetectionCandidate {
+    pub source: CandidateSource,
+    /// 候选路径; 注册表未命中时为卸载键
+    pub path: String,
+    pub result: CandidateResult,
+}
+
+/// 检测过程报告, 排查检测不到安装的原因
+#[derive(Debug, Serialize)]
+pub struct DetectionReport {
+    /// 与 detect_antigravity_path 的结果一致
+    pub found: Option<String>,
+    /// 找到的安装的可执行文件架构, 无法读取时为空 (仅 Windows)
+    #[serde(rename = "foundArch")]
+    pub found_arch: Option<InstallArch>,
+    pub candidates: Vec<DetectionCandidate>,
+    /// 未以管理员身份运行, 跳过了其他用户目录的扫描 (仅 Windows)
+    #[serde(rename = "otherProfilesSkipped")]
+    pub other_profiles_skipped: bool,
+    /// 找到的安装位于网络路径 (UNC 或映射驱动器) 上, 安装补丁等操作可能较慢
+    #[serde(rename = "slowPath")]
+    pub slow_path: bool,
+}
+
+/// 可执行文件的 CPU 架构 (PE 头中的 Machine 字段)
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BinaryArch {
+    X86,
+    X64,
+    Arm64,
+}
+
+/// 安装的架构, 以及在本机上是原生运行还是经过模拟 (ARM64 上运行的 x86/x64 安装)
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+#[derive(Debug, Serialize)]
+pub struct InstallArch {
+    pub machine: BinaryArch,
+    pub emulated: bool,
+}
+
+/// 检测 Antigravity 安装路径, 并列出检查过的每个候选路径及其未通过的原因
+#[tauri::command]
+pub async fn detect_antigravity_path_verbose(app: AppHandle) -> DetectionReport {
+    let candidates =
+        tauri::async_runtime::spawn_blocking(|| detection_candidates(Editor::Antigravity))
+            .await
+            .unwrap_or_default();
+    let found = first_antigravity_path(&app.state::<DetectionCache>()).await;
+    DetectionReport {
+        found_arch: found
+            .as_deref()
+            .and_then(|path| install_arch(Editor::Antigravity, Path::new(path))),
+        slow_path: found.as_deref().is_some_and(|path| is_network_path(Path::new(path))),
+        found,
+        candidates,
+        other_profiles_skipped: cfg!(target_os = "windows") && !is_elevated(),
+    }
+}
+
+/// 按检测顺序检查所有候选路径 (Windows 注册表不设超时)
+fn detection_candidates(editor: Editor) -> Vec<DetectionCandidate> {
+    let mut candidates = Vec::new();
+    let mut check = |source: CandidateSource, path: &Path| {
+        candidates.push(DetectionCandidate {
+            source,
+            path: path.to_string_lossy().to_string(),
+            result: candidate_result(editor, path),
+        })
+    };
+
+    if let Some(path) = manual_install_path(editor) {
+        check(CandidateSource::Manual, Path::new(&path));
+    }
+
     #[cfg(target_os = "windows")]
     {
-        detect_windows()
+        for (source, path) in common_candidates_windows(editor) {
+            check(source, &path);
+        }
+        let mut locations = Vec::new();
+        let absent = visit_registry(editor, |location| {
+            locations.push(location);
+            true
+        });
+        for location in locations {
+            check(CandidateSource::Registry, Path::new(&location));
+        }
+        candidates.extend(absent.into_iter().map(|key| DetectionCandidate {
+            source: CandidateSource::Registry,
+            path: key,
+            result: CandidateResult::RegistryKeyAbsent,
+        }));
     }
 
     #[cfg(target_os = "macos")]
     {
-        detect_macos()
+        for path in applications_candidates_macos(editor) {
+            check(CandidateSource::Applications, &path);
+        }
+        if editor == Editor::Antigravity {
+            for path in spotlight_candidates() {
+                check(CandidateSource::Spotlight, &path);
+            }
+        }
     }
 
-    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    #[cfg(target_os = "linux")]
     {
+        if editor == Editor::Antigravity {
+            for (source, path) in linux_candidates() {
+                check(source, &path);
+            }
+        }
+        for root in sandbox_roots(editor) {
+            match find_install_below(editor, &root, SANDBOX_MAX_DEPTH) {
+                Some(path) => check(CandidateSource::Sandbox, &path),
+                None => check(CandidateSource::Sandbox, &root),
+            }
+        }
+    }
+
+    candidates
+}
+
+/// 检查单个候选路径, 给出未通过的原因
+fn candidate_result(editor: Editor, path: &Path) -> CandidateResult {
+    if !is_reachable(path) {
+        return CandidateResult::NetworkUnreachable;
+    }
+    if !path.exists() {
+        return CandidateResult::NotExist;
+    }
+    match editor {
+        Editor::Antigravity => match check_antigravity_install(path) {
+            InstallCheck::Valid => CandidateResult::Found,
+            InstallCheck::NotFound => CandidateResult::MissingHookFile,
+            InstallCheck::Incomplete { missing } => CandidateResult::Incomplete { missing },
+            InstallCheck::PackagedAsAsar => CandidateResult::PackagedAsAsar,
+        },
+        _ if is_valid_install(editor, path) => CandidateResult::Found,
+        _ if is_packaged_as_asar(path) => CandidateResult::PackagedAsAsar,
+        _ => CandidateResult::MissingHookFile,
+    }
+}
+
+/// 配置中手动指定且仍然有效的安装路径 (编辑器可能已被移动或卸载)
+fn valid_manual_path(editor: Editor) -> Option<String> {
+    let path = manual_install_path(editor)?;
+    if is_valid_install(editor, Path::new(&path)) {
+        Some(path)
+    } else {
+        log::warn!("手动指定的 {} 路径无效, 改为自动检测: {}", editor.name(), path);
         None
     }
 }
 
-/// 验证路径是否为有效的 Antigravity 安装目录
-fn is_valid_antigravity_path(path: &PathBuf) -> bool {
-    // 通过核心 hook 文件判断目录有效性
-    let cascade_panel_path = path
-        .join("resources")
-        .join("app")
-        .join("extensions")
-        .join("antigravity")
-        .join("cascade-panel.html");
-    
-    cascade_panel_path.exists()
+/// 检测 Antigravity 安装路径及其版本号
+/// 返回 (路径, 版本号); package.json 缺失或无法解析时版本号为空字符串
+#[tauri::command]
+pub async fn detect_antigravity_with_version(app: AppHandle) -> Option<(String, String)> {
+    let path = first_antigravity_path(&app.state::<DetectionCache>()).await?;
+    let version = read_app_version(Path::new(&path))
+        .map(|version| version.to_string())
+        .unwrap_or_default();
+    Some((path, version))
 }
 
-// Windows 实现
-#[cfg(target_os = "windows")]
-fn detect_windows() -> Option<String> {
-    // 方式 1: 遍历所有可能盘符的常见路径
-    if let Some(path) = try_common_paths_windows() {
-        return Some(path);
+/// 从用户指定的目录查找 Antigravity (便携版/解压版)
+/// 依次检查目录本身及其下两层子目录, 返回第一个有效路径
+#[tauri::command]
+pub fn detect_antigravity_from_hint(hint: String) -> Option<String> {
+    const MAX_DEPTH: usize = 2;
+
+    find_install_below(Editor::Antigravity, &PathBuf::from(hint.trim()), MAX_DEPTH)
+        .and_then(|path| path.to_str().map(String::from))
+}
+
+/// 在指定根目录下递归查找编辑器的所有有效安装, 供部署脚本发现非标准位置的安装
+/// 最多向下 DETECT_IN_ROOT_MAX_DEPTH 层, 共检查 DETECT_IN_ROOT_MAX_ENTRIES 个目录; 有效安装的子目录不再深入
+#[tauri::command(async)]
+pub fn detect_in_root(root: String, editor: Editor) -> Vec<String> {
+    let mut found = Vec::new();
+    let mut visited = 0;
+    let mut level = vec![PathBuf::from(root.trim())];
+    for depth in 0..=DETECT_IN_ROOT_MAX_DEPTH {
+        let mut next = Vec::new();
+        for dir in level {
+            if is_cancelled() || visited >= DETECT_IN_ROOT_MAX_ENTRIES {
+                log::info!("在 {} 中查找 {} 提前结束, 已检查 {} 个目录", root, editor.name(), visited);
+                return dedup_paths(found);
+            }
+            visited += 1;
+            if is_valid_install(editor, &dir) {
+                found.extend(dir.to_str().map(String::from));
+            } else if depth < DETECT_IN_ROOT_MAX_DEPTH {
+                next.extend(list_subdirs(&dir));
+            }
+        }
+        level = next;
     }
+    dedup_paths(found)
+}
 
-    // 方式 2: 尝试从注册表读取 (增强型遍历)
-    if let Some(path) = try_registry() {
-        return Some(path);
+/// detect_in_root 的查找深度与最多检查的目录数
+const DETECT_IN_ROOT_MAX_DEPTH: usize = 6;
+const DETECT_IN_ROOT_MAX_ENTRIES: usize = 5000;
+
+/// 在目录本身及其下 max_depth 层子目录中查找编辑器安装, 广度优先, 浅层目录优先命中
+fn find_install_below(editor: Editor, root: &Path, max_depth: usize) -> Option<PathBuf> {
+    if !root.is_dir() {
+        return None;
+    }
+
+    let mut level = vec![root.to_path_buf()];
+    for depth in 0..=max_depth {
+        if is_cancelled() {
+            return None;
+        }
+        if let Some(path) = level.iter().find(|path| is_valid_install(editor, path)) {
+            return Some(path.clone());
+        }
+        if depth == max_depth {
+            break;
+        }
+        level = level.iter().flat_map(|dir| list_subdirs(dir)).collect();
     }
 
     None
 }
 
+/// 列出目录下的直接子目录
+fn list_subdirs(dir: &Path) -> Vec<PathBuf> {
+    match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// 按名称前缀查找重命名过的安装目录时, 每个父目录最多检查的目录项数与最多返回的目录数
+const RENAMED_DIR_MAX_ENTRIES: usize = 200;
+const RENAMED_DIR_MAX_MATCHES: usize = 10;
+
+/// 父目录下名称以 `prefix` 开头 (不区分大小写) 的子目录, 如改名为 Antigravity-1.2.3 的安装目录
+/// 与 prefix 完全相同的目录不算在内 (已作为常见路径检查)
+fn prefixed_subdirs(parent: &Path, prefix: &str) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(parent) else {
+        return Vec::new();
+    };
+    let prefix = prefix.to_lowercase();
+    entries
+        .flatten()
+        .take(RENAMED_DIR_MAX_ENTRIES)
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().to_lowercase();
+            name.starts_with(&prefix) && name != prefix
+        })
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .take(RENAMED_DIR_MAX_MATCHES)
+        .collect()
+}
+
+/// 规范化后去重, 返回规范化的路径
+/// 注册表与常见路径扫描可能以不同写法 (大小写, 符号链接, 8.3 短路径) 指向同一安装
+fn dedup_paths(paths: Vec<String>) -> Vec<String> {
+    dedup_with_original(paths).into_iter().map(|(_, path)| path).collect()
+}
+
+/// 同 dedup_paths, 同时保留每个安装第一次被找到时的原路径: (原路径, 规范化路径)
+fn dedup_with_original(paths: Vec<String>) -> Vec<(String, String)> {
+    let mut seen: Vec<String> = Vec::new();
+    let mut result = Vec::new();
+
+    for original in paths {
+        let path = canonical_path(&original);
+        let key = path_key(&path);
+        if seen.contains(&key) {
+            continue;
+        }
+        seen.push(key);
+        result.push((original, path));
+    }
+
+    result
+}
+
+/// 解析符号链接与 8.3 短路径 (如 C:\PROGRA~1), 无法解析 (路径不存在) 时原样返回
+pub fn canonical_path(path: &str) -> String {
+    match std::fs::canonicalize(path) {
+        Ok(canonical) => strip_verbatim_prefix(&canonical.to_string_lossy()),
+        Err(_) => path.to_string(),
+    }
+}
+
+/// Windows 上 canonicalize 返回 \\?\ 开头的扩展路径, 转回普通写法再交给前端和后续拼接
+fn strip_verbatim_prefix(path: &str) -> String {
+    if let Some(unc) = path.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{}", unc)
+    } else if let Some(local) = path.strip_prefix(r"\\?\") {
+        local.to_string()
+    } else {
+        path.to_string()
+    }
+}
+
+/// 比较用的路径键: Windows 文件系统不区分大小写
+pub fn path_key(path: &str) -> String {
+    let path = path.trim_end_matches(['/', '\\']);
+    if cfg!(target_os = "windows") {
+        path.to_lowercase()
+    } else {
+        path.to_string()
+    }
+}
+
+/// Antigravity 安装目录检查结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstallCheck {
+    /// 目录下没有任何 Antigravity 文件
+    NotFound,
+    /// 找到了部分文件, 但缺少必需文件 (如自动更新失败后的半残安装)
+    Incomplete { missing: Vec<String> },
+    /// 已安装, 但 resources/app 打包成了 app.asar, 补丁需要未打包的目录
+    PackagedAsAsar,
+    /// 所有必需文件齐全
+    Valid,
+}
+
+/// 检查 Antigravity 安装目录的完整性
+/// 必需文件: 侧边栏 hook, 扩展 package.json 及其 main 入口 JS, resources/app/package.json
+/// Linux 的 .deb / AppImage 布局与 Windows 一致, 同样以 resources/app 为根
+pub fn check_antigravity_install(path: &Path) -> InstallCheck {
+    // 不可达的网络共享上逐个检查文件会各自阻塞到超时
+    if !is_reachable(path) {
+        return InstallCheck::NotFound;
+    }
+    let app_dir = path.join("resources").join("app");
+    let extension_dir = app_dir.join("extensions").join("antigravity");
+    let extension_manifest = extension_dir.join("package.json");
+
+    let mut required = vec![
+        extension_dir.join("cascade-panel.html"),
+        extension_manifest.clone(),
+        app_dir.join("package.json"),
+    ];
+    if let Some(entry) = read_extension_entry(&extension_manifest) {
+        required.push(extension_dir.join(entry));
+    }
+
+    let missing: Vec<String> = required
+        .iter()
+        .filter(|file| !file.is_file())
+        .map(|file| {
+            file.strip_prefix(path)
+                .unwrap_or(file)
+                .to_string_lossy()
+                .replace('\\', "/")
+        })
+        .collect();
+
+    if missing.is_empty() {
+        InstallCheck::Valid
+    } else if is_packaged_as_asar(path) {
+        InstallCheck::PackagedAsAsar
+    } else if missing.len() == required.len() {
+        InstallCheck::NotFound
+    } else {
+        InstallCheck::Incomplete { missing }
+    }
+}
+
+/// 应用代码打包在 resources/app.asar 中, 没有可打补丁的 resources/app 目录
+pub fn is_packaged_as_asar(install_path: &Path) -> bool {
+    let resources = install_path.join("resources");
+    resources.join("app.asar").is_file() && !resources.join("app").join("package.json").is_file()
+}
+
+/// 读取扩展 package.json 中的 main 入口 (相对扩展目录)
+fn read_extension_entry(manifest: &Path) -> Option<String> {
+    let content = std::fs::read_to_string(manifest).ok()?;
+    let value: serde_json::Value =
+        serde_json::from_str(strip_bom(&content)).ok()?;
+    let main = value.get("main")?.as_str()?.trim_start_matches("./");
+    if main.is_empty() {
+        return None;
+    }
+    // VS Code 扩展的 main 允许省略 .js 后缀
+    if main.ends_with(".js") {
+        Some(main.to_string())
+    } else {
+        Some(format!("{}.js", main))
+    }
+}
+
+/// 验证路径是否为有效且完整的 Antigravity 安装目录 (Spotlight 与 Linux 检测使用)
+#[cfg(not(target_os = "windows"))]
+fn is_valid_antigravity_path(path: &Path) -> bool {
+    is_valid_install(Editor::Antigravity, path)
+}
+
+/// 验证路径是否为编辑器的有效安装目录
+/// Antigravity 需要完整性检查通过, 其他编辑器以 hook 文件存在为准
+pub fn is_valid_install(editor: Editor, path: &Path) -> bool {
+    match editor {
+        Editor::Antigravity => check_antigravity_install(path) == InstallCheck::Valid,
+        _ => is_reachable(path) && path.join(editor.hook_file()).is_file(),
+    }
+}
+
+// Windows 实现
+/// 在独立线程中扫描注册表, 超时后返回已找到的部分结果
+/// 卸载项很多的机器上完整遍历可能需要数秒
 #[cfg(target_os = "windows")]
-fn try_registry() -> Option<String> {
+fn try_registry(editor: Editor) -> Vec<String> {
+    use std::sync::mpsc;
+    use std::time::{Duration, Instant};
+
+    let timeout = std::env::var("ANTIGRAVITY_REGISTRY_TIMEOUT_MS")
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(DEFAULT_REGISTRY_TIMEOUT_MS);
+    let deadline = Instant::now() + Duration::from_millis(timeout);
+
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || scan_registry(editor, sender));
+
+    let mut found = Vec::new();
+    // 扫描结束 (发送端释放) 或超时都会退出; 超时的扫描线程在下一次发送失败时结束
+    while let Ok(path) = receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+        found.push(path);
+    }
+
+    found
+}
+
+/// 遍历卸载项, 每找到一个有效安装目录就发送出去
+#[cfg(target_os = "windows")]
+fn scan_registry(editor: Editor, sender: std::sync::mpsc::Sender<String>) {
+    visit_registry(editor, |install_location| {
+        // 发送失败说明调用方已超时返回
+        !is_valid_install(editor, Path::new(&install_location)) || sender.send(install_location).is_ok()
+    });
+}
+
+/// 遍历卸载项中与编辑器匹配的安装目录, visit 返回 false 时停止
+/// 返回不存在或没有任何匹配项的卸载键
+#[cfg(target_os = "windows")]
+fn visit_registry(editor: Editor, mut visit: impl FnMut(String) -> bool) -> Vec<String> {
     use winreg::enums::*;
     use winreg::RegKey;
 
-    let roots = [HKEY_LOCAL_MACHINE, HKEY_CURRENT_USER];
+    let roots = [
+        (HKEY_LOCAL_MACHINE, "HKEY_LOCAL_MACHINE"),
+        (HKEY_CURRENT_USER, "HKEY_CURRENT_USER"),
+    ];
     let uninstall_paths = [
         r"SOFTWARE\Microsoft\Windows\CurrentVersion\Uninstall",
         r"SOFTWARE\WOW6432Node\Microsoft\Windows\CurrentVersion\Uninstall",
     ];
 
-    for root in roots {
+    let mut absent = Vec::new();
+    for (root, root_name) in roots {
         let hkey = RegKey::predef(root);
         for uninstall_path in uninstall_paths {
-            if let Ok(key) = hkey.open_subkey(uninstall_path) {
-                // 递归查找所有子键，模糊匹配 DisplayName
-                for name in key.enum_keys().filter_map(|k| k.ok()) {
-                    if let Ok(sub_key) = key.open_subkey(&name) {
-                        if let Ok(display_name) = sub_key.get_value::<String, _>("DisplayName") {
-                            let lower = display_name.to_lowercase();
-                            if lower.contains("antigravity") {
-                                if let Ok(install_location) = sub_key.get_value::<String, _>("InstallLocation") {
-                                    if !install_location.is_empty() {
-                                        let path = PathBuf::from(&install_location);
-                                        if is_valid_antigravity_path(&path) {
-                                            return Some(install_location);
-                                        }
-                                    }
-                                }
-                            }
-                        }
+            let key_name = format!("{}\\{}", root_name, uninstall_path);
+            let Ok(key) = hkey.open_subkey(uninstall_path) else {
+                absent.push(key_name);
+                continue;
+            };
+            let mut matched = false;
+            // 遍历所有子键，模糊匹配 DisplayName
+            for name in key.enum_keys().filter_map(|k| k.ok()) {
+                let Ok(sub_key) = key.open_subkey(&name) else {
+                    continue;
+                };
+                let Ok(display_name) = sub_key.get_value::<String, _>("DisplayName") else {
+                    continue;
+                };
+                let lower = display_name.to_lowercase();
+                if !lower.contains(editor.registry_match())
+                    || editor.registry_excludes().iter().any(|word| lower.contains(word))
+                {
+                    continue;
+                }
+                if let Some(install_location) = registry_install_location(&sub_key) {
+                    matched = true;
+                    if !visit(install_location) {
+                        return absent;
                     }
                 }
             }
+            if !matched {
+                absent.push(key_name);
+            }
         }
     }
+    absent
+}
 
-    None
+/// 从卸载项读取安装目录
+/// 部分安装器 (GUID 子键) 不写 InstallLocation 或带引号, 此时退回 DisplayIcon 所在目录
+#[cfg(target_os = "windows")]
+fn registry_install_location(sub_key: &winreg::RegKey) -> Option<String> {
+    let unquote = |value: &str| value.trim().trim_matches('"').trim_end_matches('\\').to_string();
+
+    if let Ok(location) = sub_key.get_value::<String, _>("InstallLocation") {
+        let location = unquote(&location);
+        if !location.is_empty() {
+            return Some(location);
+        }
+    }
+
+    // DisplayIcon 形如 "C:\...\Antigravity.exe",0
+    let icon = sub_key.get_value::<String, _>("DisplayIcon").ok()?;
+    let exe = match icon.rsplit_once(',') {
+        Some((path, index)) if index.trim().parse::<i32>().is_ok() => unquote(path),
+        _ => unquote(&icon),
+    };
+    PathBuf::from(exe)
+        .parent()
+        .and_then(|dir| dir.to_str())
+        .filter(|dir| !dir.is_empty())
+        .map(String::from)
 }
 
 #[cfg(target_os = "windows")]
-fn try_common_paths_windows() -> Option<String> {
-    // 1. 尝试常见的安装盘符 (C-G)
-    for drive_letter in b'C'..=b'G' {
-        let drive = format!("{}:\\", drive_letter as char);
-        let candidates = [
-            format!("{}Antigravity", drive),
-            format!("{}Program Files\\Antigravity", drive),
-            format!("{}Program Files (x86)\\Antigravity", drive),
-        ];
-
-        for path_str in candidates {
-            let path = PathBuf::from(&path_str);
-            if is_valid_antigravity_path(&path) {
-                return Some(path_str);
+fn try_common_paths_windows(editor: Editor) -> Vec<String> {
+    // 逐个候选检查, 网络驱动器等慢速路径上可以中途取消
+    common_candidates_windows(editor)
+        .into_iter()
+        .take_while(|_| !is_cancelled())
+        .filter(|(_, path)| is_valid_install(editor, path))
+        .filter_map(|(_, path)| path.to_str().map(String::from))
+        .collect()
+}
+
+/// 常见位置下所有可能的安装目录 (逐个目录名), 以管理员身份运行时包括其他用户目录
+#[cfg(target_os = "windows")]
+fn common_candidates_windows(editor: Editor) -> Vec<(CandidateSource, PathBuf)> {
+    let mut candidates: Vec<(CandidateSource, PathBuf)> = editor
+        .install_dir_names()
+        .iter()
+        .flat_map(|dir_name| common_dir_candidates_windows(dir_name))
+        .collect();
+    candidates.extend(package_manager_candidates_windows(editor));
+    // 未提权时其他用户的 AppData 无权访问, 不扫描以免报告中满是拒绝访问
+    if is_elevated() {
+        candidates.extend(other_profile_candidates_windows(editor));
+    }
+    candidates
+}
+
+/// Scoop 与 Chocolatey 安装的目录
+/// Scoop 安装在 <根目录>\apps\<包名>\current; shim 记录了可执行文件的实际路径, 包名不同时据此找到安装目录
+/// Chocolatey 的便携包解压在 <根目录>\lib\<包名> 下若干层, bin 中的 shim 指向其中的可执行文件
+#[cfg(target_os = "windows")]
+fn package_manager_candidates_windows(editor: Editor) -> Vec<(CandidateSource, PathBuf)> {
+    let mut candidates = Vec::new();
+    for package in editor.package_names() {
+        for scoop in scoop_roots() {
+            candidates.push((
+                CandidateSource::PackageManager,
+                scoop.join("apps").join(package).join("current"),
+            ));
+            let shim = scoop.join("shims").join(format!("{}.shim", package));
+            if let Some(dir) = shim_target_dir(&shim) {
+                candidates.push((CandidateSource::PackageManager, dir));
             }
         }
+        if let Some(chocolatey) = chocolatey_root() {
+            let lib = chocolatey.join("lib").join(package);
+            let path = find_install_below(editor, &lib, PACKAGE_MAX_DEPTH).unwrap_or(lib);
+            candidates.push((CandidateSource::PackageManager, path));
+        }
     }
+    candidates
+}
 
-    // 2. 检查用户本地目录 (AppData)
-    if let Some(local_data) = dirs::data_local_dir() {
-        let user_path = local_data.join("Programs").join("Antigravity");
-        if is_valid_antigravity_path(&user_path) {
-            return user_path.to_str().map(String::from);
+/// Chocolatey 包目录内的查找深度 (tools 目录下通常还有一层解压目录)
+#[cfg(target_os = "windows")]
+const PACKAGE_MAX_DEPTH: usize = 3;
+
+/// 非空环境变量表示的目录
+#[cfg(target_os = "windows")]
+fn env_dir(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Scoop 的用户与全局根目录, 可由 SCOOP / SCOOP_GLOBAL 环境变量改到其他位置
+#[cfg(target_os = "windows")]
+fn scoop_roots() -> Vec<PathBuf> {
+    let user = env_dir("SCOOP").or_else(|| dirs::home_dir().map(|home| home.join("scoop")));
+    let global = env_dir("SCOOP_GLOBAL")
+        .or_else(|| env_dir("ProgramData").map(|data| data.join("scoop")));
+    user.into_iter().chain(global).collect()
+}
+
+/// Chocolatey 的根目录, 默认 %ProgramData%\chocolatey
+#[cfg(target_os = "windows")]
+fn chocolatey_root() -> Option<PathBuf> {
+    env_dir("ChocolateyInstall")
+        .or_else(|| env_dir("ProgramData").map(|data| data.join("chocolatey")))
+}
+
+/// 从 Scoop shim 文件 (`path = "...\Antigravity.exe"`) 读出可执行文件所在目录
+#[cfg(target_os = "windows")]
+fn shim_target_dir(shim: &Path) -> Option<PathBuf> {
+    let content = std::fs::read_to_string(shim).ok()?;
+    let target = content.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        (key.trim() == "path").then(|| value.trim().trim_matches('"').to_string())
+    })?;
+    PathBuf::from(target).parent().map(Path::to_path_buf)
+}
+
+/// 其他用户目录 (与当前用户目录同级) 下 AppData\Local\Programs 中的安装目录
+#[cfg(target_os = "windows")]
+fn other_profile_candidates_windows(editor: Editor) -> Vec<(CandidateSource, PathBuf)> {
+    let Some(home) = dirs::home_dir() else {
+        return Vec::new();
+    };
+    let Some(Ok(entries)) = home.parent().map(std::fs::read_dir) else {
+        return Vec::new();
+    };
+
+    let mut candidates = Vec::new();
+    let profiles = entries
+        .flatten()
+        .take(OTHER_PROFILES_MAX)
+        .map(|entry| entry.path())
+        .filter(|profile| *profile != home && profile.is_dir())
+        .filter(|profile| {
+            let name = profile.file_name().unwrap_or_default().to_string_lossy().to_lowercase();
+            !SYSTEM_PROFILE_NAMES.contains(&name.as_str())
+        });
+    for profile in profiles {
+        let programs = profile.join("AppData").join("Local").join("Programs");
+        for dir_name in editor.install_dir_names() {
+            candidates.push((CandidateSource::OtherProfile, programs.join(dir_name)));
+            candidates.extend(
+                prefixed_subdirs(&programs, dir_name)
+                    .into_iter()
+                    .map(|path| (CandidateSource::OtherProfile, path)),
+            );
         }
     }
+    candidates
+}
 
-    None
+/// 用户目录的上级目录中最多检查的目录项数
+#[cfg(target_os = "windows")]
+const OTHER_PROFILES_MAX: usize = 100;
+
+/// 用户目录的上级目录中不属于真实用户的目录 (小写)
+#[cfg(target_os = "windows")]
+const SYSTEM_PROFILE_NAMES: &[&str] = &["public", "default", "default user", "all users"];
+
+/// 当前进程是否以管理员身份 (提权) 运行
+#[cfg(target_os = "windows")]
+fn is_elevated() -> bool {
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows_sys::Win32::Security::{
+        GetTokenInformation, TokenElevation, TOKEN_ELEVATION, TOKEN_QUERY,
+    };
+    use windows_sys::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
+
+    let mut token: HANDLE = std::ptr::null_mut();
+    // SAFETY: GetCurrentProcess 返回的伪句柄始终有效, token 只在打开成功后使用并关闭
+    unsafe {
+        if OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) == 0 {
+            return false;
+        }
+        let mut elevation = TOKEN_ELEVATION::default();
+        let mut returned = 0u32;
+        let ok = GetTokenInformation(
+            token,
+            TokenElevation,
+            (&mut elevation as *mut TOKEN_ELEVATION).cast(),
+            std::mem::size_of::<TOKEN_ELEVATION>() as u32,
+            &mut returned,
+        );
+        CloseHandle(token);
+        ok != 0 && elevation.TokenIsElevated != 0
+    }
 }
 
-/// 检测 Windsurf 安装路径
-#[tauri::command]
-pub fn detect_windsurf_path() -> Option<String> {
-    #[cfg(target_os = "windows")]
-    {
-        detect_windsurf_windows()
+#[cfg(not(target_os = "windows"))]
+fn is_elevated() -> bool {
+    false
+}
+
+/// 在常见位置拼出指定目录名的候选安装目录
+#[cfg(target_os = "windows")]
+fn common_dir_candidates_windows(dir_name: &str) -> Vec<(CandidateSource, PathBuf)> {
+    let mut parents = Vec::new();
+
+    // 1. 系统的 Program Files 目录 (本地化系统中目录名可能不是英文)
+    for program_files in program_files_dirs() {
+        parents.push((CandidateSource::CommonPath, program_files));
     }
 
-    #[cfg(target_os = "macos")]
-    {
-        detect_windsurf_macos()
+    // 2. 遍历所有存在的盘符 (常见盘符优先)
+    for drive in windows_drive_roots() {
+        for parent in [
+            drive.clone(),
+            format!("{}Program Files", drive),
+            format!("{}Program Files (x86)", drive),
+        ] {
+            parents.push((CandidateSource::CommonPath, PathBuf::from(parent)));
+        }
     }
 
-    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
-    {
-        None
+    // 3. 检查用户本地目录 (AppData)
+    if let Some(local_data) = dirs::data_local_dir() {
+        parents.push((CandidateSource::UserLocal, local_data.join("Programs")));
     }
+
+    let mut candidates: Vec<(CandidateSource, PathBuf)> = parents
+        .iter()
+        .map(|(source, parent)| (*source, parent.join(dir_name)))
+        .collect();
+    // 4. 重命名过的安装目录 (如 Antigravity-1.2.3), 排在标准目录名之后
+    for (source, parent) in &parents {
+        candidates.extend(
+            prefixed_subdirs(parent, dir_name)
+                .into_iter()
+                .map(|path| (*source, path)),
+        );
+    }
+    candidates
 }
 
-/// 验证路径是否为有效的 Windsurf 安装目录
-fn is_valid_windsurf_path(path: &PathBuf) -> bool {
-    let workbench_path = path
-        .join("resources")
-        .join("app")
-        .join("out")
-        .join("vs")
-        .join("code")
-        .join("electron-browser")
-        .join("workbench")
-        .join("workbench.html");
+/// 从环境变量读取 Program Files 目录 (64 位, 32 位, 当前进程视角)
+/// ARM64 系统上另有存放原生 ARM 程序的 Program Files (Arm)
+#[cfg(target_os = "windows")]
+fn program_files_dirs() -> Vec<PathBuf> {
+    let mut names = vec!["ProgramW6432", "ProgramFiles", "ProgramFiles(x86)"];
+    if host_machine() == Some(BinaryArch::Arm64) {
+        names.push("ProgramFiles(Arm)");
+    }
 
-    workbench_path.exists()
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    for name in names {
+        let Some(value) = std::env::var_os(name).filter(|value| !value.is_empty()) else {
+            continue;
+        };
+        let dir = PathBuf::from(value);
+        if !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+    dirs
 }
 
+/// 本机 CPU 架构; x64 补丁器在 ARM64 上经模拟运行时仍返回 ARM64
 #[cfg(target_os = "windows")]
-fn detect_windsurf_windows() -> Option<String> {
-    if let Some(path) = try_windsurf_registry() {
-        return Some(path);
+fn host_machine() -> Option<BinaryArch> {
+    use windows_sys::Win32::System::SystemInformation::IMAGE_FILE_MACHINE;
+    use windows_sys::Win32::System::Threading::{GetCurrentProcess, IsWow64Process2};
+
+    let mut process_machine: IMAGE_FILE_MACHINE = 0;
+    let mut native_machine: IMAGE_FILE_MACHINE = 0;
+    // SAFETY: GetCurrentProcess 返回的伪句柄始终有效, 输出参数指向有效的 u16
+    let ok = unsafe {
+        IsWow64Process2(GetCurrentProcess(), &mut process_machine, &mut native_machine)
+    };
+    if ok != 0 {
+        return binary_arch(native_machine);
     }
-    if let Some(path) = try_windsurf_common_paths_windows() {
-        return Some(path);
+
+    // IsWow64Process2 需要 Windows 10 1709 以上, 更早的系统按环境变量判断
+    let arch = std::env::var("PROCESSOR_ARCHITEW6432")
+        .or_else(|_| std::env::var("PROCESSOR_ARCHITECTURE"))
+        .ok()?;
+    match arch.to_ascii_uppercase().as_str() {
+        "ARM64" => Some(BinaryArch::Arm64),
+        "AMD64" => Some(BinaryArch::X64),
+        "X86" => Some(BinaryArch::X86),
+        _ => None,
     }
-    None
 }
 
 #[cfg(target_os = "windows")]
-fn try_windsurf_registry() -> Option<String> {
-    use winreg::enums::*;
-    use winreg::RegKey;
+fn binary_arch(machine: u16) -> Option<BinaryArch> {
+    use windows_sys::Win32::System::SystemInformation::{
+        IMAGE_FILE_MACHINE_AMD64, IMAGE_FILE_MACHINE_ARM64, IMAGE_FILE_MACHINE_I386,
+    };
 
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    let uninstall_path = r"SOFTWARE\Microsoft\Windows\CurrentVersion\Uninstall";
-
-    if let Ok(uninstall_key) = hkcu.open_subkey(uninstall_path) {
-        for name in uninstall_key.enum_keys().filter_map(|k| k.ok()) {
-            if let Ok(sub_key) = uninstall_key.open_subkey(&name) {
-                if let Ok(display_name) = sub_key.get_value::<String, _>("DisplayName") {
-                    let lower = display_name.to_lowercase();
-                    if lower.contains("windsurf") && !lower.contains("account") && !lower.contains("assistant") {
-                        if let Ok(install_location) = sub_key.get_value::<String, _>("InstallLocation") {
-                            let path = PathBuf::from(&install_location);
-                            if is_valid_windsurf_path(&path) {
-                                return Some(install_location);
-                            }
-                        }
-                    }
-                }
-            }
-        }
+    match machine {
+        IMAGE_FILE_MACHINE_I386 => Some(BinaryArch::X86),
+        IMAGE_FILE_MACHINE_AMD64 => Some(BinaryArch::X64),
+        IMAGE_FILE_MACHINE_ARM64 => Some(BinaryArch::Arm64),
+        _ => None,
     }
+}
 
+/// 读取安装中编辑器主程序的 PE 头, 判断其架构以及是否经模拟运行
+/// ARM64EC 程序的 PE 头标记为 x64, 会被当作模拟运行
+#[cfg(target_os = "windows")]
+fn install_arch(editor: Editor, install_path: &Path) -> Option<InstallArch> {
+    use std::fs::File;
+    use std::io::{Read, Seek, SeekFrom};
+
+    let exe = install_path.join(format!("{}.exe", editor.process_name()));
+    let mut file = File::open(exe).ok()?;
+
+    // DOS 头的 e_lfanew (偏移 0x3C) 指向 PE 签名, 其后紧跟 Machine 字段
+    let mut dos_header = [0u8; 0x40];
+    file.read_exact(&mut dos_header).ok()?;
+    if &dos_header[..2] != b"MZ" {
+        return None;
+    }
+    let pe_offset = u32::from_le_bytes(dos_header[0x3c..0x40].try_into().ok()?);
+    file.seek(SeekFrom::Start(u64::from(pe_offset))).ok()?;
+    let mut pe_header = [0u8; 6];
+    file.read_exact(&mut pe_header).ok()?;
+    if &pe_header[..4] != b"PE\0\0" {
+        return None;
+    }
+
+    let machine = binary_arch(u16::from_le_bytes([pe_header[4], pe_header[5]]))?;
+    let emulated = host_machine() == Some(BinaryArch::Arm64) && machine != BinaryArch::Arm64;
+    Some(InstallArch { machine, emulated })
+}
+
+#[cfg(not(target_os = "windows"))]
+fn install_arch(_editor: Editor, _install_path: &Path) -> Option<InstallArch> {
     None
 }
 
+/// 列出本机存在的盘符根目录, C/D/E 排在最前以保证常规机器的检测速度
+/// 按驱动器类型判断盘符是否存在, 断开的网络驱动器不会阻塞; 映射驱动器上的候选路径检查时才访问网络
 #[cfg(target_os = "windows")]
-fn try_windsurf_common_paths_windows() -> Option<String> {
-    if let Some(local_data) = dirs::data_local_dir() {
-        let user_path = local_data.join("Programs").join("Windsurf");
-        if is_valid_windsurf_path(&user_path) {
-            return user_path.to_str().map(String::from);
-        }
-    }
+fn windows_drive_roots() -> Vec<String> {
+    use super::network::drive_exists;
 
-    let literal_paths = [
-        r"C:\Program Files\Windsurf",
-        r"D:\Program Files\Windsurf",
-    ];
+    const FAST_PATH: [u8; 3] = [b'C', b'D', b'E'];
+
+    let mut roots: Vec<String> = FAST_PATH
+        .iter()
+        .map(|letter| format!("{}:\\", *letter as char))
+        .collect();
 
-    for path_str in literal_paths {
-        let path = PathBuf::from(path_str);
-        if is_valid_windsurf_path(&path) {
-            return Some(path_str.to_string());
+    for letter in b'A'..=b'Z' {
+        if FAST_PATH.contains(&letter) {
+            continue;
+        }
+        if drive_exists(letter) {
+            roots.push(format!("{}:\\", letter as char));
         }
     }
 
-    None
+    roots
+}
+
+// macOS 实现
+#[cfg(target_os = "macos")]
+fn try_applications_macos(editor: Editor) -> Vec<String> {
+    applications_candidates_macos(editor)
+        .into_iter()
+        .filter(|path| is_valid_install(editor, path))
+        .filter_map(|path| path.to_str().map(String::from))
+        .collect()
 }
 
+/// 系统与用户 Applications 目录下的候选 .app, 包括放在子文件夹 (如 /Applications/AI Tools) 中的
 #[cfg(target_os = "macos")]
-fn detect_windsurf_macos() -> Option<String> {
-    let paths = [
-        "/Applications/Windsurf.app",
-    ];
+fn applications_candidates_macos(editor: Editor) -> Vec<PathBuf> {
+    let mut app_dirs = vec![PathBuf::from("/Applications")];
 
-    for path_str in paths {
-        let path = PathBuf::from(path_str);
-        if is_valid_windsurf_path(&path) {
-            return Some(path_str.to_string());
+    // 检查用户 Applications 目录
+    if let Some(home) = dirs::home_dir() {
+        app_dirs.push(home.join("Applications"));
+    }
+
+    let mut candidates: Vec<PathBuf> = app_dirs
+        .iter()
+        .flat_map(|dir| editor.app_bundles().iter().map(|bundle| dir.join(bundle)))
+        .collect();
+    // 重命名过的应用包 (如 Antigravity 1.2.3.app)
+    for dir in &app_dirs {
+        for bundle in editor.app_bundles() {
+            let stem = bundle.trim_end_matches(".app");
+            candidates.extend(
+                prefixed_subdirs(dir, stem)
+                    .into_iter()
+                    .filter(|path| path.extension().is_some_and(|ext| ext == "app")),
+            );
         }
     }
+    candidates.extend(app_dirs.iter().flat_map(|dir| nested_app_bundles(dir)));
+    candidates
+}
 
-    if let Some(home) = dirs::home_dir() {
-        let user_app = home.join("Applications").join("Windsurf.app");
-        if is_valid_windsurf_path(&user_app) {
-            return user_app.to_str().map(String::from);
+/// Applications 子文件夹的扫描深度
+#[cfg(target_os = "macos")]
+const NESTED_APPS_MAX_DEPTH: usize = 2;
+
+/// 扫描子文件夹时最多检查的目录项数, 避免子文件夹很多时拖慢检测
+#[cfg(target_os = "macos")]
+const NESTED_APPS_MAX_ENTRIES: usize = 500;
+
+/// 列出 Applications 子文件夹中的 .app (不进入 .app 包内部), 直接位于根目录的不算在内
+#[cfg(target_os = "macos")]
+fn nested_app_bundles(root: &Path) -> Vec<PathBuf> {
+    let is_bundle = |path: &Path| path.extension().is_some_and(|ext| ext == "app");
+
+    let mut bundles = Vec::new();
+    let mut visited = 0;
+    let mut level: Vec<PathBuf> = list_subdirs(root)
+        .into_iter()
+        .filter(|path| !is_bundle(path))
+        .collect();
+    for _ in 0..NESTED_APPS_MAX_DEPTH {
+        let mut next = Vec::new();
+        for dir in &level {
+            for path in list_subdirs(dir) {
+                visited += 1;
+                if visited > NESTED_APPS_MAX_ENTRIES || is_cancelled() {
+                    return bundles;
+                }
+                if is_bundle(&path) {
+                    bundles.push(path);
+                } else {
+                    next.push(path);
+                }
+            }
         }
+        level = next;
     }
 
-    None
+    bundles
 }
 
-// macOS 实现
 #[cfg(target_os = "macos")]
-fn detect_macos() -> Option<String> {
-    let standard_paths = [
-        "/Applications/Antigravity.app",
-    ];
+fn try_spotlight() -> Vec<String> {
+    spotlight_candidates()
+        .into_iter()
+        .filter(|path| is_valid_antigravity_path(path))
+        .filter_map(|path| path.to_str().map(String::from))
+        .collect()
+}
+
+/// 使用 mdfind 查询 Antigravity.app, 超时后放弃以免 Spotlight 索引缓慢时卡住 UI
+#[cfg(target_os = "macos")]
+fn spotlight_candidates() -> Vec<PathBuf> {
+    use std::process::{Command, Stdio};
+    use std::time::{Duration, Instant};
+
+    const QUERY: &str = "kMDItemCFBundleIdentifier == 'com.google.antigravity*' || \
+        (kMDItemContentType == 'com.apple.application-bundle' && kMDItemFSName == 'Antigravity*.app'c)";
+    const TIMEOUT: Duration = Duration::from_secs(2);
+
+    let mut child = match Command::new("mdfind")
+        .arg(QUERY)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(_) => return Vec::new(),
+    };
 
-    for path_str in standard_paths {
-        let path = PathBuf::from(path_str);
-        if is_valid_antigravity_path(&path) {
-            return Some(path_str.to_string());
+    let deadline = Instant::now() + TIMEOUT;
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(50)),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Vec::new();
+            }
         }
     }
 
-    // 检查用户 Applications 目录
-    if let Some(home) = dirs::home_dir() {
-        let user_app = home.join("Applications").join("Antigravity.app");
-        if is_valid_antigravity_path(&user_app) {
-            return user_app.to_str().map(String::from);
+    let output = match child.wait_with_output() {
+        Ok(output) => output,
+        Err(_) => return Vec::new(),
+    };
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.ends_with(".app"))
+        .map(PathBuf::from)
+        .collect()
+}
+
+// Linux 实现
+#[cfg(target_os = "linux")]
+fn detect_linux() -> Vec<String> {
+    linux_candidates()
+        .into_iter()
+        .filter(|(_, path)| is_valid_antigravity_path(path))
+        .filter_map(|(_, path)| path.to_str().map(String::from))
+        .collect()
+}
+
+/// Antigravity 在 Linux 上的候选安装目录
+#[cfg(target_os = "linux")]
+fn linux_candidates() -> Vec<(CandidateSource, PathBuf)> {
+    let mut candidates = Vec::new();
+
+    // 1. 用户显式指定的安装目录优先
+    if let Some(home) = std::env::var_os("ANTIGRAVITY_HOME") {
+        if !home.is_empty() {
+            candidates.push((CandidateSource::Environment, PathBuf::from(home)));
         }
     }
 
-    None
+    // 2. 系统级安装 (.deb / .rpm / 手动解压)
+    candidates.push((CandidateSource::CommonPath, PathBuf::from("/usr/share/antigravity")));
+    candidates.push((CandidateSource::CommonPath, PathBuf::from("/opt/Antigravity")));
+
+    // 3. 用户级安装
+    let user_share = dirs::home_dir().map(|home| home.join(".local").join("share"));
+    if let Some(share) = &user_share {
+        candidates.push((CandidateSource::UserLocal, share.join("antigravity")));
+    }
+
+    // 重命名过的安装目录 (如 /opt/Antigravity-1.2.3), 前缀不区分大小写
+    for root in ["/usr/share", "/opt"] {
+        candidates.extend(
+            prefixed_subdirs(Path::new(root), "antigravity")
+                .into_iter()
+                .map(|path| (CandidateSource::CommonPath, path)),
+        );
+    }
+    if let Some(share) = &user_share {
+        candidates.extend(
+            prefixed_subdirs(share, "antigravity")
+                .into_iter()
+                .map(|path| (CandidateSource::UserLocal, path)),
+        );
+    }
+
+    // 4. AppImage 运行时挂载点 (/tmp/.mount_Antigr*)
+    candidates.extend(
+        find_appimage_mounts()
+            .into_iter()
+            .map(|path| (CandidateSource::AppImage, path)),
+    );
+
+    candidates
+}
+
+/// Flatpak 与 Snap 安装
+/// Flatpak 的编辑器文件在 <安装位置>/app/<ID>/current/active/files 下 (~/.var/app/<ID> 只有用户数据);
+/// Snap 挂载在 /snap/<包名>/current, 编辑器通常在其下的 usr/share 或 opt 子目录中
+#[cfg(target_os = "linux")]
+fn try_sandboxed_linux(editor: Editor) -> Vec<String> {
+    sandbox_roots(editor)
+        .iter()
+        .filter_map(|root| find_install_below(editor, root, SANDBOX_MAX_DEPTH))
+        .filter_map(|path| path.to_str().map(String::from))
+        .collect()
+}
+
+/// Flatpak 与 Snap 包内的查找深度
+#[cfg(target_os = "linux")]
+const SANDBOX_MAX_DEPTH: usize = 3;
+
+/// Flatpak 与 Snap 包的根目录, 编辑器安装在其下若干层
+#[cfg(target_os = "linux")]
+fn sandbox_roots(editor: Editor) -> Vec<PathBuf> {
+    let mut flatpak_installations = vec![PathBuf::from("/var/lib/flatpak")];
+    if let Some(data_dir) = dirs::data_dir() {
+        flatpak_installations.push(data_dir.join("flatpak"));
+    }
+
+    let mut roots: Vec<PathBuf> = flatpak_installations
+        .into_iter()
+        .map(|installation| {
+            installation
+                .join("app")
+                .join(editor.flatpak_id())
+                .join("current")
+                .join("active")
+                .join("files")
+        })
+        .collect();
+    roots.push(PathBuf::from("/snap").join(editor.snap_name()).join("current"));
+    roots
+}
+
+/// 查找正在运行的 Antigravity AppImage 挂载目录
+#[cfg(target_os = "linux")]
+fn find_appimage_mounts() -> Vec<PathBuf> {
+    let entries = match std::fs::read_dir(std::env::temp_dir()) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    entries
+        .flatten()
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .to_lowercase()
+                .starts_with(".mount_antigr")
+        })
+        .map(|entry| entry.path())
+        .collect()
 }
@@ -0,0 +1,159 @@
+// 诊断信息
+// 反馈问题时需要的环境信息 (系统, 检测到的安装及其版本和补丁状态, 补丁器版本) 一次收集, 可导出为文件附在 issue 中
+
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+use crate::embedded;
+use super::config::{app_data_dir, CONFIG_SCHEMA_VERSION};
+use super::detect::{detect_in_background, is_read_only_install, DetectionCache};
+use super::editor::Editor;
+use super::error::PatchError;
+use super::patch::{check_patch_status, panel_config_path};
+use super::status::PatchState;
+use super::version::read_app_version;
+
+/// 单个检测到的安装
+#[derive(Debug, Serialize)]
+pub struct InstallDiagnostics {
+    pub editor: Editor,
+    pub path: String,
+    /// 编辑器版本, 读取不到 package.json 时为空
+    pub version: Option<String>,
+    #[serde(rename = "readOnly")]
+    pub read_only: bool,
+    #[serde(rename = "patchState")]
+    pub patch_state: Option<PatchState>,
+    /// 检查补丁状态失败的原因
+    #[serde(rename = "statusError")]
+    pub status_error: Option<PatchError>,
+}
+
+/// 诊断信息
+#[derive(Debug, Serialize)]
+pub struct Diagnostics {
+    pub os: String,
+    pub arch: String,
+    /// 系统版本描述, 获取失败时为空
+    #[serde(rename = "osVersion")]
+    pub os_version: Option<String>,
+    #[serde(rename = "patcherVersion")]
+    pub patcher_version: String,
+    /// 内置补丁内容版本
+    #[serde(rename = "payloadVersion")]
+    pub payload_version: String,
+    #[serde(rename = "configSchemaVersion")]
+    pub config_schema_version: u32,
+    pub installs: Vec<InstallDiagnostics>,
+}
+
+/// 收集诊断信息
+#[tauri::command]
+pub async fn collect_diagnostics(app: AppHandle) -> Diagnostics {
+    let cache = app.state::<DetectionCache>();
+    let mut installs = Vec::new();
+    for editor in Editor::ALL {
+        for path in detect_in_background(&cache, editor).await {
+            installs.push(install_diagnostics(editor, path));
+        }
+    }
+
+    Diagnostics {
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        os_version: os_version(),
+        patcher_version: env!("CARGO_PKG_VERSION").to_string(),
+        payload_version: embedded::patch_version().to_string(),
+        config_schema_version: CONFIG_SCHEMA_VERSION,
+        installs,
+    }
+}
+
+/// 收集诊断信息并写入文件, 返回写入的路径
+/// 未指定路径时写入应用数据目录下的 diagnostics.json
+#[tauri::command]
+pub async fn save_diagnostics(app: AppHandle, path: Option<String>) -> Result<String, String> {
+    let path = path
+        .map(PathBuf::from)
+        .unwrap_or_else(|| app_data_dir().join("diagnostics.json"));
+    let diagnostics = collect_diagnostics(app).await;
+
+    let content = serde_json::to_string_pretty(&diagnostics)
+        .map_err(|e| format!("序列化诊断信息失败: {}", e))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建目录失败: {}", e))?;
+    }
+    fs::write(&path, content).map_err(|e| format!("写入诊断信息失败: {}", e))?;
+
+    log::info!("诊断信息已导出: {:?}", path);
+    Ok(path.to_string_lossy().to_string())
+}
+
+fn install_diagnostics(editor: Editor, path: String) -> InstallDiagnostics {
+    let install_path = Path::new(&path);
+    let (patch_state, status_error) = match editor {
+        Editor::Antigravity => match check_patch_status(path.clone()) {
+            Ok(status) => (Some(status.overall), None),
+            Err(e) => (None, Some(e)),
+        },
+        // 面板补丁只有是否安装两种状态
+        Editor::Windsurf | Editor::Cursor => {
+            let patched = panel_config_path(install_path).exists();
+            let state = if patched { PatchState::Patched } else { PatchState::NotPatched };
+            (Some(state), None)
+        }
+    };
+
+    InstallDiagnostics {
+        editor,
+        version: read_app_version(install_path).map(|version| version.to_string()),
+        read_only: is_read_only_install(install_path),
+        patch_state,
+        status_error,
+        path,
+    }
+}
+
+/// Windows: 注册表中的产品名与内部版本号
+#[cfg(target_os = "windows")]
+fn os_version() -> Option<String> {
+    use winreg::enums::HKEY_LOCAL_MACHINE;
+    use winreg::RegKey;
+
+    let key = RegKey::predef(HKEY_LOCAL_MACHINE)
+        .open_subkey(r"SOFTWARE\Microsoft\Windows NT\CurrentVersion")
+        .ok()?;
+    let product: String = key.get_value("ProductName").ok()?;
+    match key.get_value::<String, _>("CurrentBuild") {
+        Ok(build) => Some(format!("{} (build {})", product, build)),
+        Err(_) => Some(product),
+    }
+}
+
+/// macOS: sw_vers 输出的系统版本
+#[cfg(target_os = "macos")]
+fn os_version() -> Option<String> {
+    let output = std::process::Command::new("sw_vers")
+        .arg("-productVersion")
+        .output()
+        .ok()?;
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!version.is_empty()).then(|| format!("macOS {}", version))
+}
+
+/// Linux: /etc/os-release 中的发行版名称
+#[cfg(target_os = "linux")]
+fn os_version() -> Option<String> {
+    let content = fs::read_to_string("/etc/os-release").ok()?;
+    content.lines().find_map(|line| {
+        line.strip_prefix("PRETTY_NAME=")
+            .map(|value| value.trim_matches('"').to_string())
+    })
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+fn os_version() -> Option<String> {
+    None
+}
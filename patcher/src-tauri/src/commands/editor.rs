@@ -0,0 +1,121 @@
+// 支持的编辑器
+// 各编辑器的检测与补丁流程一致, 差异 (hook 文件, 注册表匹配串, 安装目录名等) 集中在这里
+
+use serde::{Deserialize, Serialize};
+
+/// 可打补丁的编辑器
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Editor {
+    Antigravity,
+    Windsurf,
+    Cursor,
+}
+
+impl Editor {
+    /// 所有支持的编辑器
+    pub const ALL: [Editor; 3] = [Editor::Antigravity, Editor::Windsurf, Editor::Cursor];
+
+    /// 显示名称, 用于错误信息
+    pub fn name(self) -> &'static str {
+        match self {
+            Editor::Antigravity => "Antigravity",
+            Editor::Windsurf => "Windsurf",
+            Editor::Cursor => "Cursor",
+        }
+    }
+
+    /// 补丁挂载的 hook 文件 (相对安装目录), 存在即视为该编辑器的安装目录
+    pub fn hook_file(self) -> &'static str {
+        match self {
+            Editor::Antigravity => "resources/app/extensions/antigravity/cascade-panel.html",
+            Editor::Windsurf | Editor::Cursor => {
+                "resources/app/out/vs/code/electron-browser/workbench/workbench.html"
+            }
+        }
+    }
+
+    /// 覆盖 workbench.html 的补丁模板; None 表示在原文件中注入面板标签
+    pub fn workbench_template(self) -> Option<&'static str> {
+        match self {
+            Editor::Antigravity => Some("workbench-antigravity.html"),
+            Editor::Windsurf => Some("workbench-windsurf.html"),
+            Editor::Cursor => None,
+        }
+    }
+
+    /// 默认安装目录名 (Program Files 与 %LOCALAPPDATA%\Programs 下), 可并存的各发布渠道各一个
+    #[cfg(target_os = "windows")]
+    pub fn install_dir_names(self) -> &'static [&'static str] {
+        match self {
+            Editor::Antigravity => &["Antigravity"],
+            Editor::Windsurf => &["Windsurf", "Windsurf - Next"],
+            Editor::Cursor => &["Cursor"],
+        }
+    }
+
+    /// Scoop / Chocolatey 中的包名, 可并存的各发布渠道各一个
+    #[cfg(target_os = "windows")]
+    pub fn package_names(self) -> &'static [&'static str] {
+        match self {
+            Editor::Antigravity => &["antigravity"],
+            Editor::Windsurf => &["windsurf", "windsurf-next"],
+            Editor::Cursor => &["cursor"],
+        }
+    }
+
+    /// 注册表卸载项 DisplayName 需要包含的关键字 (小写)
+    #[cfg(target_os = "windows")]
+    pub fn registry_match(self) -> &'static str {
+        match self {
+            Editor::Antigravity => "antigravity",
+            Editor::Windsurf => "windsurf",
+            Editor::Cursor => "cursor",
+        }
+    }
+
+    /// DisplayName 带有这些关键字的卸载项不是编辑器本体 (账号助手, 更新器等)
+    #[cfg(target_os = "windows")]
+    pub fn registry_excludes(self) -> &'static [&'static str] {
+        match self {
+            Editor::Antigravity => &[],
+            Editor::Windsurf => &["account", "assistant"],
+            Editor::Cursor => &["helper", "updater"],
+        }
+    }
+
+    /// macOS 应用包名, 可并存的各发布渠道各一个
+    #[cfg(target_os = "macos")]
+    pub fn app_bundles(self) -> &'static [&'static str] {
+        match self {
+            Editor::Antigravity => &["Antigravity.app"],
+            Editor::Windsurf => &["Windsurf.app", "Windsurf - Next.app"],
+            Editor::Cursor => &["Cursor.app"],
+        }
+    }
+
+    /// Flatpak 应用 ID
+    #[cfg(target_os = "linux")]
+    pub fn flatpak_id(self) -> &'static str {
+        match self {
+            Editor::Antigravity => "com.google.Antigravity",
+            Editor::Windsurf => "com.codeium.Windsurf",
+            Editor::Cursor => "com.cursor.Cursor",
+        }
+    }
+
+    /// Snap 包名
+    #[cfg(target_os = "linux")]
+    pub fn snap_name(self) -> &'static str {
+        match self {
+            Editor::Antigravity => "antigravity",
+            Editor::Windsurf => "windsurf",
+            Editor::Cursor => "cursor",
+        }
+    }
+
+    /// 主进程名 (不含 .exe 后缀)
+    pub fn process_name(self) -> &'static str {
+        self.name()
+    }
+}
@@ -0,0 +1,147 @@
+// 补丁命令错误类型
+// 序列化为 { kind, detail }, 前端按 kind 区分错误而不必匹配错误文本
+
+use serde::Serialize;
+use std::io;
+use std::path::Path;
+
+use super::plan::ApplyError;
+
+/// 补丁操作错误
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", content = "detail", rename_all = "camelCase")]
+pub enum PatchError {
+    /// 安装目录不存在或不是对应编辑器的安装目录
+    PathNotFound,
+    /// 安装目录已被删除 (编辑器已从系统卸载), 可用 forget_install 清理补丁器记录的路径与备份
+    InstallRemoved { path: String },
+    /// 安装目录缺少必需文件 (例如自动更新中途失败)
+    IncompleteInstall { missing: Vec<String> },
+    /// 编辑器的应用代码打包在 app.asar 中, 补丁需要未打包 (resources/app 目录) 的版本
+    PackagedAsAsar,
+    /// 编辑器正在运行, 需要先关闭 (或使用 force 强制继续)
+    EditorRunning,
+    /// 另一个安装/卸载操作正在进行
+    Busy,
+    /// 操作被用户取消, 已做的改动已撤销
+    Cancelled,
+    /// 没有目标路径的写入权限 (Windows 上通常是 Program Files 下未以管理员身份运行)
+    PermissionDenied { path: String },
+    /// 补丁尚未安装 (更新配置时)
+    NotPatched,
+    /// 所有检测到的安装中都没有找到补丁配置, searched 为查找过的安装目录
+    NotFound { searched: Vec<String> },
+    /// 磁盘剩余空间不足以备份原版文件 (单位: 字节)
+    InsufficientSpace { needed: u64, available: u64 },
+    /// 备份原版文件失败
+    BackupFailed(String),
+    /// 指定的备份集不存在 (可能已被清理)
+    BackupNotFound { id: String },
+    /// 备份来自另一个编辑器版本 (备份后编辑器更新过), 用它还原会把文件降级
+    BackupVersionMismatch {
+        #[serde(rename = "backupVersion")]
+        backup_version: String,
+        #[serde(rename = "currentVersion")]
+        current_version: String,
+    },
+    /// 其他文件读写错误
+    Io(String),
+    /// 写入中途失败, 已写入的文件均已撤销; rollback_errors 为撤销时遇到的错误 (非空时磁盘上可能残留部分改动)
+    RolledBack {
+        error: String,
+        #[serde(rename = "rollbackErrors")]
+        rollback_errors: Vec<String>,
+    },
+    /// 内置补丁不支持当前编辑器版本, supported 为支持的版本范围
+    VersionIncompatible { found: String, supported: Vec<String> },
+    /// 内置补丁内容与编译时记录的摘要不一致 (构建产物损坏)
+    PayloadCorrupted { expected: String, actual: String },
+    /// 目标文件中找不到补丁的注入锚点 (编辑器更新改变了文件结构)
+    AnchorNotFound { anchor: String },
+    /// 安装中残留旧版独立脚本的注入, 需要先迁移 (migrate_from_legacy_patch)
+    LegacyPatchFound { files: Vec<String> },
+    /// 卸载后重新扫描, 仍有文件带有补丁标记 (剥离补丁标记未能完全清除注入内容)
+    ResidualMarkersFound { files: Vec<String> },
+    /// 配置中的接口地址不合法 (无法解析或不是 http(s))
+    InvalidEndpoint { url: String, message: String },
+    /// 无法连接接口地址, cause 区分失败环节
+    EndpointUnreachable { cause: EndpointFailure, message: String },
+}
+
+/// 连接接口地址失败的环节
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EndpointFailure {
+    /// 地址格式错误或不是 http(s)
+    InvalidUrl,
+    /// 域名解析失败
+    Dns,
+    /// 建立连接失败 (拒绝连接, 网络不可达, 代理错误)
+    Connect,
+    /// TLS 握手或证书校验失败
+    Tls,
+    /// 超时未响应
+    Timeout,
+}
+
+impl PatchError {
+    /// 带上下文包装 io::Error, 权限不足 (含 Windows ERROR_ACCESS_DENIED) 单独归类并带上路径
+    pub fn io(path: &Path, context: impl std::fmt::Display, error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::PermissionDenied => PatchError::PermissionDenied {
+                path: path.to_string_lossy().to_string(),
+            },
+            _ => PatchError::Io(format!("{} {:?}: {}", context, path, error)),
+        }
+    }
+}
+
+impl From<ApplyError> for PatchError {
+    fn from(error: ApplyError) -> Self {
+        match error {
+            ApplyError::BeforeWrite(error) => error,
+            ApplyError::RolledBack { error, rollback_errors } => {
+                PatchError::RolledBack { error, rollback_errors }
+            }
+            ApplyError::Cancelled { rollback_errors } if rollback_errors.is_empty() => {
+                PatchError::Cancelled
+            }
+            ApplyError::Cancelled { rollback_errors } => PatchError::Io(format!(
+                "已取消 (回滚未完成: {})",
+                rollback_errors.join("; ")
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rolled_back_apply_keeps_its_own_kind() {
+        let error = PatchError::from(ApplyError::RolledBack {
+            error: "写入文件失败".to_string(),
+            rollback_errors: vec!["a.html".to_string()],
+        });
+
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({
+                "kind": "rolledBack",
+                "detail": { "error": "写入文件失败", "rollbackErrors": ["a.html"] }
+            })
+        );
+    }
+
+    #[test]
+    fn access_denied_is_reported_with_the_path() {
+        let error = PatchError::io(
+            Path::new("/app/workbench.html"),
+            "读取文件失败",
+            io::Error::from(io::ErrorKind::PermissionDenied),
+        );
+
+        assert!(matches!(error, PatchError::PermissionDenied { ref path } if path == "/app/workbench.html"));
+    }
+}
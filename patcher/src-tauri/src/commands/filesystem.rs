@@ -0,0 +1,330 @@
+// 文件系统访问
+// 补丁计划的读取, 写入与回滚都经由 FileSystem 进行, 执行与回滚逻辑不直接绑定真实磁盘
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use super::plan::retry_locked;
+
+/// 补丁计划用到的文件操作
+pub trait FileSystem {
+    fn exists(&self, path: &Path) -> bool;
+    fn is_dir(&self, path: &Path) -> bool;
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+    /// 整体替换文件内容, 替换过程中目标文件不会处于写了一半的状态
+    fn write(&self, path: &Path, content: &[u8]) -> io::Result<()>;
+    /// 已存在的文件能否以写方式打开 (未被占用, 非只读)
+    fn check_writable(&self, path: &Path) -> io::Result<()>;
+    fn create_dir_all(&self, dir: &Path) -> io::Result<()>;
+    fn remove_file(&self, path: &Path) -> io::Result<()>;
+    /// 删除空目录
+    fn remove_dir(&self, dir: &Path) -> io::Result<()>;
+    fn remove_dir_all(&self, dir: &Path) -> io::Result<()>;
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+    /// 目录下的直接子项
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<PathBuf>>;
+}
+
+/// 借用的文件系统, 调用方在计划执行后仍可检查其中的内容
+impl<T: FileSystem + ?Sized> FileSystem for &T {
+    fn exists(&self, path: &Path) -> bool {
+        (**self).exists(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        (**self).is_dir(path)
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        (**self).read(path)
+    }
+
+    fn write(&self, path: &Path, content: &[u8]) -> io::Result<()> {
+        (**self).write(path, content)
+    }
+
+    fn check_writable(&self, path: &Path) -> io::Result<()> {
+        (**self).check_writable(path)
+    }
+
+    fn create_dir_all(&self, dir: &Path) -> io::Result<()> {
+        (**self).create_dir_all(dir)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        (**self).remove_file(path)
+    }
+
+    fn remove_dir(&self, dir: &Path) -> io::Result<()> {
+        (**self).remove_dir(dir)
+    }
+
+    fn remove_dir_all(&self, dir: &Path) -> io::Result<()> {
+        (**self).remove_dir_all(dir)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        (**self).rename(from, to)
+    }
+
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        (**self).read_dir(dir)
+    }
+}
+
+/// 真实磁盘
+#[derive(Default, Clone, Copy)]
+pub struct DiskFs;
+
+impl FileSystem for DiskFs {
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn write(&self, path: &Path, content: &[u8]) -> io::Result<()> {
+        write_via_temp(path, content, |temp, path| retry_locked(|| fs::rename(temp, path)))
+    }
+
+    fn check_writable(&self, path: &Path) -> io::Result<()> {
+        fs::OpenOptions::new().append(true).open(path).map(drop)
+    }
+
+    fn create_dir_all(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn remove_dir(&self, dir: &Path) -> io::Result<()> {
+        fs::remove_dir(dir)
+    }
+
+    fn remove_dir_all(&self, dir: &Path) -> io::Result<()> {
+        fs::remove_dir_all(dir)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        fs::read_dir(dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect()
+    }
+}
+
+/// 先写临时文件并落盘再用 rename 替换, 避免进程被杀或断电时目标文件只写了一半
+/// 临时文件与目标在同一目录, rename 在同一文件系统上是原子的; 任一步失败时删除临时文件, 目标保持原样
+fn write_via_temp(
+    path: &Path,
+    content: &[u8],
+    rename: impl FnOnce(&Path, &Path) -> io::Result<()>,
+) -> io::Result<()> {
+    let temp = PathBuf::from(format!("{}.tmp", path.to_string_lossy()));
+    // 新文件替换原文件, 沿用原文件的权限 (如 Unix 上的可执行位)
+    let permissions = fs::metadata(path).ok().map(|metadata| metadata.permissions());
+    let written = retry_locked(|| {
+        let mut file = fs::File::create(&temp)?;
+        file.write_all(content)?;
+        file.sync_all()?;
+        match &permissions {
+            Some(permissions) => file.set_permissions(permissions.clone()),
+            None => Ok(()),
+        }
+    })
+    .and_then(|()| rename(&temp, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    written
+}
+
+/// 测试用的内存文件系统
+#[cfg(test)]
+pub mod memory {
+    use std::cell::RefCell;
+    use std::collections::{HashMap, HashSet};
+    use std::io;
+    use std::path::{Path, PathBuf};
+
+    use super::FileSystem;
+
+    /// 内存中的文件系统, 供测试在不接触磁盘的情况下执行补丁计划
+    /// 目录只记录显式创建的, 含有文件的路径的上级也视为目录
+    #[derive(Default)]
+    pub struct MemoryFs {
+        files: RefCell<HashMap<PathBuf, Vec<u8>>>,
+        dirs: RefCell<HashSet<PathBuf>>,
+        /// 写入这些路径时返回错误, 用于模拟写入中途失败
+        failing_writes: RefCell<HashSet<PathBuf>>,
+    }
+
+    impl MemoryFs {
+        pub fn with_files<P: Into<PathBuf>, C: Into<Vec<u8>>>(
+            files: impl IntoIterator<Item = (P, C)>,
+        ) -> Self {
+            let file_system = Self::default();
+            for (path, content) in files {
+                file_system.files.borrow_mut().insert(path.into(), content.into());
+            }
+            file_system
+        }
+
+        /// 文件当前的内容
+        pub fn file(&self, path: impl AsRef<Path>) -> Option<Vec<u8>> {
+            self.files.borrow().get(path.as_ref()).cloned()
+        }
+
+        /// 之后写入该路径时失败
+        pub fn fail_writes_to(&self, path: impl Into<PathBuf>) {
+            self.failing_writes.borrow_mut().insert(path.into());
+        }
+
+        /// 所有文件的路径
+        pub fn paths(&self) -> Vec<PathBuf> {
+            let mut paths: Vec<PathBuf> = self.files.borrow().keys().cloned().collect();
+            paths.sort();
+            paths
+        }
+    }
+
+    impl FileSystem for MemoryFs {
+        fn exists(&self, path: &Path) -> bool {
+            self.files.borrow().contains_key(path) || self.is_dir(path)
+        }
+
+        fn is_dir(&self, path: &Path) -> bool {
+            self.dirs.borrow().contains(path)
+                || self.files.borrow().keys().any(|file| file != path && file.starts_with(path))
+        }
+
+        fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+            self.file(path).ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+        }
+
+        fn write(&self, path: &Path, content: &[u8]) -> io::Result<()> {
+            if self.failing_writes.borrow().contains(path) {
+                return Err(io::Error::other("模拟写入失败"));
+            }
+            if path.parent().is_some_and(|parent| !self.is_dir(parent)) {
+                return Err(io::Error::from(io::ErrorKind::NotFound));
+            }
+            self.files.borrow_mut().insert(path.to_path_buf(), content.to_vec());
+            Ok(())
+        }
+
+        fn check_writable(&self, path: &Path) -> io::Result<()> {
+            self.read(path).map(drop)
+        }
+
+        fn create_dir_all(&self, dir: &Path) -> io::Result<()> {
+            self.dirs.borrow_mut().extend(dir.ancestors().map(Path::to_path_buf));
+            Ok(())
+        }
+
+        fn remove_file(&self, path: &Path) -> io::Result<()> {
+            self.files
+                .borrow_mut()
+                .remove(path)
+                .map(drop)
+                .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+        }
+
+        fn remove_dir(&self, dir: &Path) -> io::Result<()> {
+            if !self.read_dir(dir)?.is_empty() {
+                return Err(io::Error::other("目录不为空"));
+            }
+            self.dirs.borrow_mut().remove(dir);
+            Ok(())
+        }
+
+        fn remove_dir_all(&self, dir: &Path) -> io::Result<()> {
+            self.files.borrow_mut().retain(|path, _| !path.starts_with(dir));
+            self.dirs.borrow_mut().retain(|path| !path.starts_with(dir));
+            Ok(())
+        }
+
+        fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+            if !self.exists(from) {
+                return Err(io::Error::from(io::ErrorKind::NotFound));
+            }
+            let moved = |path: &Path| match path.strip_prefix(from) {
+                Ok(rest) if rest.as_os_str().is_empty() => Some(to.to_path_buf()),
+                Ok(rest) => Some(to.join(rest)),
+                Err(_) => None,
+            };
+            let files = std::mem::take(&mut *self.files.borrow_mut());
+            *self.files.borrow_mut() = files
+                .into_iter()
+                .map(|(path, content)| (moved(&path).unwrap_or(path), content))
+                .collect();
+            let dirs = std::mem::take(&mut *self.dirs.borrow_mut());
+            *self.dirs.borrow_mut() =
+                dirs.into_iter().map(|path| moved(&path).unwrap_or(path)).collect();
+            Ok(())
+        }
+
+        fn read_dir(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+            if !self.is_dir(dir) {
+                return Err(io::Error::from(io::ErrorKind::NotFound));
+            }
+            let files = self.files.borrow();
+            let dirs = self.dirs.borrow();
+            let mut entries: Vec<PathBuf> = files
+                .keys()
+                .chain(dirs.iter())
+                .filter_map(|path| path.strip_prefix(dir).ok()?.components().next())
+                .map(|child| dir.join(child))
+                .collect();
+            entries.sort();
+            entries.dedup();
+            Ok(entries)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_rename_keeps_previous_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        DiskFs.write(&path, br#"{"schemaVersion": 8, "backupRetention": 3}"#).unwrap();
+
+        // 临时文件已写完, 替换前失败 (相当于进程在 rename 前被杀)
+        let result = write_via_temp(&path, br#"{"schemaVersion": 8, "backupRet"#, |temp, _| {
+            assert!(temp.is_file());
+            Err(io::Error::other("rename 前中断"))
+        });
+
+        assert!(result.is_err());
+        let config: serde_json::Value =
+            serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(config["backupRetention"], 3);
+        assert!(!dir.path().join("config.json.tmp").exists());
+    }
+
+    #[test]
+    fn write_replaces_whole_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        DiskFs.write(&path, b"old content that is longer").unwrap();
+        DiskFs.write(&path, b"new").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        assert!(!dir.path().join("config.json.tmp").exists());
+    }
+}
@@ -0,0 +1,57 @@
+// 清理已卸载编辑器的补丁器记录
+// 编辑器从系统中卸载后, 配置中的安装路径与应用数据目录中的备份仍指向已不存在的目录; 卸载补丁此时返回 InstallRemoved
+
+use serde::Serialize;
+use std::path::Path;
+use tauri::State;
+
+use super::backup::remove_install_backups;
+use super::config::forget_install_path;
+use super::detect::DetectionCache;
+use super::editor::Editor;
+use super::error::PatchError;
+use super::lock::PatchLock;
+
+/// 清理结果
+#[derive(Debug, Serialize)]
+pub struct ForgetReport {
+    pub editor: Editor,
+    pub path: String,
+    /// 从配置中清除的字段
+    #[serde(rename = "configFields")]
+    pub config_fields: Vec<String>,
+    /// 删除的备份目录
+    #[serde(rename = "removedBackups")]
+    pub removed_backups: Vec<String>,
+}
+
+/// 忘记已删除的安装: 清除配置中指向它的路径并删除它的备份, 不触碰其他安装
+/// 安装目录仍然存在时拒绝清理, 应改用卸载补丁
+#[tauri::command]
+pub fn forget_install(
+    cache: State<'_, DetectionCache>,
+    editor: Editor,
+    path: String,
+) -> Result<ForgetReport, PatchError> {
+    if Path::new(&path).exists() {
+        return Err(PatchError::Io(format!("安装目录仍然存在, 请改用恢复原版: {}", path)));
+    }
+
+    let _lock = PatchLock::acquire()?;
+    let config_fields = forget_install_path(&cache, editor, &path).map_err(PatchError::Io)?;
+    let removed_backups =
+        remove_install_backups(editor, Path::new(&path)).map_err(PatchError::BackupFailed)?;
+    log::info!(
+        "已忘记 {} 安装 {}: 清除配置 {:?}, 删除备份 {:?}",
+        editor.name(),
+        path,
+        config_fields,
+        removed_backups
+    );
+    Ok(ForgetReport {
+        editor,
+        path,
+        config_fields,
+        removed_backups,
+    })
+}
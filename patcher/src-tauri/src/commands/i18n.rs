@@ -0,0 +1,68 @@
+// 界面语言与消息表
+// 命令在机器可读的结果之外附带一句按配置语言 (config.language) 生成的说明, 供命令行与日志直接显示
+
+use super::config::read_config;
+use super::status::PatchState;
+
+/// 支持的语言, 未知的语言代码回退到英文
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    English,
+    Chinese,
+}
+
+impl Language {
+    /// 按语言代码的主标签识别 (如 zh-CN, zh_TW 均为中文), 不区分大小写
+    pub fn from_code(code: &str) -> Self {
+        let primary = code.split(['-', '_']).next().unwrap_or_default();
+        if primary.eq_ignore_ascii_case("zh") {
+            Language::Chinese
+        } else {
+            Language::English
+        }
+    }
+}
+
+/// 配置中的界面语言
+pub fn configured_language() -> Language {
+    Language::from_code(&read_config().language)
+}
+
+/// 补丁状态的说明: (状态, 英文, 中文)
+const STATUS_SUMMARIES: &[(PatchState, &str, &str)] = &[
+    (PatchState::NotPatched, "The patch is not installed.", "补丁未安装。"),
+    (
+        PatchState::Partial,
+        "The patch is only partly installed; reinstall it.",
+        "补丁不完整，请重新安装。",
+    ),
+    (PatchState::Patched, "The patch is installed.", "补丁已安装。"),
+    (
+        PatchState::PatchedByOlderVersion,
+        "The patch was installed by an older patcher; reinstall it before restoring the original.",
+        "补丁由旧版本补丁器安装，建议先重新安装再恢复原版。",
+    ),
+    (
+        PatchState::RevertedByUpdate,
+        "An editor update reverted the patch; reinstall it.",
+        "补丁已被编辑器更新还原，请重新安装。",
+    ),
+    (
+        PatchState::ConfigPayloadMismatch,
+        "The patch config does not match the installed patch; save the config again.",
+        "补丁配置与补丁版本不匹配，请重新保存配置。",
+    ),
+];
+
+/// 补丁状态的说明
+pub fn status_summary(state: PatchState, language: Language) -> String {
+    STATUS_SUMMARIES
+        .iter()
+        .find(|(entry, _, _)| *entry == state)
+        .map(|(_, english, chinese)| match language {
+            Language::English => *english,
+            Language::Chinese => *chinese,
+        })
+        .unwrap_or_default()
+        .to_string()
+}
@@ -0,0 +1,74 @@
+// 编辑器标识与遥测设置
+// 只读显示打过补丁的编辑器上报的标识: 机器 ID 等存于 <用户数据目录>/User/globalStorage/storage.json,
+// 遥测级别存于用户设置; 旧版把机器 ID 单独存于 <用户数据目录>/machineid
+
+use serde::Serialize;
+use serde_json::Value;
+use std::fs;
+use tauri::State;
+
+use super::editor::Editor;
+use super::detect::DetectionCache;
+use super::user_data::{first_install, read_json, read_product, settings_path, user_data_dir};
+
+/// 编辑器上报的标识, 新安装尚未生成的字段为空
+#[derive(Debug, Default, Serialize)]
+pub struct EditorIdentity {
+    #[serde(rename = "userDataDir")]
+    pub user_data_dir: Option<String>,
+    #[serde(rename = "machineId")]
+    pub machine_id: Option<String>,
+    #[serde(rename = "macMachineId")]
+    pub mac_machine_id: Option<String>,
+    #[serde(rename = "devDeviceId")]
+    pub dev_device_id: Option<String>,
+    #[serde(rename = "sqmId")]
+    pub sqm_id: Option<String>,
+    /// 用户设置中的 telemetry.telemetryLevel (all, error, crash, off), 未设置时为空
+    #[serde(rename = "telemetryLevel")]
+    pub telemetry_level: Option<String>,
+}
+
+/// 读取检测到的安装的标识与遥测设置; 存储文件尚不存在 (新安装) 时对应字段为空
+#[tauri::command]
+pub async fn read_editor_identity(
+    cache: State<'_,
+    DetectionCache>,
+    editor: Editor,
+) -> Result<EditorIdentity, String> {
+    let install_path = first_install(&cache, editor)
+        .await
+        .ok_or_else(|| format!("未检测到 {} 安装", editor.name()))?;
+    let product = read_product(&install_path).unwrap_or(Value::Null);
+    let Some(user_data) = user_data_dir(editor, &install_path, &product) else {
+        return Ok(EditorIdentity::default());
+    };
+
+    let storage = read_json(&user_data.join("User").join("globalStorage").join("storage.json"))
+        .unwrap_or(Value::Null);
+    let text = |value: &Value, key: &str| {
+        value
+            .get(key)
+            .and_then(Value::as_str)
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+    };
+    let legacy_machine_id = || {
+        fs::read_to_string(user_data.join("machineid"))
+            .ok()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    };
+    let telemetry_level = settings_path(editor, &install_path, &product)
+        .and_then(|path| read_json(&path))
+        .and_then(|settings| text(&settings, "telemetry.telemetryLevel"));
+
+    Ok(EditorIdentity {
+        machine_id: text(&storage, "telemetry.machineId").or_else(legacy_machine_id),
+        mac_machine_id: text(&storage, "telemetry.macMachineId"),
+        dev_device_id: text(&storage, "telemetry.devDeviceId"),
+        sqm_id: text(&storage, "telemetry.sqmId"),
+        telemetry_level,
+        user_data_dir: Some(user_data.to_string_lossy().to_string()),
+    })
+}
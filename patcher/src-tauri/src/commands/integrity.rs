@@ -0,0 +1,77 @@
+// 补丁文件完整性校验
+// 安装时记录补丁写入文件的 SHA-256, 之后可重新计算以发现被编辑器更新等外部改动覆盖的文件
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use super::backup::resolve_relative;
+use super::filesystem::FileSystem;
+use super::plan::PatchPlan;
+
+/// 补丁配置文件名, 配置本身会随 update_config 改变, 不参与校验
+const CONFIG_FILE_NAME: &str = "config.json";
+
+/// 单个文件的校验结果
+#[derive(Debug, Serialize)]
+pub struct FileIntegrity {
+    /// 相对安装目录的路径
+    pub path: String,
+    /// 安装时记录的摘要
+    pub expected: String,
+    /// 当前文件的摘要, 文件不存在时为 None
+    pub actual: Option<String>,
+    /// 当前内容与安装时一致
+    pub matches: bool,
+}
+
+/// 计算内容的 SHA-256 (小写十六进制)
+pub fn sha256_hex(content: &[u8]) -> String {
+    Sha256::digest(content)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// 计算计划中所有补丁文件 (配置文件除外) 的摘要, 以相对安装目录的路径为键
+pub fn planned_file_hashes<F: FileSystem>(
+    plan: &PatchPlan<F>,
+    install_path: &Path,
+) -> BTreeMap<String, String> {
+    plan.files
+        .iter()
+        .filter(|file| file.path.file_name().is_some_and(|name| name != CONFIG_FILE_NAME))
+        .filter_map(|file| {
+            let relative = file.path.strip_prefix(install_path).ok()?;
+            let key = relative
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            Some((key, sha256_hex(&file.content)))
+        })
+        .collect()
+}
+
+/// 重新计算磁盘上的文件摘要并与记录值比对
+pub fn verify_file_hashes(
+    install_path: &Path,
+    file_hashes: &BTreeMap<String, String>,
+) -> Vec<FileIntegrity> {
+    file_hashes
+        .iter()
+        .map(|(relative_path, expected)| {
+            let actual = fs::read(resolve_relative(install_path, relative_path))
+                .ok()
+                .map(|content| sha256_hex(&content));
+            FileIntegrity {
+                path: relative_path.clone(),
+                expected: expected.clone(),
+                matches: actual.as_deref() == Some(expected.as_str()),
+                actual,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,115 @@
+// JSON 文件按键修改
+// 解析后只改动指定的键再序列化, 不做文本注入: 文件被重新格式化后补丁依然有效, 其余键和键顺序保持不变
+
+use serde::Serialize;
+use serde_json::ser::PrettyFormatter;
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::Path;
+
+/// 去掉其他编辑器或工具写入的 UTF-8 BOM (serde_json 不接受)
+pub fn strip_bom(content: &str) -> &str {
+    content.strip_prefix('\u{feff}').unwrap_or(content)
+}
+
+/// 单个键的修改: 把 `path` (从根对象开始的各级键名) 处已存在的值替换为 `value`, 键不存在时不添加
+pub struct JsonEdit<'a> {
+    pub path: &'a [&'a str],
+    pub value: Value,
+}
+
+/// 对文件现有内容 raw 按顺序应用修改, 返回需要写回的新内容 (path 只用于错误信息)
+///
+/// 修改后内容不变时返回 None; 缩进, UTF-8 BOM 与末尾换行沿用原文件
+pub fn edited_json(path: &Path, raw: &[u8], edits: &[JsonEdit]) -> Result<Option<String>, String> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let raw = std::str::from_utf8(raw).map_err(|e| format!("读取 {} 失败: {}", name, e))?;
+    let content = strip_bom(raw);
+
+    let original: Value =
+        serde_json::from_str(content).map_err(|e| format!("解析 {} 失败: {}", name, e))?;
+    let mut json = original.clone();
+    for edit in edits {
+        apply_edit(&mut json, edit);
+    }
+    if json == original {
+        return Ok(None);
+    }
+
+    serialize_like(raw, &json, &name).map(Some)
+}
+
+/// 在根对象中设置 `key`, 键不存在时添加, 返回需要写回的新内容; 文件不存在时新建只含该键的对象
+pub fn with_root_key(path: &Path, key: &str, value: Value) -> Result<String, String> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let raw = if path.exists() {
+        fs::read_to_string(path).map_err(|e| format!("读取 {} 失败: {}", name, e))?
+    } else {
+        "{}\n".to_string()
+    };
+
+    let mut json = if strip_bom(&raw).trim().is_empty() {
+        Value::Object(Map::new())
+    } else {
+        serde_json::from_str(strip_bom(&raw))
+            .map_err(|e| format!("解析 {} 失败: {}", name, e))?
+    };
+    json.as_object_mut()
+        .ok_or_else(|| format!("{} 不是 JSON 对象", name))?
+        .insert(key.to_string(), value);
+
+    serialize_like(&raw, &json, &name)
+}
+
+/// 按原文件的缩进, UTF-8 BOM 与末尾换行序列化
+fn serialize_like(raw: &str, json: &Value, name: &str) -> Result<String, String> {
+    let bom = raw.starts_with('\u{feff}');
+    let content = strip_bom(raw);
+    let indent = detect_indent(content);
+    let mut output = Vec::new();
+    let mut serializer =
+        serde_json::Serializer::with_formatter(&mut output, PrettyFormatter::with_indent(indent.as_bytes()));
+    json.serialize(&mut serializer)
+        .map_err(|e| format!("序列化 {} 失败: {}", name, e))?;
+
+    let mut result = String::from_utf8(output).map_err(|e| format!("序列化 {} 失败: {}", name, e))?;
+    if bom {
+        result.insert(0, '\u{feff}');
+    }
+    if content.ends_with('\n') {
+        result.push('\n');
+    }
+    Ok(result)
+}
+
+fn apply_edit(json: &mut Value, edit: &JsonEdit) {
+    let Some((key, parents)) = edit.path.split_last() else {
+        return;
+    };
+    if let Some(existing) = object_at(json, parents).and_then(|object| object.get_mut(*key)) {
+        *existing = edit.value.clone();
+    }
+}
+
+/// 定位到 `path` 对应的对象, 路径上缺少键或遇到非对象值时返回 None
+fn object_at<'v>(json: &'v mut Value, path: &[&str]) -> Option<&'v mut Map<String, Value>> {
+    let mut current = json.as_object_mut()?;
+    for key in path {
+        current = current.get_mut(*key)?.as_object_mut()?;
+    }
+    Some(current)
+}
+
+/// 取第一个有缩进的行作为整个文件的缩进 (VS Code 系的 product.json 使用制表符), 没有缩进时用两个空格
+fn detect_indent(content: &str) -> String {
+    content
+        .lines()
+        .skip(1)
+        .map(|line| {
+            let trimmed = line.trim_start_matches([' ', '\t']);
+            &line[..line.len() - trimmed.len()]
+        })
+        .find(|indent| !indent.is_empty())
+        .unwrap_or("  ")
+        .to_string()
+}
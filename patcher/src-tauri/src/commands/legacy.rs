@@ -0,0 +1,311 @@
+// 旧版独立补丁脚本的残留
+// 早期的独立脚本用另一套注释块与资源文件名注入 HTML, 本补丁器识别不到它们, 两套注入同时存在时会冲突
+// 迁移时先把带有旧注入的文件备份到应用数据目录, 再剥离旧注入, 之后可以正常安装补丁
+
+use serde::Serialize;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::State;
+
+use super::backup::resolve_relative;
+use super::bytes;
+use super::config::app_data_dir;
+use super::editor::Editor;
+use super::error::PatchError;
+use super::lock::PatchLock;
+use super::patch::STATUS_FILES;
+use super::plan::PatchPlan;
+use super::process::is_editor_running;
+use super::detect::DetectionCache;
+use super::user_data::first_install;
+
+/// 旧版脚本的注入特征
+enum LegacySignature {
+    /// 成对的注释块, 块内全部内容都是旧版注入
+    Block { name: &'static str, start: &'static str, end: &'static str },
+    /// 引用旧版资源文件的 `<script>` / `<link>` 标签
+    Tag { name: &'static str, reference: &'static str },
+}
+
+/// 旧版脚本写入的全部特征; 发现新的旧版注入方式时在此登记
+const LEGACY_SIGNATURES: &[LegacySignature] = &[
+    LegacySignature::Block {
+        name: "旧版注释块",
+        start: "<!-- Antigravity Power START -->",
+        end: "<!-- Antigravity Power END -->",
+    },
+    LegacySignature::Tag { name: "旧版脚本", reference: "antigravity-power.js" },
+    LegacySignature::Tag { name: "旧版样式", reference: "antigravity-power.css" },
+];
+
+/// 单处旧版注入
+#[derive(Debug, Serialize)]
+pub struct LegacyInjection {
+    /// 匹配到的特征
+    pub signature: String,
+    /// 在文件中的字节偏移
+    pub offset: u64,
+    /// 注入的原文
+    pub content: String,
+}
+
+/// 单个文件中的旧版注入
+#[derive(Debug, Serialize)]
+pub struct LegacyFile {
+    /// 相对安装目录的路径
+    pub path: String,
+    pub injections: Vec<LegacyInjection>,
+}
+
+/// 旧版补丁检测 / 迁移报告
+#[derive(Debug, Serialize)]
+pub struct LegacyReport {
+    pub editor: Editor,
+    pub path: String,
+    /// 检测时为发现的旧版注入, 迁移时为已清除的旧版注入
+    pub files: Vec<LegacyFile>,
+    /// 迁移前备份文件所在目录, 检测或没有需要清除的内容时为空
+    #[serde(rename = "backupDir")]
+    pub backup_dir: Option<String>,
+}
+
+/// 检测检测到的安装中是否残留旧版脚本的注入
+#[tauri::command]
+pub async fn detect_legacy_patch(
+    cache: State<'_,
+    DetectionCache>,
+    editor: Editor,
+) -> Result<LegacyReport, PatchError> {
+    let install_path = first_install(&cache, editor).await.ok_or(PatchError::PathNotFound)?;
+    let files = scan_install(&install_path);
+    Ok(LegacyReport {
+        editor,
+        path: install_path.to_string_lossy().to_string(),
+        files,
+        backup_dir: None,
+    })
+}
+
+/// 备份并清除旧版脚本的注入, 返回清除的内容; 编辑器运行时拒绝迁移
+#[tauri::command]
+pub async fn migrate_from_legacy_patch(
+    cache: State<'_,
+    DetectionCache>,
+    editor: Editor,
+) -> Result<LegacyReport, PatchError> {
+    let install_path = first_install(&cache, editor).await.ok_or(PatchError::PathNotFound)?;
+    if is_editor_running(editor) {
+        return Err(PatchError::EditorRunning);
+    }
+    let result = tauri::async_runtime::spawn_blocking(move || migrate(editor, &install_path))
+        .await
+        .map_err(|e| PatchError::Io(format!("迁移旧版补丁失败: {}", e)))?;
+    match &result {
+        Ok(report) => log::info!(
+            "{} 旧版补丁迁移完成: 清除 {} 个文件中的注入, 备份于 {:?}",
+            editor.name(),
+            report.files.len(),
+            report.backup_dir
+        ),
+        Err(error) => log::error!("{} 旧版补丁迁移失败: {:?}", editor.name(), error),
+    }
+    result
+}
+
+fn migrate(editor: Editor, install_path: &Path) -> Result<LegacyReport, PatchError> {
+    let _lock = PatchLock::acquire()?;
+    let files = scan_install(install_path);
+    let mut report = LegacyReport {
+        editor,
+        path: install_path.to_string_lossy().to_string(),
+        files: Vec::new(),
+        backup_dir: None,
+    };
+    if files.is_empty() {
+        return Ok(report);
+    }
+
+    let backup_dir = backup_legacy_files(editor, install_path, &files)?;
+    let mut plan = PatchPlan::default();
+    for file in &files {
+        let path = resolve_relative(install_path, &file.path);
+        let content = fs::read(&path)
+            .map_err(|e| PatchError::io(&path, "读取文件失败", e))?;
+        plan.write(path, strip_legacy(&content));
+    }
+    plan.apply()?;
+
+    report.files = files;
+    report.backup_dir = Some(backup_dir.to_string_lossy().to_string());
+    Ok(report)
+}
+
+/// 安装前确认没有旧版注入, 否则返回 LegacyPatchFound; 旧版注入会被当作原版一起备份
+pub fn ensure_no_legacy_patch(install_path: &Path) -> Result<(), PatchError> {
+    let files: Vec<String> = scan_install(install_path).into_iter().map(|file| file.path).collect();
+    if files.is_empty() {
+        Ok(())
+    } else {
+        Err(PatchError::LegacyPatchFound { files })
+    }
+}
+
+/// 扫描补丁涉及的 HTML 文件, 只返回含有旧版注入的文件
+fn scan_install(install_path: &Path) -> Vec<LegacyFile> {
+    STATUS_FILES
+        .iter()
+        .filter(|relative_path| relative_path.ends_with(".html"))
+        .filter_map(|relative_path| {
+            let content = fs::read(resolve_relative(install_path, relative_path)).ok()?;
+            let injections: Vec<LegacyInjection> = legacy_ranges(&content)
+                .into_iter()
+                .map(|(signature, range)| LegacyInjection {
+                    signature: signature.to_string(),
+                    offset: range.start as u64,
+                    content: String::from_utf8_lossy(&content[range]).into_owned(),
+                })
+                .collect();
+            (!injections.is_empty()).then(|| LegacyFile {
+                path: relative_path.to_string(),
+                injections,
+            })
+        })
+        .collect()
+}
+
+/// 把含有旧版注入的文件复制到 <应用数据目录>/backups/legacy/<编辑器>-<时间戳>
+fn backup_legacy_files(
+    editor: Editor,
+    install_path: &Path,
+    files: &[LegacyFile],
+) -> Result<PathBuf, PatchError> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let dir = app_data_dir()
+        .join("backups")
+        .join("legacy")
+        .join(format!("{}-{}", editor.name().to_lowercase(), timestamp));
+
+    for file in files {
+        let source = resolve_relative(install_path, &file.path);
+        let target = resolve_relative(&dir, &file.path);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| PatchError::BackupFailed(format!("创建备份目录失败: {}", e)))?;
+        }
+        fs::copy(&source, &target)
+            .map_err(|e| PatchError::BackupFailed(format!("备份 {} 失败: {}", file.path, e)))?;
+    }
+    Ok(dir)
+}
+
+/// 剥离全部旧版注入: 依次复制各处注入之间的内容, 其余字节原样保留
+fn strip_legacy(content: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(content.len());
+    let mut last = 0;
+    for (_, range) in legacy_ranges(content) {
+        result.extend_from_slice(&content[last..range.start.max(last)]);
+        last = last.max(range.end);
+    }
+    result.extend_from_slice(&content[last..]);
+    result
+}
+
+/// 各处旧版注入的位置, 按偏移排序且互不重叠; 独占一行的注入连同缩进与换行一起计入
+fn legacy_ranges(content: &[u8]) -> Vec<(&'static str, Range<usize>)> {
+    let mut ranges = Vec::new();
+    for signature in LEGACY_SIGNATURES {
+        match signature {
+            LegacySignature::Block { name, start, end } => {
+                let mut from = 0;
+                while let Some(offset) = bytes::find(&content[from..], start.as_bytes()) {
+                    let block_start = from + offset;
+                    let block_end = match bytes::find(&content[block_start..], end.as_bytes()) {
+                        Some(offset) => block_start + offset + end.len(),
+                        None => block_start + start.len(),
+                    };
+                    ranges.push((*name, block_start..block_end));
+                    from = block_end;
+                }
+            }
+            LegacySignature::Tag { name, reference } => {
+                let mut from = 0;
+                while let Some(offset) = bytes::find(&content[from..], reference.as_bytes()) {
+                    let at = from + offset;
+                    from = at + reference.len();
+                    let Some(range) = enclosing_tag(content, at) else {
+                        continue;
+                    };
+                    from = range.end;
+                    ranges.push((*name, range));
+                }
+            }
+        }
+    }
+
+    ranges.sort_by_key(|(_, range)| range.start);
+    let mut merged: Vec<(&'static str, Range<usize>)> = Vec::new();
+    for (name, range) in ranges {
+        match merged.last_mut() {
+            Some((_, last)) if range.start < last.end => last.end = last.end.max(range.end),
+            _ => merged.push((name, range)),
+        }
+    }
+    merged
+        .into_iter()
+        .map(|(name, range)| (name, whole_lines(content, range)))
+        .collect()
+}
+
+/// 包含 at 的 `<script ...>...</script>` 或 `<link ...>` 标签
+fn enclosing_tag(content: &[u8], at: usize) -> Option<Range<usize>> {
+    let start = bytes::rfind(&content[..at], b"<")?;
+    let open_end = at + bytes::find(&content[at..], b">")? + 1;
+    let open_tag = &content[start..open_end];
+    if open_tag.starts_with(b"<script") {
+        let end = bytes::find(&content[open_end..], b"</script>")
+            .map(|offset| open_end + offset + "</script>".len())
+            .unwrap_or(open_end);
+        Some(start..end)
+    } else if open_tag.starts_with(b"<link") {
+        Some(start..open_end)
+    } else {
+        None
+    }
+}
+
+/// 注入前只有缩进、后面紧跟换行时, 把缩进与换行一起去掉, 避免留下空白行
+fn whole_lines(content: &[u8], range: Range<usize>) -> Range<usize> {
+    let line_start = bytes::trim_indent_end(&content[..range.start]).len();
+    let starts_line = line_start == 0 || content[..line_start].ends_with(b"\n");
+    let rest = &content[range.end..];
+    let newline = if rest.starts_with(b"\r\n") {
+        2
+    } else if rest.starts_with(b"\n") {
+        1
+    } else {
+        0
+    };
+    if starts_line && newline > 0 {
+        line_start..range.end + newline
+    } else {
+        range
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_legacy_removes_whole_lines_and_keeps_other_bytes() {
+        let content: &[u8] = b"<head>\xff\r\n  <script src=\"antigravity-power.js\"></script>\r\n\
+            <!-- Antigravity Power START -->x<!-- Antigravity Power END --></head>";
+
+        assert_eq!(strip_legacy(content), b"<head>\xff\r\n</head>".to_vec());
+    }
+}
@@ -0,0 +1,70 @@
+// 补丁操作锁
+// 同一时间只允许一个安装/卸载操作 (重复点击, 多开补丁器), 避免并发写坏同一批文件
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::cancel::{self, Operation};
+use super::config::app_data_dir;
+use super::error::PatchError;
+
+/// 超过该时长的锁文件视为上次异常退出 (进程被杀) 遗留, 直接接管
+const STALE_AFTER: Duration = Duration::from_secs(10 * 60);
+
+/// 持有期间独占补丁操作, 离开作用域 (包括 panic 展开) 时删除锁文件
+/// 持有锁的操作可以被 cancel_operation 取消
+pub struct PatchLock {
+    path: PathBuf,
+    _operation: Operation,
+}
+
+impl PatchLock {
+    /// 获取锁, 已被其他操作持有时返回 Busy
+    pub fn acquire() -> Result<Self, PatchError> {
+        let dir = app_data_dir();
+        fs::create_dir_all(&dir).map_err(|e| PatchError::io(&dir, "创建数据目录失败", e))?;
+        let path = dir.join("patch.lock");
+
+        match Self::create(&path) {
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists && Self::is_stale(&path) => {
+                let _ = fs::remove_file(&path);
+                Self::create(&path)
+            }
+            result => result,
+        }
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::AlreadyExists => PatchError::Busy,
+            _ => PatchError::io(&path, "创建锁文件失败", e),
+        })?;
+
+        Ok(Self {
+            path,
+            _operation: cancel::begin(),
+        })
+    }
+
+    /// 原子创建锁文件, 已存在时失败
+    fn create(path: &Path) -> io::Result<()> {
+        fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .map(|_| ())
+    }
+
+    fn is_stale(path: &Path) -> bool {
+        fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age > STALE_AFTER)
+    }
+}
+
+impl Drop for PatchLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,140 @@
+// 运行日志
+// 记录命令调用, 检测到的路径与错误, 用户反馈问题时可以附上日志文件
+// 日志级别默认 info, 可通过环境变量 ANTIGRAVITY_LOG 调整 (error / warn / info / debug / trace)
+
+use log::{LevelFilter, Log, Metadata, Record};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::config::app_data_dir;
+
+/// 日志级别环境变量
+const LOG_LEVEL_ENV: &str = "ANTIGRAVITY_LOG";
+
+/// 单个日志文件上限, 超过后轮转为 patcher.log.1 (只保留一份旧日志)
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
+
+/// 写入 app 数据目录的文件日志
+struct FileLogger {
+    level: LevelFilter,
+    file: Mutex<Option<LogFile>>,
+}
+
+struct LogFile {
+    file: File,
+    size: u64,
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let line = format!(
+            "[{}] {:<5} {}: {}\n",
+            unix_seconds(),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+
+        let Ok(mut guard) = self.file.lock() else {
+            return;
+        };
+        if guard.as_ref().is_some_and(|log_file| log_file.size >= MAX_LOG_SIZE) {
+            // 先关闭旧文件, Windows 下打开中的文件无法重命名
+            *guard = None;
+            *guard = open_log_file(true);
+        }
+        if let Some(log_file) = guard.as_mut() {
+            if log_file.file.write_all(line.as_bytes()).is_ok() {
+                log_file.size += line.len() as u64;
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut guard) = self.file.lock() {
+            if let Some(log_file) = guard.as_mut() {
+                let _ = log_file.file.flush();
+            }
+        }
+    }
+}
+
+/// 初始化日志, 在应用启动时调用一次; 日志文件打不开时静默放弃记录
+pub fn init() {
+    let level = std::env::var(LOG_LEVEL_ENV)
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(LevelFilter::Info);
+
+    let logger = FileLogger {
+        level,
+        file: Mutex::new(open_log_file(false)),
+    };
+    if log::set_boxed_logger(Box::new(logger)).is_ok() {
+        log::set_max_level(level);
+    }
+    log::info!("补丁器启动, 版本 {}", env!("CARGO_PKG_VERSION"));
+}
+
+/// 日志文件路径
+pub fn log_path() -> PathBuf {
+    app_data_dir().join("logs").join("patcher.log")
+}
+
+/// 打开日志文件, 超过上限 (或 rotate 为 true) 时先轮转
+fn open_log_file(rotate: bool) -> Option<LogFile> {
+    let path = log_path();
+    fs::create_dir_all(path.parent()?).ok()?;
+
+    let size = fs::metadata(&path).map(|metadata| metadata.len()).unwrap_or(0);
+    if rotate || size >= MAX_LOG_SIZE {
+        let _ = fs::rename(&path, path.with_extension("log.1"));
+    }
+
+    let file = OpenOptions::new().create(true).append(true).open(&path).ok()?;
+    let size = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+    Some(LogFile { file, size })
+}
+
+fn unix_seconds() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// 获取日志文件路径
+#[tauri::command]
+pub fn get_log_path() -> String {
+    log_path().to_string_lossy().to_string()
+}
+
+/// 用系统默认程序打开日志文件
+#[tauri::command]
+pub fn open_log() -> Result<(), String> {
+    let path = log_path();
+    if !path.exists() {
+        return Err("日志文件尚未生成".to_string());
+    }
+    tauri_plugin_opener::open_path(&path, None::<&str>)
+        .map_err(|e| format!("打开日志失败: {}", e))
+}
+
+/// 记录命令执行结果: 成功记为 info, 失败记为 error 并附带错误详情
+pub fn log_result<T, E: std::fmt::Debug>(action: &str, path: &str, result: &Result<T, E>) {
+    match result {
+        Ok(_) => log::info!("{}成功: {}", action, path),
+        Err(error) => log::error!("{}失败: {}: {:?}", action, path, error),
+    }
+}
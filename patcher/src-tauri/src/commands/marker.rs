@@ -0,0 +1,142 @@
+// 补丁标记识别与清除
+// 补丁写入的 HTML 中带有固定特征 (注释标记, 面板资源路径), 没有备份时据此剥离注入内容
+
+use std::ops::Range;
+
+use super::bytes;
+
+/// 补丁写入内容中的特征标记, 用于判断文件是否已被打过补丁
+const PATCH_MARKERS: &[&str] = &[
+    "Antigravity-Power-Pro",
+    "./cascade-panel/",
+    "./manager-panel/",
+    "./windsurf-panel/",
+];
+
+/// 补丁注入的面板资源路径, 引用它们的 `<link>` / `<script>` 标签会被剥离
+const PANEL_REFS: &[&str] = &["./cascade-panel/", "./manager-panel/", "./windsurf-panel/"];
+
+/// 注释块标记 (Manager 模板)
+const BLOCK_START: &str = "<!-- Antigravity-Power-Pro 增强开始 -->";
+const BLOCK_END: &str = "<!-- Antigravity-Power-Pro 增强结束 -->";
+
+/// TrustedTypes bypass 脚本标记 (workbench.html 注入)
+pub const CASCADE_INJECT_MARKER: &str = "[Antigravity-Power-Pro] Cascade Inject";
+
+/// 判断文件内容是否包含补丁标记
+pub fn is_patched_content(content: &[u8]) -> bool {
+    PATCH_MARKERS.iter().any(|marker| bytes::contains(content, marker.as_bytes()))
+}
+
+/// 剥离补丁注入的内容, 没有任何改动时返回 None; 标记之外的字节 (包括非 UTF-8 字节) 原样保留
+pub fn strip_patch_markers(content: &[u8]) -> Option<Vec<u8>> {
+    let mut result = content.to_vec();
+
+    // 1. Manager 模板中的注释块
+    while let Some(start) = bytes::find(&result, BLOCK_START.as_bytes()) {
+        let end = match bytes::find(&result[start..], BLOCK_END.as_bytes()) {
+            Some(offset) => start + offset + BLOCK_END.len(),
+            None => start + BLOCK_START.len(),
+        };
+        result.drain(start..end);
+    }
+
+    // 2. TrustedTypes bypass 脚本
+    while let Some(range) = cascade_inject_block(&result) {
+        result.drain(range);
+    }
+
+    // 3. 引用面板资源的 link / script 标签
+    result = strip_panel_tags(&result);
+
+    // 4. 其余 Antigravity-Power-Pro 注释
+    while let Some(start) = bytes::find(&result, b"<!-- Antigravity-Power-Pro") {
+        let end = match bytes::find(&result[start..], b"-->") {
+            Some(offset) => start + offset + "-->".len(),
+            None => break,
+        };
+        result.drain(start..end);
+    }
+
+    (result != content).then_some(result)
+}
+
+/// 第一个 TrustedTypes bypass 脚本块的位置 (含 `<script>` 标签), 缺少结束标签时只取标记本身
+pub fn cascade_inject_block(content: &[u8]) -> Option<Range<usize>> {
+    let marker = bytes::find(content, CASCADE_INJECT_MARKER.as_bytes())?;
+    let start = bytes::rfind(&content[..marker], b"<script").unwrap_or(marker);
+    let end = match bytes::find(&content[marker..], b"</script>") {
+        Some(offset) => marker + offset + "</script>".len(),
+        None => marker + CASCADE_INJECT_MARKER.len(),
+    };
+    Some(start..end)
+}
+
+/// 删除开始标签中引用了面板资源的 `<link ...>` 与 `<script ...></script>`
+fn strip_panel_tags(content: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(content.len());
+    let mut rest = content;
+
+    while let Some(start) = next_tag_start(rest) {
+        let Some(open_end) = bytes::find(&rest[start..], b">").map(|offset| start + offset + 1) else {
+            break;
+        };
+        let open_tag = &rest[start..open_end];
+
+        if !PANEL_REFS.iter().any(|panel| bytes::contains(open_tag, panel.as_bytes())) {
+            result.extend_from_slice(&rest[..open_end]);
+            rest = &rest[open_end..];
+            continue;
+        }
+
+        let end = if open_tag.starts_with(b"<script") {
+            bytes::find(&rest[open_end..], b"</script>")
+                .map(|offset| open_end + offset + "</script>".len())
+                .unwrap_or(open_end)
+        } else {
+            open_end
+        };
+
+        result.extend_from_slice(trim_trailing_indent(&rest[..start]));
+        rest = &rest[end..];
+    }
+
+    result.extend_from_slice(rest);
+    result
+}
+
+fn next_tag_start(content: &[u8]) -> Option<usize> {
+    match (bytes::find(content, b"<link"), bytes::find(content, b"<script")) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// 被剥离的标签独占一行时, 连同前面的缩进一起去掉, 避免留下空白行
+fn trim_trailing_indent(prefix: &[u8]) -> &[u8] {
+    let trimmed = bytes::trim_indent_end(prefix);
+    if trimmed.ends_with(b"\n") {
+        trimmed.strip_suffix(b"\r\n").or_else(|| trimmed.strip_suffix(b"\n")).unwrap_or(trimmed)
+    } else {
+        prefix
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stripping_keeps_non_utf8_bytes() {
+        let original: &[u8] = b"<head>\xff\xfe\n</head>\n";
+        let patched = [
+            &b"<head>\xff\xfe\n    <link rel=\"stylesheet\" href=\"./cascade-panel/a.css\">\n"[..],
+            b"    <script src=\"./cascade-panel/a.js\"></script>\n</head>\n",
+        ]
+        .concat();
+
+        assert!(is_patched_content(&patched));
+        assert_eq!(strip_patch_markers(&patched).unwrap(), original);
+        assert_eq!(strip_patch_markers(original), None);
+    }
+}
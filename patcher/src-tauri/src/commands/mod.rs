@@ -1,16 +1,86 @@
 // 命令模块入口
 
+mod audit;
+mod backup;
+mod bytes;
+mod cancel;
+mod codesign;
+mod compare;
 mod detect;
+mod diagnostics;
+mod editor;
+mod error;
+mod filesystem;
+mod forget;
+mod i18n;
+mod identity;
+mod integrity;
+mod json_edit;
+mod legacy;
+mod lock;
+mod logging;
+mod marker;
+mod network;
 mod patch;
+#[cfg(feature = "dev")]
+mod payload;
+mod plan;
+mod preflight;
 mod config;
+mod progress;
+mod process;
 mod prompt;
+mod secret;
+mod selftest;
+mod snapshot;
+mod space;
+mod status;
+mod update;
+mod user_data;
+mod version;
+mod watch;
 
-pub use detect::{detect_antigravity_path, detect_windsurf_path};
+pub use detect::{
+    detect_antigravity_path, detect_all_antigravity_paths, detect_antigravity_from_hint,
+    detect_antigravity_with_version, detect_antigravity_installs, detect_windsurf_path,
+    detect_windsurf_with_channel, detect_cursor_path, detect_antigravity_path_verbose,
+    supported_editors, refresh_detection, detect_in_root, first_antigravity_path, DetectionCache,
+};
 pub use patch::{
-    install_patch, uninstall_patch, update_config, check_patch_status,
-    read_patch_config, read_manager_patch_config,
+    install_patch, install_patch_dry_run, uninstall_patch, uninstall_patch_dry_run, uninstall_all,
+    repatch,
+    update_config, check_patch_status,
+    verify_patch_integrity, check_patch_compatibility, is_path_writable,
+    read_patch_config, get_patch_config_path, reveal_install_dir, open_editor_config,
+    backup_now,
+    read_manager_patch_config,
+    get_embedded_patch_version, verify_embedded_payload,
     install_windsurf_patch, uninstall_windsurf_patch, update_windsurf_config,
     check_windsurf_patch_status, read_windsurf_patch_config,
+    install_cursor_patch, uninstall_cursor_patch, update_cursor_config, check_cursor_patch_status,
+};
+#[cfg(feature = "dev")]
+pub use patch::install_patch_from_file;
+pub use patch::{installed_features, InstallOutcome};
+pub use error::PatchError;
+pub use config::{
+    get_config, save_config, export_config, import_config, reset_config, open_config_file,
+    list_profiles, save_profile, load_profile, delete_profile, set_manual_path,
 };
-pub use config::{get_config, save_config};
-pub use prompt::test_prompt_connection;
+pub use codesign::is_bundle_signed;
+pub use backup::{list_backups, restore_backup, verify_backup};
+pub use cancel::cancel_operation;
+pub use watch::{watch_patch_status, stop_watching_patch_status};
+pub use watch::stop_watching;
+pub use logging::{get_log_path, open_log};
+pub use diagnostics::{collect_diagnostics, save_diagnostics};
+pub use preflight::preflight_check;
+pub use logging::init as init_logging;
+pub use prompt::{test_prompt_connection, test_endpoint};
+pub use update::{get_editor_update_setting, set_editor_update_setting};
+pub use identity::read_editor_identity;
+pub use selftest::run_patch_self_test;
+pub use legacy::{detect_legacy_patch, migrate_from_legacy_patch};
+pub use compare::compare_installs;
+pub use forget::forget_install;
+pub use version::Version;
@@ -0,0 +1,125 @@
+// 网络路径 (UNC 路径与映射的网络驱动器)
+// 企业环境中编辑器可能安装在 \\server\share 或映射的网络驱动器上; 服务器不可达时
+// 文件系统调用可能阻塞数十秒, 先在独立线程中以超时检查共享根目录是否可达, 结果短暂缓存
+
+use std::path::{Component, Path, PathBuf, Prefix};
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 网络路径可达性检查的默认超时, 可通过 ANTIGRAVITY_NETWORK_TIMEOUT_MS 调整
+const DEFAULT_NETWORK_TIMEOUT_MS: u64 = 8000;
+
+/// 可达性检查结果的缓存时间, 一次检测中同一共享下的多个候选路径只检查一次
+const REACHABILITY_TTL: Duration = Duration::from_secs(30);
+
+/// 共享根目录 -> (是否可达, 检查时间)
+static REACHABILITY: Mutex<Vec<(PathBuf, bool, Instant)>> = Mutex::new(Vec::new());
+
+/// 路径是否位于网络上 (UNC 路径或映射的网络驱动器), 访问可能较慢
+pub fn is_network_path(path: &Path) -> bool {
+    match path_prefix(path) {
+        Some(Prefix::UNC(..) | Prefix::VerbatimUNC(..)) => true,
+        Some(Prefix::Disk(letter) | Prefix::VerbatimDisk(letter)) => is_remote_drive(letter),
+        _ => false,
+    }
+}
+
+/// 路径所在的网络共享是否可达; 本地路径直接返回 true, 不在这里检查路径本身是否存在
+pub fn is_reachable(path: &Path) -> bool {
+    let Some(root) = network_root(path) else {
+        return true;
+    };
+
+    if let Ok(cache) = REACHABILITY.lock() {
+        let cached = cache
+            .iter()
+            .find(|(cached_root, _, checked_at)| {
+                *cached_root == root && checked_at.elapsed() < REACHABILITY_TTL
+            })
+            .map(|(_, reachable, _)| *reachable);
+        if let Some(reachable) = cached {
+            return reachable;
+        }
+    }
+
+    let reachable = exists_with_timeout(&root, network_timeout());
+    if !reachable {
+        log::warn!("网络路径不可达或响应超时: {:?}", root);
+    }
+    if let Ok(mut cache) = REACHABILITY.lock() {
+        cache.retain(|(cached_root, _, _)| *cached_root != root);
+        cache.push((root, reachable, Instant::now()));
+    }
+    reachable
+}
+
+fn network_timeout() -> Duration {
+    let timeout = std::env::var("ANTIGRAVITY_NETWORK_TIMEOUT_MS")
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(DEFAULT_NETWORK_TIMEOUT_MS);
+    Duration::from_millis(timeout)
+}
+
+/// 在独立线程中检查路径是否存在, 超时视为不存在; 超时的线程在文件系统调用返回后自行结束
+fn exists_with_timeout(path: &Path, timeout: Duration) -> bool {
+    let (sender, receiver) = mpsc::channel();
+    let path = path.to_path_buf();
+    std::thread::spawn(move || {
+        let _ = sender.send(path.exists());
+    });
+    receiver.recv_timeout(timeout).unwrap_or(false)
+}
+
+/// 网络路径的共享根目录 (\\server\share\ 或映射驱动器的 Z:\), 本地路径返回 None
+fn network_root(path: &Path) -> Option<PathBuf> {
+    if !is_network_path(path) {
+        return None;
+    }
+    let mut root = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir => root.push(component.as_os_str()),
+            _ => break,
+        }
+    }
+    Some(root)
+}
+
+fn path_prefix(path: &Path) -> Option<Prefix<'_>> {
+    match path.components().next()? {
+        Component::Prefix(prefix) => Some(prefix.kind()),
+        _ => None,
+    }
+}
+
+/// 盘符是否为映射的网络驱动器 (GetDriveTypeW 不访问网络, 不会阻塞)
+#[cfg(target_os = "windows")]
+pub fn is_remote_drive(letter: u8) -> bool {
+    drive_type(letter) == windows_sys::Win32::System::WindowsProgramming::DRIVE_REMOTE
+}
+
+/// 盘符是否存在 (本地磁盘, 光驱或映射的网络驱动器), 不访问驱动器本身
+#[cfg(target_os = "windows")]
+pub fn drive_exists(letter: u8) -> bool {
+    drive_type(letter) != windows_sys::Win32::System::WindowsProgramming::DRIVE_NO_ROOT_DIR
+}
+
+#[cfg(target_os = "windows")]
+fn drive_type(letter: u8) -> u32 {
+    use windows_sys::Win32::Storage::FileSystem::GetDriveTypeW;
+
+    let root: Vec<u16> = format!("{}:\\", letter as char)
+        .encode_utf16()
+        .chain(std::iter::once(0))
+        .collect();
+    // SAFETY: root 是以 0 结尾的 UTF-16 字符串, 调用期间保持有效
+    unsafe { GetDriveTypeW(root.as_ptr()) }
+}
+
+/// 其他平台上没有盘符
+#[cfg(not(target_os = "windows"))]
+pub fn is_remote_drive(_letter: u8) -> bool {
+    false
+}
@@ -1,12 +1,188 @@
 // 补丁安装与卸载模块
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
-use crate::embedded;
+use std::path::{Path, PathBuf};
+use crate::embedded::{
+    self, AnchorPosition, CascadeAnchors, InjectionAnchor, PANEL_CSS_ANCHOR, PANEL_JS_ANCHOR,
+};
+use super::codesign::{signature_warning, SignatureWarning};
+use super::config::{active_prompt_enhance, editor_enabled};
+use super::backup::{
+    backup_dir, backup_files, check_backup_version, create_backup_set, plan_restore, resolve_relative,
+    RestoreStrategy, RestoredFile, UninstallReport,
+};
+use super::detect::{
+    canonical_path, check_antigravity_install, detect_in_background, is_packaged_as_asar, DetectionCache,
+    InstallCheck,
+};
+use super::error::PatchError;
+use super::lock::PatchLock;
+use super::bytes;
+use super::marker::{cascade_inject_block, is_patched_content, CASCADE_INJECT_MARKER};
+use super::logging::log_result;
+use super::i18n::{configured_language, status_summary};
+use super::json_edit::{edited_json, strip_bom, JsonEdit};
+use super::legacy::ensure_no_legacy_patch;
+use super::audit::FileChanges;
+use super::integrity::{planned_file_hashes, sha256_hex, verify_file_hashes, FileIntegrity};
+use super::filesystem::{DiskFs, FileSystem};
+use super::plan::{check_dir_writable, write_replacing, ApplyError, DryRunReport, PatchPlan};
+use super::editor::Editor;
+use super::process::is_editor_running;
+use super::snapshot::Snapshot;
+use super::space::ensure_space_for;
+use super::version::{read_app_version, Version};
+use super::progress::{report, report_write, PatchProgress};
+use super::prompt::normalize_api_base;
+use super::status::{component_state, file_state, missing_config_keys, PatchState, PatchStatus};
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Manager, State};
 
 const FEATURE_DEFAULTS_VERSION: u32 = 1;
 
+/// 当前补丁器版本, 安装时写入补丁配置
+const PATCHER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// 被补丁覆盖的原始文件 (相对安装目录)
+const CASCADE_PANEL_HTML: &str = "resources/app/extensions/antigravity/cascade-panel.html";
+const JETSKI_AGENT_HTML: &str =
+    "resources/app/out/vs/code/electron-browser/workbench/workbench-jetski-agent.html";
+const WORKBENCH_HTML: &str = "resources/app/out/vs/code/electron-browser/workbench/workbench.html";
+pub const PRODUCT_JSON: &str = "resources/app/product.json";
+
+// 补丁自带的配置文件, 用于判断对应组件是否已安装
+const CASCADE_CONFIG_JSON: &str = "resources/app/extensions/antigravity/cascade-panel/config.json";
+const MANAGER_CONFIG_JSON: &str =
+    "resources/app/out/vs/code/electron-browser/workbench/manager-panel/config.json";
+
+/// 补丁脚本从侧边栏配置读取的键, 以及开始读取该键的补丁内容版本 (embedded::patch_version)
+///
+/// 补丁内容开始读取新键时在此登记, check_patch_status 据此发现未随补丁升级重新写入的配置
+const CASCADE_CONFIG_KEYS: &[(&str, u32)] = &[
+    ("scrollToBottom", 1),
+    ("fontSizeEnabled", 1),
+    ("fontSize", 1),
+    ("sidePaddingLeft", 1),
+    ("sidePaddingRight", 1),
+    ("promptEnhance.enabled", 1),
+    ("promptEnhance.provider", 1),
+    ("promptEnhance.apiBase", 1),
+    ("promptEnhance.apiKey", 1),
+    ("promptEnhance.model", 1),
+    ("promptEnhance.systemPrompt", 1),
+];
+
+/// 补丁脚本从 Manager 配置读取的键, 同 CASCADE_CONFIG_KEYS
+const MANAGER_CONFIG_KEYS: &[(&str, u32)] = &[
+    ("scrollToBottom", 1),
+    ("fontSizeEnabled", 1),
+    ("fontSize", 1),
+    ("promptEnhance.enabled", 1),
+    ("promptEnhance.provider", 1),
+    ("promptEnhance.apiBase", 1),
+    ("promptEnhance.apiKey", 1),
+    ("promptEnhance.model", 1),
+    ("promptEnhance.systemPrompt", 1),
+];
+
+/// 决定补丁状态的文件, 监视补丁状态时检查这些文件的变化
+pub const STATUS_FILES: &[&str] = &[
+    CASCADE_PANEL_HTML,
+    CASCADE_CONFIG_JSON,
+    JETSKI_AGENT_HTML,
+    MANAGER_CONFIG_JSON,
+    WORKBENCH_HTML,
+];
+
+/// Antigravity 补丁会覆盖的原始文件
+const ANTIGRAVITY_OVERWRITTEN_FILES: &[&str] =
+    &[CASCADE_PANEL_HTML, JETSKI_AGENT_HTML, WORKBENCH_HTML, PRODUCT_JSON];
+
+/// 侧边栏 / Manager 补丁启用时在安装目录中留一份 .bak 副本的文件
+const CASCADE_BAK_FILES: &[&str] = &[CASCADE_PANEL_HTML];
+const MANAGER_BAK_FILES: &[&str] = &[JETSKI_AGENT_HTML, WORKBENCH_HTML, PRODUCT_JSON];
+
+/// Windsurf / Cursor 面板补丁会修改的原始文件
+const PANEL_OVERWRITTEN_FILES: &[&str] = &[WORKBENCH_HTML, PRODUCT_JSON];
+
+/// 面板补丁自带的配置文件
+const PANEL_CONFIG_JSON: &str =
+    "resources/app/out/vs/code/electron-browser/workbench/windsurf-panel/config.json";
+
+/// 每次安装前存入备份集的文件: 会被覆盖的文件与补丁配置
+const ANTIGRAVITY_BACKUP_SET_FILES: &[&str] = &[
+    CASCADE_PANEL_HTML,
+    JETSKI_AGENT_HTML,
+    WORKBENCH_HTML,
+    PRODUCT_JSON,
+    CASCADE_CONFIG_JSON,
+    MANAGER_CONFIG_JSON,
+];
+const PANEL_BACKUP_SET_FILES: &[&str] = &[WORKBENCH_HTML, PRODUCT_JSON, PANEL_CONFIG_JSON];
+
+/// 可单独安装的补丁文件组, 调试时只写入其中一部分
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PatchTarget {
+    /// 侧边栏入口 cascade-panel.html
+    CascadePanelHtml,
+    /// 侧边栏脚本与样式 (cascade-panel/ 与 shared/ 目录, 含侧边栏配置)
+    CascadeScripts,
+    /// Manager 入口 workbench-jetski-agent.html
+    JetskiAgentHtml,
+    /// workbench.html (Manager 模板与侧边栏注入)
+    WorkbenchHtml,
+    /// Manager 脚本与样式 (manager-panel/ 与 shared/ 目录, 含 Manager 配置)
+    ManagerScripts,
+    /// product.json 的校验和清理
+    ProductJson,
+}
+
+impl PatchTarget {
+    /// 该文件组包含的文件或目录 (相对安装目录)
+    fn relative_paths(self) -> &'static [&'static str] {
+        match self {
+            PatchTarget::CascadePanelHtml => &[CASCADE_PANEL_HTML],
+            PatchTarget::CascadeScripts => &[
+                "resources/app/extensions/antigravity/cascade-panel",
+                "resources/app/extensions/antigravity/shared",
+                "resources/app/out/vs/code/electron-browser/workbench/cascade-panel",
+                "resources/app/out/vs/code/electron-browser/workbench/shared",
+            ],
+            PatchTarget::JetskiAgentHtml => &[JETSKI_AGENT_HTML],
+            PatchTarget::WorkbenchHtml => &[WORKBENCH_HTML],
+            PatchTarget::ManagerScripts => &[
+                "resources/app/out/vs/code/electron-browser/workbench/manager-panel",
+                "resources/app/out/vs/code/electron-browser/workbench/shared",
+            ],
+            PatchTarget::ProductJson => &[PRODUCT_JSON],
+        }
+    }
+
+    /// 路径是否属于该文件组; 文件旁的旧版 .bak 副本随文件本身
+    fn covers(self, install_path: &Path, path: &Path) -> bool {
+        self.relative_paths().iter().any(|relative_path| {
+            let target = resolve_relative(install_path, relative_path);
+            let mut backup = target.clone().into_os_string();
+            backup.push(".bak");
+            path.starts_with(&target) || path == Path::new(&backup)
+        })
+    }
+}
+
+/// 只保留计划中属于指定文件组的改动, targets 为 None 时保留全部
+fn limit_to_targets<F: FileSystem>(
+    plan: &mut PatchPlan<F>,
+    install_path: &Path,
+    targets: Option<&[PatchTarget]>,
+) {
+    if let Some(targets) = targets {
+        plan.retain(|path| targets.iter().any(|target| target.covers(install_path, path)));
+    }
+}
+
 /// 提示词增强配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
@@ -22,6 +198,19 @@ pub struct PromptEnhanceConfig {
     pub system_prompt: String,
 }
 
+impl PromptEnhanceConfig {
+    /// 规范化接口地址, 不合法时返回 InvalidEndpoint
+    pub fn normalize(&mut self) -> Result<(), PatchError> {
+        self.api_base = normalize_api_base(&self.api_base).map_err(|message| {
+            PatchError::InvalidEndpoint {
+                url: self.api_base.clone(),
+                message,
+            }
+        })?;
+        Ok(())
+    }
+}
+
 const DEFAULT_SYSTEM_PROMPT: &str = "你是一个智能提示词优化器，专门帮助用户生成更有效的 AI 对话提示词。\n\n## 核心任务\n将用户输入的原始提示词优化为更清晰、更具体、更有效的版本。\n\n## 你会收到的信息\n1. **对话上下文**：之前的对话历史（如果有）\n2. **当前文件**：用户正在编辑的文件（如果有）\n3. **选中代码**：用户选中的代码片段（如果有）\n4. **用户原始提示词**：需要优化的内容\n\n## 优化规则\n1. **理解上下文**：仔细阅读对话历史，理解当前讨论的主题和背景\n2. **保持连贯性**：优化后的提示词应该与之前的对话保持逻辑连贯\n3. **具体化**：让模糊的问题变得具体，如果上下文中有相关信息就引用它\n4. **结构化**：为复杂问题添加清晰的结构，使用 Markdown 列表\n5. **保持意图**：不改变用户的原始意图，只是表达得更清晰\n6. **保留格式**：必须使用 Markdown 格式（换行、列表、代码块），确保生成的提示词易于阅读\n\n## 输出要求\n- **只输出优化后的提示词**，不要任何解释、前缀或额外内容\n- 保持用户使用的语言（中文/英文）\n- 如果原始提示词是追问或继续之前的话题，保持这种连续性\n- **关键**：确保输出包含必要的换行符，不要将长文本压缩成一行\n\n## 示例\n\n### 示例 1 - 无上下文\n输入: hi\n输出: 你好，请帮我解决一个问题。我会详细描述需求，请提供完整的解决方案。\n\n### 示例 2 - 有上下文（之前讨论了一个 bug）\n对话历史: [用户问了如何修复登录 bug，AI 提供了方案]\n输入: 还有问题\n输出: 按照你之前提供的登录 bug 修复方案，我尝试后发现仍有问题。请帮我进一步排查，可能是哪些原因导致的？\n\n### 示例 3 - 引用代码\n选中代码: function getData() { ... }\n输入: 优化这个\n输出: 请帮我优化上面选中的 getData 函数。具体需要：\n1. 提高性能\n2. 改进可读性\n3. 添加错误处理\n\n请解释每处修改的原因。\n\n记住：直接输出优化后的提示词，不要任何其他内容。";
 
 impl Default for PromptEnhanceConfig {
@@ -58,6 +247,15 @@ pub struct FeatureConfig {
     /// 提示词增强配置
     #[serde(rename = "promptEnhance")]
     pub prompt_enhance: PromptEnhanceConfig,
+    /// 补丁写入文件的 SHA-256 (相对安装目录的路径 -> 摘要), 安装时生成
+    #[serde(rename = "fileHashes")]
+    pub file_hashes: BTreeMap<String, String>,
+    /// 安装补丁的补丁器版本, 旧版本安装的配置中为空
+    #[serde(rename = "patcherVersion")]
+    pub patcher_version: String,
+    /// 安装时的补丁内容版本 (见 embedded::patch_version), 旧版本安装的配置中为空
+    #[serde(rename = "payloadVersion")]
+    pub payload_version: String,
 }
 
 impl Default for FeatureConfig {
@@ -71,10 +269,22 @@ impl Default for FeatureConfig {
             side_padding_left: 8.0,
             side_padding_right: 3.0,
             prompt_enhance: PromptEnhanceConfig::default(),
+            file_hashes: BTreeMap::new(),
+            patcher_version: String::new(),
+            payload_version: String::new(),
         }
     }
 }
 
+impl FeatureConfig {
+    /// 关闭所有行为开关, 注入的脚本只加载不生效
+    fn disable_all(&mut self) {
+        self.scroll_to_bottom = false;
+        self.font_size_enabled = false;
+        self.prompt_enhance.enabled = false;
+    }
+}
+
 /// Manager 窗口功能开关配置
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(default)]
@@ -92,6 +302,15 @@ pub struct ManagerFeatureConfig {
     /// 提示词增强配置
     #[serde(rename = "promptEnhance")]
     pub prompt_enhance: PromptEnhanceConfig,
+    /// 补丁写入文件的 SHA-256 (相对安装目录的路径 -> 摘要), 安装时生成
+    #[serde(rename = "fileHashes")]
+    pub file_hashes: BTreeMap<String, String>,
+    /// 安装补丁的补丁器版本, 旧版本安装的配置中为空
+    #[serde(rename = "patcherVersion")]
+    pub patcher_version: String,
+    /// 安装时的补丁内容版本 (见 embedded::patch_version), 旧版本安装的配置中为空
+    #[serde(rename = "payloadVersion")]
+    pub payload_version: String,
 }
 
 impl Default for ManagerFeatureConfig {
@@ -103,120 +322,808 @@ impl Default for ManagerFeatureConfig {
             font_size_enabled: false,
             font_size: 16.0,
             prompt_enhance: PromptEnhanceConfig::default(),
+            file_hashes: BTreeMap::new(),
+            patcher_version: String::new(),
+            payload_version: String::new(),
         }
     }
 }
 
+impl ManagerFeatureConfig {
+    fn disable_all(&mut self) {
+        self.scroll_to_bottom = false;
+        self.font_size_enabled = false;
+        self.prompt_enhance.enabled = false;
+    }
+}
+
+/// 安装补丁的结果
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum InstallOutcome {
+    /// 全部写入成功, 附带写入后各补丁文件的 SHA-256 校验结果与逐字节改动
+    Applied {
+        files: Vec<FileIntegrity>,
+        /// 各写入文件相对写入前内容的改动, 供审计重建差异
+        changes: Vec<FileChanges>,
+        /// macOS 上应用包原本已签名时, 提示重新签名
+        #[serde(rename = "signatureWarning")]
+        signature_warning: Option<SignatureWarning>,
+        /// 已是预期补丁内容而跳过的文件 (相对安装目录), 既未备份也未重写
+        skipped: Vec<String>,
+    },
+    /// 写入中途失败, 已撤销之前的改动
+    RolledBack {
+        error: String,
+        /// 撤销时遇到的错误, 非空说明安装目录可能未完全恢复
+        #[serde(rename = "rollbackErrors")]
+        rollback_errors: Vec<String>,
+    },
+    /// 所有文件已是同样的补丁内容, 未备份也未写入任何文件
+    AlreadyPatched,
+    /// 写入补丁文件前就失败 (编辑器运行中, 校验, 备份或可写性检查), 安装目录未被修改
+    FailedBeforeWrite { error: PatchError },
+    /// 写入中途被取消, 已撤销之前的改动
+    Cancelled {
+        #[serde(rename = "rollbackErrors")]
+        rollback_errors: Vec<String>,
+    },
+}
+
 /// 安装补丁
-#[tauri::command]
+///
+/// 编辑器运行时拒绝安装, `force` 为 true 时跳过该检查;
+/// 传入 `targets` 时只写入这些文件组 (调试用), 不传时安装全部补丁文件
+#[tauri::command(async)]
 pub fn install_patch(
     path: String, 
     features: FeatureConfig,
-    manager_features: ManagerFeatureConfig
-) -> Result<(), String> {
-    let antigravity_path = PathBuf::from(&path);
-    
-    // 侧边栏目标目录
-    let extensions_dir = antigravity_path
-        .join("resources")
-        .join("app")
-        .join("extensions")
-        .join("antigravity");
+    manager_features: ManagerFeatureConfig,
+    on_progress: Channel<PatchProgress>,
+    force: Option<bool>,
+    targets: Option<Vec<PatchTarget>>,
+) -> InstallOutcome {
+    let source = match PatchSource::embedded(Path::new(&path)) {
+        Ok(source) => source,
+        Err(error) => return InstallOutcome::FailedBeforeWrite { error },
+    };
+    install_from_source(
+        Path::new(&path),
+        &features,
+        &manager_features,
+        &on_progress,
+        force,
+        &source,
+        targets.as_deref(),
+    )
+}
 
-    // Manager 目标目录
-    let workbench_dir = antigravity_path
-        .join("resources")
-        .join("app")
-        .join("out")
-        .join("vs")
-        .join("code")
-        .join("electron-browser")
-        .join("workbench");
+/// 从外部补丁包安装 (仅 dev feature), 用于测试尚未编译进补丁器的补丁
+///
+/// 功能开关沿用已安装的配置, 未安装时使用默认值
+#[cfg(feature = "dev")]
+#[tauri::command]
+pub fn install_patch_from_file(
+    editor_path: String,
+    payload_path: String,
+    on_progress: Channel<PatchProgress>,
+    force: Option<bool>,
+) -> InstallOutcome {
+    let source = match super::payload::PatchPayload::load(Path::new(&payload_path)) {
+        Ok(payload) => payload.into_source(),
+        Err(error) => return InstallOutcome::FailedBeforeWrite { error: error.into() },
+    };
+    let features = read_patch_config(editor_path.clone())
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    let manager_features = read_manager_config_at(Path::new(&editor_path))
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+
+    install_from_source(
+        Path::new(&editor_path),
+        &features,
+        &manager_features,
+        &on_progress,
+        force,
+        &source,
+        None,
+    )
+}
 
-    if !extensions_dir.exists() {
-        return Err("无效的 Antigravity 安装目录".to_string());
+/// 补丁内容来源: 内置补丁或外部补丁包
+pub struct PatchSource {
+    /// 补丁内容版本, 写入配置文件的 payloadVersion
+    pub version: String,
+    /// 相对 patches 目录的路径 -> 文件内容
+    pub files: Vec<(String, String)>,
+    /// 侧边栏补丁在 workbench.html 中的注入锚点
+    pub cascade_anchors: CascadeAnchors,
+}
+
+impl PatchSource {
+    /// 编译进补丁器的补丁 (debug 构建读取 patches 目录)
+    ///
+    /// 按编辑器版本选择对应的补丁内容, 没有适用的版本时返回 VersionIncompatible
+    fn embedded(antigravity_path: &Path) -> Result<Self, PatchError> {
+        verify_embedded_payload()?;
+        let version = read_app_version(antigravity_path);
+        let payload = embedded::select_payload(version).ok_or_else(|| {
+            PatchError::VersionIncompatible {
+                found: version.map(|version| version.to_string()).unwrap_or_default(),
+                supported: embedded::supported_versions(),
+            }
+        })?;
+        Ok(Self {
+            version: embedded::patch_version().to_string(),
+            files: embedded::get_all_files_runtime().map_err(PatchError::Io)?,
+            cascade_anchors: payload.cascade_anchors,
+        })
     }
+}
 
-    if !workbench_dir.exists() {
-        return Err("Manager 窗口目录不存在".to_string());
+/// 按补丁来源安装 Antigravity 补丁
+fn install_from_source(
+    antigravity_path: &Path,
+    features: &FeatureConfig,
+    manager_features: &ManagerFeatureConfig,
+    on_progress: &Channel<PatchProgress>,
+    force: Option<bool>,
+    source: &PatchSource,
+    targets: Option<&[PatchTarget]>,
+) -> InstallOutcome {
+    log::info!("安装补丁: {:?} (补丁版本 {})", antigravity_path, source.version);
+    if let Some(targets) = targets {
+        log::info!("只安装部分文件: {:?}", targets);
     }
+    let outcome = run_install(
+        antigravity_path,
+        features,
+        manager_features,
+        on_progress,
+        force,
+        source,
+        targets,
+    );
+    match &outcome {
+        InstallOutcome::Applied { files, .. } => {
+            log::info!("安装补丁成功: {:?}, 写入 {} 个文件", antigravity_path, files.len())
+        }
+        InstallOutcome::AlreadyPatched => {
+            log::info!("补丁已是最新, 无需重复安装: {:?}", antigravity_path)
+        }
+        InstallOutcome::RolledBack { error, rollback_errors } => log::error!(
+            "安装补丁失败并已回滚: {:?}: {} (回滚错误: {:?})",
+            antigravity_path,
+            error,
+            rollback_errors
+        ),
+        InstallOutcome::FailedBeforeWrite { error } => {
+            log::error!("安装补丁失败: {:?}: {:?}", antigravity_path, error)
+        }
+        InstallOutcome::Cancelled { rollback_errors } => log::warn!(
+            "安装补丁已取消并回滚: {:?} (回滚错误: {:?})",
+            antigravity_path,
+            rollback_errors
+        ),
+    }
+    outcome
+}
 
-    // 根据 enabled 状态处理侧边栏补丁
-    if features.enabled {
-        // 备份并安装侧边栏补丁（extensions + workbench 双路径写入文件）
-        backup_cascade_files(&extensions_dir)?;
-        write_cascade_patches(&extensions_dir, &workbench_dir, &features)?;
-    } else {
-        // 禁用时还原侧边栏文件
-        restore_cascade_files(&extensions_dir)?;
+fn run_install(
+    antigravity_path: &Path,
+    features: &FeatureConfig,
+    manager_features: &ManagerFeatureConfig,
+    on_progress: &Channel<PatchProgress>,
+    force: Option<bool>,
+    source: &PatchSource,
+    targets: Option<&[PatchTarget]>,
+) -> InstallOutcome {
+    if let Err(error) = ensure_editor_closed(Editor::Antigravity, force) {
+        return InstallOutcome::FailedBeforeWrite { error };
     }
+    let _lock = match PatchLock::acquire() {
+        Ok(lock) => lock,
+        Err(error) => return InstallOutcome::FailedBeforeWrite { error },
+    };
+    install_locked(antigravity_path, features, manager_features, on_progress, source, targets)
+}
 
-    // 根据 enabled 状态处理 Manager 补丁
-    if manager_features.enabled {
-        // 备份并安装 Manager 补丁
-        backup_manager_files(&workbench_dir)?;
-        write_manager_patches(&workbench_dir, &manager_features)?;
-        
-        // 清空 product.json 的 checksums 字段，消除"安装损坏"提示
-        let product_json_path = antigravity_path
-            .join("resources")
-            .join("app")
-            .join("product.json");
-        clear_product_checksums(&product_json_path)?;
-    } else {
-        // 禁用时还原 Manager 文件
-        restore_manager_files(&workbench_dir)?;
+/// 安装补丁 (调用方已持有补丁操作锁)
+fn install_locked(
+    antigravity_path: &Path,
+    features: &FeatureConfig,
+    manager_features: &ManagerFeatureConfig,
+    on_progress: &Channel<PatchProgress>,
+    source: &PatchSource,
+    targets: Option<&[PatchTarget]>,
+) -> InstallOutcome {
+    let (plan, skipped) = match prepare_antigravity_install(
+        antigravity_path,
+        features,
+        manager_features,
+        on_progress,
+        source,
+        targets,
+    ) {
+        Ok(prepared) => prepared,
+        Err(error) => return InstallOutcome::FailedBeforeWrite { error },
+    };
+
+    // 所有文件已是同样的补丁内容时不重复写入, 也不创建备份
+    if plan.is_noop() {
+        return InstallOutcome::AlreadyPatched;
     }
 
-    // 最后注入 cascade 到 workbench.html（必须在 manager 覆盖之后）
-    if features.enabled {
-        let workbench_html = workbench_dir.join("workbench.html");
-        if workbench_html.exists() {
-            inject_cascade_into_html(&workbench_html)?;
+    // 覆盖任何文件前先备份原版
+    report(on_progress, 5, "备份原版文件");
+    if let Err(error) =
+        backup_originals(&DiskFs, antigravity_path, features, manager_features, &skipped)
+    {
+        return InstallOutcome::FailedBeforeWrite { error };
+    }
+
+    let changes = plan.change_report();
+    let applied = plan.apply_with_progress(|index, total, path| {
+        report_write(on_progress, index, total, path)
+    });
+    match applied {
+        Ok(()) => {
+            // 重新读取磁盘内容, 确认写入的正是计划中的内容
+            report(on_progress, 95, "校验写入结果");
+            let file_hashes = planned_file_hashes(&plan, antigravity_path);
+            let files = verify_file_hashes(antigravity_path, &file_hashes);
+            let signature_warning = signature_warning(antigravity_path);
+            report(on_progress, 100, "安装完成");
+            InstallOutcome::Applied { files, changes, signature_warning, skipped }
+        }
+        Err(ApplyError::BeforeWrite(error)) => InstallOutcome::FailedBeforeWrite { error },
+        Err(ApplyError::RolledBack { error, rollback_errors }) => {
+            InstallOutcome::RolledBack { error, rollback_errors }
+        }
+        Err(ApplyError::Cancelled { rollback_errors }) => {
+            InstallOutcome::Cancelled { rollback_errors }
         }
     }
+}
 
+/// 校验安装目录并生成补丁计划, 返回计划与跳过的文件; 不写入任何文件 (备份在确认需要写入后进行)
+fn prepare_antigravity_install(
+    antigravity_path: &Path,
+    features: &FeatureConfig,
+    manager_features: &ManagerFeatureConfig,
+    on_progress: &Channel<PatchProgress>,
+    source: &PatchSource,
+    targets: Option<&[PatchTarget]>,
+) -> Result<(PatchPlan, Vec<String>), PatchError> {
+    report(on_progress, 0, "检测安装目录");
+    validate_antigravity_install(antigravity_path)?;
+    ensure_writable(&write_dirs(antigravity_path))?;
+    ensure_no_legacy_patch(antigravity_path)?;
+
+    let workbench_dir = workbench_dir(antigravity_path);
+
+    // 备份复制到一半因磁盘写满失败会留下不完整的备份, 先确认空间足够
+    // 备份写入应用数据目录, 旧版 .bak 副本写在安装目录旁
+    let backup_sources: Vec<PathBuf> = ANTIGRAVITY_OVERWRITTEN_FILES
+        .iter()
+        .map(|relative_path| resolve_relative(antigravity_path, relative_path))
+        .collect();
+    let backup_sources: Vec<&Path> = backup_sources.iter().map(PathBuf::as_path).collect();
+    let backup_target = backup_dir(antigravity_path);
+    ensure_space_for(&backup_sources, &[&backup_target, &workbench_dir])?;
+
+    // 生成计划只读取文件; 先生成计划, 已是预期补丁内容的文件不再备份与重写
+    let mut plan = plan_antigravity_patch(
+        DiskFs,
+        antigravity_path,
+        features,
+        manager_features,
+        source,
+        targets,
+    )?;
+    let skipped = skip_current_files(&mut plan, antigravity_path);
+    if !skipped.is_empty() {
+        log::info!("跳过已是最新补丁内容的文件: {:?}", skipped);
+    }
+
+    Ok((plan, skipped))
+}
+
+/// 备份即将被覆盖的原版文件: 备份清单中的原版, 备份集, 以及安装目录中的 .bak 副本
+/// 已跳过 (不会重写) 的文件不做任何备份
+fn backup_originals(
+    file_system: &impl FileSystem,
+    antigravity_path: &Path,
+    features: &FeatureConfig,
+    manager_features: &ManagerFeatureConfig,
+    skipped: &[String],
+) -> Result<(), PatchError> {
+    let rewritten = |relative_path: &&str| !skipped.iter().any(|skip| skip == relative_path);
+    let overwritten_files: Vec<&str> =
+        ANTIGRAVITY_OVERWRITTEN_FILES.iter().copied().filter(rewritten).collect();
+    backup_files(file_system, antigravity_path, &overwritten_files)
+        .map_err(PatchError::BackupFailed)?;
+    let backup_set_files: Vec<&str> =
+        ANTIGRAVITY_BACKUP_SET_FILES.iter().copied().filter(rewritten).collect();
+    create_backup_set(file_system, Editor::Antigravity, antigravity_path, &backup_set_files, None)
+        .map_err(PatchError::BackupFailed)?;
+
+    let mut bak_files = Vec::new();
+    if features.enabled {
+        bak_files.extend_from_slice(CASCADE_BAK_FILES);
+    }
+    if manager_features.enabled {
+        bak_files.extend_from_slice(MANAGER_BAK_FILES);
+    }
+    for relative_path in bak_files.into_iter().filter(rewritten) {
+        copy_to_bak(file_system, &resolve_relative(antigravity_path, relative_path))
+            .map_err(PatchError::BackupFailed)?;
+    }
     Ok(())
 }
 
-/// 卸载补丁 (恢复原版)
+/// 从计划中去掉已是预期补丁内容的文件, 返回去掉的文件 (相对安装目录)
+///
+/// 安装时记录的摘要与本次计划写入的内容相同, 且磁盘上的文件仍是该内容时才跳过;
+/// 所在目录会被整体清理重写的文件不能跳过
+fn skip_current_files<F: FileSystem>(plan: &mut PatchPlan<F>, install_path: &Path) -> Vec<String> {
+    let file_system = plan.file_system();
+    // 与 stored_file_hashes 相同: 侧边栏配置中没有摘要时取 Manager 配置中的
+    let stored = [CASCADE_CONFIG_JSON, MANAGER_CONFIG_JSON]
+        .iter()
+        .map(|config| {
+            stored_patch_meta(file_system, &resolve_relative(install_path, config)).file_hashes
+        })
+        .find(|file_hashes| !file_hashes.is_empty())
+        .unwrap_or_default();
+    let skipped: Vec<String> = planned_file_hashes(plan, install_path)
+        .into_iter()
+        .filter(|(relative_path, hash)| {
+            let path = resolve_relative(install_path, relative_path);
+            stored.get(relative_path) == Some(hash)
+                && !plan.remove_dirs.iter().any(|dir| path.starts_with(dir))
+                && file_system.read(&path).is_ok_and(|content| sha256_hex(&content) == *hash)
+        })
+        .map(|(relative_path, _)| relative_path)
+        .collect();
+
+    let skipped_paths: Vec<PathBuf> = skipped
+        .iter()
+        .map(|relative_path| resolve_relative(install_path, relative_path))
+        .collect();
+    plan.retain(|path| !skipped_paths.iter().any(|skip| skip == path));
+    skipped
+}
+
+/// 预览安装补丁: 列出将被修改的文件及大小变化, 不写入磁盘
+///
+/// 与 install_patch 一样跳过已是预期补丁内容的文件 (列在 skipped 中), `targets` 含义相同
 #[tauri::command]
-pub fn uninstall_patch(path: String) -> Result<(), String> {
+pub fn install_patch_dry_run(
+    path: String,
+    features: FeatureConfig,
+    manager_features: ManagerFeatureConfig,
+    targets: Option<Vec<PatchTarget>>,
+) -> Result<DryRunReport, PatchError> {
     let antigravity_path = PathBuf::from(&path);
-    
-    let extensions_dir = antigravity_path
-        .join("resources")
-        .join("app")
-        .join("extensions")
-        .join("antigravity");
+    validate_antigravity_install(&antigravity_path)?;
+
+    let source = PatchSource::embedded(&antigravity_path)?;
+    let mut plan = plan_antigravity_patch(
+        DiskFs,
+        &antigravity_path,
+        &features,
+        &manager_features,
+        &source,
+        targets.as_deref(),
+    )?;
+    let skipped = skip_current_files(&mut plan, &antigravity_path);
+    Ok(DryRunReport {
+        skipped,
+        ..plan.dry_run_report()
+    })
+}
 
-    let workbench_dir = antigravity_path
-        .join("resources")
-        .join("app")
-        .join("out")
-        .join("vs")
-        .join("code")
-        .join("electron-browser")
-        .join("workbench");
+/// 卸载补丁 (恢复原版)
+///
+/// 优先用安装时备份的原版文件还原, 没有备份时退回到剥离补丁标记;
+/// 返回每个文件采用的还原方式。编辑器运行时拒绝卸载, `force` 为 true 时跳过该检查。
+/// 备份来自另一个编辑器版本时返回 BackupVersionMismatch, `use_markers` 为 true 时不用备份, 只剥离补丁标记。
+#[tauri::command(async)]
+pub fn uninstall_patch(
+    path: String,
+    force: Option<bool>,
+    use_markers: Option<bool>,
+) -> Result<UninstallReport, PatchError> {
+    let result = run_uninstall(&path, force, use_markers.unwrap_or(false));
+    log_result("卸载补丁", &path, &result);
+    result
+}
+
+fn run_uninstall(
+    path: &str,
+    force: Option<bool>,
+    use_markers: bool,
+) -> Result<UninstallReport, PatchError> {
+    let antigravity_path = PathBuf::from(path);
+    let (extensions_dir, _) = antigravity_dirs(&antigravity_path);
 
+    ensure_install_exists(&antigravity_path)?;
     if !extensions_dir.exists() {
-        return Err("无效的 Antigravity 安装目录".to_string());
+        return Err(PatchError::PathNotFound);
     }
 
-    // 恢复备份文件
-    restore_backup_files(&extensions_dir, &workbench_dir)?;
+    ensure_editor_closed(Editor::Antigravity, force)?;
+    if !use_markers {
+        check_backup_version(&DiskFs, &antigravity_path)?;
+    }
+    let _lock = PatchLock::acquire()?;
+    let report = uninstall_locked(&antigravity_path, !use_markers)?;
+    ensure_no_residual_markers(&DiskFs, &report)?;
+    Ok(report)
+}
 
-    Ok(())
+/// 安装目录本身已不存在时返回 InstallRemoved, 与目录存在但不是有效安装 (PathNotFound) 区分开
+fn ensure_install_exists(install_path: &Path) -> Result<(), PatchError> {
+    if install_path.exists() {
+        Ok(())
+    } else {
+        Err(PatchError::InstallRemoved {
+            path: install_path.to_string_lossy().to_string(),
+        })
+    }
+}
+
+/// 卸载后重新扫描还原过的 HTML 文件, 仍带有补丁标记时返回 ResidualMarkersFound
+fn ensure_no_residual_markers(
+    file_system: &impl FileSystem,
+    report: &UninstallReport,
+) -> Result<(), PatchError> {
+    let files: Vec<String> = report
+        .files
+        .iter()
+        .map(|file| file.path.clone())
+        .filter(|path| path.ends_with(".html"))
+        .filter(|path| {
+            file_system
+                .read(Path::new(path))
+                .is_ok_and(|content| is_patched_content(&content))
+        })
+        .collect();
+    if files.is_empty() {
+        return Ok(());
+    }
+    log::error!("卸载后仍有文件带有补丁标记: {:?}", files);
+    Err(PatchError::ResidualMarkersFound { files })
+}
+
+/// 卸载补丁 (调用方已持有补丁操作锁)
+fn uninstall_locked(
+    antigravity_path: &Path,
+    use_backups: bool,
+) -> Result<UninstallReport, PatchError> {
+    ensure_writable(&write_dirs(antigravity_path))?;
+    let (plan, report) = plan_uninstall(DiskFs, antigravity_path, use_backups)?;
+    plan.apply()?;
+    Ok(report)
+}
+
+/// 计算卸载的全部改动
+fn plan_uninstall<F: FileSystem>(
+    file_system: F,
+    antigravity_path: &Path,
+    use_backups: bool,
+) -> Result<(PatchPlan<F>, UninstallReport), PatchError> {
+    let workbench_dir = workbench_dir(antigravity_path);
+    let mut plan = PatchPlan::with_file_system(file_system);
+    let mut report = UninstallReport::default();
+    report.files.extend(plan_cascade_restore(&mut plan, antigravity_path, use_backups)?);
+    report.files.extend(plan_manager_restore(&mut plan, antigravity_path, use_backups)?);
+    report.files.push(plan_restore(&mut plan, antigravity_path, PRODUCT_JSON, use_backups)?);
+    // 用户删除过补丁配置 (或只剩补丁标记) 时仍按标记清除, 报告中注明
+    report.mark_best_effort(
+        [CASCADE_CONFIG_JSON, MANAGER_CONFIG_JSON]
+            .iter()
+            .any(|config| plan.file_system().exists(&resolve_relative(antigravity_path, config))),
+    );
+
+    // workbench 下的侧边栏副本只在完整卸载时删除 (Manager 模板也会引用它)
+    plan.remove_dir(workbench_dir.join("cascade-panel"));
+    plan.remove_dir(workbench_dir.join("shared"));
+
+    Ok((plan, report))
+}
+
+/// 卸载预览: 单个文件的还原方式与大小变化
+#[derive(Debug, Serialize)]
+pub struct UninstallDryRunFile {
+    pub path: String,
+    pub strategy: RestoreStrategy,
+    #[serde(rename = "sizeBefore")]
+    pub size_before: u64,
+    #[serde(rename = "sizeAfter")]
+    pub size_after: u64,
+}
+
+/// 卸载预览报告
+#[derive(Debug, Serialize)]
+pub struct UninstallDryRunReport {
+    pub files: Vec<UninstallDryRunFile>,
+    /// 会被整体删除的补丁目录
+    #[serde(rename = "removedDirs")]
+    pub removed_dirs: Vec<String>,
+}
+
+/// 预览卸载补丁: 列出每个文件从备份还原还是剥离补丁标记, 以及还原后的大小, 不写入磁盘
+///
+/// 参数与 uninstall_patch 一致, 备份版本不一致时同样返回 BackupVersionMismatch
+#[tauri::command]
+pub fn uninstall_patch_dry_run(
+    path: String,
+    use_markers: Option<bool>,
+) -> Result<UninstallDryRunReport, PatchError> {
+    let antigravity_path = PathBuf::from(&path);
+    let (extensions_dir, _) = antigravity_dirs(&antigravity_path);
+    ensure_install_exists(&antigravity_path)?;
+    if !extensions_dir.exists() {
+        return Err(PatchError::PathNotFound);
+    }
+
+    let use_markers = use_markers.unwrap_or(false);
+    if !use_markers {
+        check_backup_version(&DiskFs, &antigravity_path)?;
+    }
+    let (plan, report) = plan_uninstall(DiskFs, &antigravity_path, !use_markers)?;
+
+    let files = report
+        .files
+        .into_iter()
+        .map(|file| {
+            let target = PathBuf::from(&file.path);
+            let size_before = fs::metadata(&target).map(|meta| meta.len()).unwrap_or(0);
+            let size_after = plan
+                .content_of(&target)?
+                .map_or(0, |content| content.len() as u64);
+            Ok(UninstallDryRunFile {
+                path: file.path,
+                strategy: file.strategy,
+                size_before,
+                size_after,
+            })
+        })
+        .collect::<Result<Vec<_>, PatchError>>()?;
+
+    Ok(UninstallDryRunReport {
+        files,
+        removed_dirs: plan.dry_run_report().removed_dirs,
+    })
+}
+
+/// 重新安装的结果
+#[derive(Debug, Serialize)]
+pub struct RepatchReport {
+    pub uninstall: UninstallReport,
+    pub install: InstallOutcome,
+    /// 安装失败时恢复到重新安装前状态遇到的错误, 非空说明安装目录可能未完全恢复
+    #[serde(rename = "restoreErrors")]
+    pub restore_errors: Vec<String>,
+}
+
+/// 重新安装补丁: 先卸载 (还原原版), 再用内置补丁安装
+///
+/// 功能开关沿用已安装的配置 (都没有时使用默认值); 安装阶段失败时把安装目录恢复到卸载前的状态。
+/// 编辑器运行时拒绝操作, `force` 为 true 时跳过该检查。
+#[tauri::command(async)]
+pub fn repatch(
+    path: String,
+    on_progress: Channel<PatchProgress>,
+    force: Option<bool>,
+) -> Result<RepatchReport, PatchError> {
+    let result = run_repatch(&path, &on_progress, force);
+    log_result("重新安装补丁", &path, &result);
+    result
+}
+
+fn run_repatch(
+    path: &str,
+    on_progress: &Channel<PatchProgress>,
+    force: Option<bool>,
+) -> Result<RepatchReport, PatchError> {
+    let antigravity_path = PathBuf::from(path);
+    validate_antigravity_install(&antigravity_path)?;
+    let source = PatchSource::embedded(&antigravity_path)?;
+    let (features, manager_features) = installed_features(&antigravity_path);
+
+    ensure_editor_closed(Editor::Antigravity, force)?;
+    let _lock = PatchLock::acquire()?;
+
+    let snapshot = Snapshot::capture(&repatch_paths(&antigravity_path)).map_err(PatchError::Io)?;
+    report(on_progress, 0, "卸载旧补丁");
+    let uninstall = uninstall_locked(&antigravity_path, backups_match_version(&DiskFs, &antigravity_path))?;
+
+    let install = install_locked(
+        &antigravity_path,
+        &features,
+        &manager_features,
+        on_progress,
+        &source,
+        None,
+    );
+    let restore_errors = match install {
+        InstallOutcome::Applied { .. } | InstallOutcome::AlreadyPatched => Vec::new(),
+        _ => snapshot.restore(),
+    };
+
+    Ok(RepatchReport {
+        uninstall,
+        install,
+        restore_errors,
+    })
+}
+
+/// 已安装的功能开关: 有配置的组件沿用配置, 两者都没有时全部使用默认值
+pub fn installed_features(antigravity_path: &Path) -> (FeatureConfig, ManagerFeatureConfig) {
+    let path = antigravity_path.to_string_lossy().to_string();
+    let features = read_patch_config(path).ok().flatten();
+    let manager_features = read_manager_config_at(antigravity_path).ok().flatten();
+    if features.is_none() && manager_features.is_none() {
+        return (FeatureConfig::default(), ManagerFeatureConfig::default());
+    }
+
+    let features = features.map_or_else(
+        || FeatureConfig { enabled: false, ..Default::default() },
+        |config| FeatureConfig { enabled: true, ..config },
+    );
+    let manager_features = manager_features.map_or_else(
+        || ManagerFeatureConfig { enabled: false, ..Default::default() },
+        |config| ManagerFeatureConfig { enabled: true, ..config },
+    );
+    (features, manager_features)
+}
+
+/// 卸载与安装会改动的全部文件和目录
+fn repatch_paths(antigravity_path: &Path) -> Vec<PathBuf> {
+    let (extensions_dir, workbench_dir) = antigravity_dirs(antigravity_path);
+    let mut paths: Vec<PathBuf> = ANTIGRAVITY_OVERWRITTEN_FILES
+        .iter()
+        .map(|relative_path| resolve_relative(antigravity_path, relative_path))
+        .collect();
+    paths.extend([
+        extensions_dir.join("cascade-panel"),
+        workbench_dir.join("cascade-panel"),
+        workbench_dir.join("shared"),
+        workbench_dir.join("manager-panel"),
+    ]);
+    paths
+}
+
+/// 一键卸载中单个安装的结果
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum UninstallResult {
+    Uninstalled { path: String, report: UninstallReport },
+    /// 未安装补丁, 跳过
+    NotPatched { path: String },
+    Failed { path: String, error: PatchError },
 }
 
-/// 仅更新配置文件 (不重新复制补丁文件)
+/// 卸载所有检测到的编辑器安装中的补丁, 用于排查问题时一次恢复干净状态
+///
+/// 逐个安装执行, 某个安装失败不影响其余安装
+#[tauri::command]
+pub async fn uninstall_all(app: AppHandle) -> Vec<(Editor, UninstallResult)> {
+    let cache = app.state::<DetectionCache>();
+    let mut results = Vec::new();
+    for editor in Editor::ALL {
+        for path in detect_in_background(&cache, editor).await {
+            let result = tauri::async_runtime::spawn_blocking({
+                let path = path.clone();
+                move || uninstall_editor(editor, path)
+            })
+            .await
+            .unwrap_or_else(|e| UninstallResult::Failed {
+                path,
+                error: PatchError::Io(format!("卸载任务异常退出: {}", e)),
+            });
+            results.push((editor, result));
+        }
+    }
+    results
+}
+
+/// 卸载单个安装中的补丁, 未安装补丁时直接跳过
+fn uninstall_editor(editor: Editor, path: String) -> UninstallResult {
+    let is_patched = match editor {
+        Editor::Antigravity => check_patch_status(path.clone())
+            .is_ok_and(|status| status.overall != PatchState::NotPatched),
+        Editor::Windsurf | Editor::Cursor => panel_patch_present(Path::new(&path)),
+    };
+    if !is_patched {
+        return UninstallResult::NotPatched { path };
+    }
+
+    let result = match editor {
+        Editor::Antigravity => uninstall_patch(path.clone(), None, None),
+        Editor::Windsurf | Editor::Cursor => uninstall_panel_patch(editor, &path, None),
+    };
+    match result {
+        Ok(report) => UninstallResult::Uninstalled { path, report },
+        Err(error) => UninstallResult::Failed { path, error },
+    }
+}
+
+/// 更新配置的结果
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum ConfigUpdateOutcome {
+    /// 回读确认所有配置都已生效
+    Applied,
+    /// 回读时补丁器管理的字段与写入的值不一致 (通常是编辑器随即覆盖了配置文件)
+    ConfigDriftDetected { files: Vec<ConfigDrift> },
+}
+
+/// 单个配置文件中未生效的字段
+#[derive(Debug, Serialize)]
+pub struct ConfigDrift {
+    pub path: String,
+    /// 不一致的字段, 嵌套字段以 `.` 连接 (如 promptEnhance.model)
+    pub keys: Vec<String>,
+}
+
+/// 更新配置时如何对待配置文件中已有的内容
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConfigMergeMode {
+    /// 整个文件由补丁器接管, 只写入补丁器管理的字段, 其他字段全部丢弃
+    Replace,
+    /// 递归合并: 嵌套对象逐字段合并, 已有的同级字段 (包括补丁器管理的对象中的额外字段) 保留
+    DeepMerge,
+    /// 只替换补丁器管理的顶层字段 (整个对象一起替换), 其他顶层字段原样保留
+    #[default]
+    ManagedKeysOnly,
+}
+
+/// 仅更新配置文件 (不重新复制补丁文件), 写入后回读确认设置已生效
+///
+/// mode 缺省为 ManagedKeysOnly
 #[tauri::command]
 pub fn update_config(
     path: String, 
-    features: FeatureConfig,
-    manager_features: ManagerFeatureConfig
-) -> Result<(), String> {
-    let antigravity_path = PathBuf::from(&path);
+    mut features: FeatureConfig,
+    mut manager_features: ManagerFeatureConfig,
+    mode: Option<ConfigMergeMode>,
+) -> Result<ConfigUpdateOutcome, PatchError> {
+    let mode = mode.unwrap_or_default();
+    let result = write_feature_configs(&path, &mut features, &mut manager_features, mode);
+    log_result("更新配置", &path, &result);
+    result
+}
+
+fn write_feature_configs(
+    path: &str,
+    features: &mut FeatureConfig,
+    manager_features: &mut ManagerFeatureConfig,
+    mode: ConfigMergeMode,
+) -> Result<ConfigUpdateOutcome, PatchError> {
+    let antigravity_path = PathBuf::from(path);
+    let mut drifts = Vec::new();
+
+    // 当前配置方案指定了提示词增强设置时以方案为准
+    if let Some(prompt_enhance) = active_prompt_enhance() {
+        features.prompt_enhance = prompt_enhance.clone();
+        manager_features.prompt_enhance = prompt_enhance;
+    }
+    features.prompt_enhance.normalize()?;
+    manager_features.prompt_enhance.normalize()?;
+    // 配置中停用了该编辑器时只关闭功能, 注入保留, 重新启用后再次更新配置即可恢复
+    if !editor_enabled(Editor::Antigravity) {
+        log::info!("Antigravity 补丁已在配置中停用, 写入关闭全部功能的配置");
+        features.disable_all();
+        manager_features.disable_all();
+    }
     
     // 侧边栏配置
     let cascade_config_path = antigravity_path
@@ -228,10 +1135,12 @@ pub fn update_config(
         .join("config.json");
 
     if !cascade_config_path.parent().map(|p| p.exists()).unwrap_or(false) {
-        return Err("补丁尚未安装，请先安装补丁".to_string());
+        return Err(PatchError::NotPatched);
     }
 
-    write_config_file(&cascade_config_path, &features)?;
+    let meta = stored_patch_meta(&DiskFs, &cascade_config_path);
+    write_config_file(&cascade_config_path, features, &meta, mode)?;
+    drifts.extend(config_drift(&cascade_config_path, &cascade_config_content(features, &meta)));
 
     let workbench_cascade_config_path = antigravity_path
         .join("resources")
@@ -245,7 +1154,11 @@ pub fn update_config(
         .join("config.json");
 
     if workbench_cascade_config_path.parent().map(|p| p.exists()).unwrap_or(false) {
-        write_config_file(&workbench_cascade_config_path, &features)?;
+        write_config_file(&workbench_cascade_config_path, features, &meta, mode)?;
+        drifts.extend(config_drift(
+            &workbench_cascade_config_path,
+            &cascade_config_content(features, &meta),
+        ));
     }
 
     // Manager 配置
@@ -261,27 +1174,298 @@ pub fn update_config(
         .join("config.json");
 
     if manager_config_path.parent().map(|p| p.exists()).unwrap_or(false) {
-        write_manager_config_file(&manager_config_path, &manager_features)?;
+        let meta = stored_patch_meta(&DiskFs, &manager_config_path);
+        write_manager_config_file(&manager_config_path, manager_features, &meta, mode)?;
+        drifts.extend(config_drift(
+            &manager_config_path,
+            &manager_config_content(manager_features, &meta),
+        ));
     }
 
-    Ok(())
+    if drifts.is_empty() {
+        Ok(ConfigUpdateOutcome::Applied)
+    } else {
+        log::warn!("配置写入后未生效: {:?}", drifts);
+        Ok(ConfigUpdateOutcome::ConfigDriftDetected { files: drifts })
+    }
+}
+
+/// 回读配置文件, 与写入的补丁器管理字段逐项比对; 文件读不到或无法解析时视为全部字段未生效
+fn config_drift(config_path: &Path, managed_content: &str) -> Option<ConfigDrift> {
+    let Ok(serde_json::Value::Object(managed)) = serde_json::from_str(managed_content) else {
+        return None;
+    };
+    let actual = fs::read_to_string(config_path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .unwrap_or(serde_json::Value::Null);
+
+    let mut keys = Vec::new();
+    drifted_keys(&managed, &actual, "", &mut keys);
+    (!keys.is_empty()).then(|| ConfigDrift {
+        path: config_path.to_string_lossy().to_string(),
+        keys,
+    })
+}
+
+/// 收集与期望值不一致的字段; 与 merge_json 一致, fileHashes 整体比较
+fn drifted_keys(
+    expected: &serde_json::Map<String, serde_json::Value>,
+    actual: &serde_json::Value,
+    prefix: &str,
+    keys: &mut Vec<String>,
+) {
+    for (key, value) in expected {
+        let path = format!("{}{}", prefix, key);
+        let actual_value = actual.get(key);
+        match (value, actual_value) {
+            (serde_json::Value::Object(nested), Some(actual_value)) if key != "fileHashes" => {
+                drifted_keys(nested, actual_value, &format!("{}.", path), keys)
+            }
+            _ if actual_value == Some(value) => {}
+            _ => keys.push(path),
+        }
+    }
 }
 
-/// 检测补丁是否已安装
+/// 检测补丁状态, 逐个文件报告是否存在以及是否带有补丁标记
 #[tauri::command]
-pub fn check_patch_status(path: String) -> Result<bool, String> {
+pub fn check_patch_status(path: String) -> Result<PatchStatus, PatchError> {
     let antigravity_path = PathBuf::from(&path);
-    
-    let config_path = antigravity_path
-        .join("resources")
-        .join("app")
-        .join("extensions")
-        .join("antigravity")
-        .join("cascade-panel")
-        .join("config.json");
 
-    // 如果 config.json 存在, 则认为补丁已安装
-    Ok(config_path.exists())
+    let cascade_html = file_state(&antigravity_path, CASCADE_PANEL_HTML);
+    let cascade_config = file_state(&antigravity_path, CASCADE_CONFIG_JSON);
+    let jetski_html = file_state(&antigravity_path, JETSKI_AGENT_HTML);
+    let manager_config = file_state(&antigravity_path, MANAGER_CONFIG_JSON);
+    let workbench_html = file_state(&antigravity_path, WORKBENCH_HTML);
+
+    // 侧边栏与 Manager 可分别启用, 但两者都依赖 workbench.html 中的注入
+    let cascade = component_state(&[&cascade_html, &cascade_config]);
+    let manager = component_state(&[&jetski_html, &manager_config]);
+    let components = [cascade, manager];
+
+    // 与安装时记录的摘要比对, 找出之后被改动 (通常是编辑器更新还原) 的补丁文件
+    let changed_files: Vec<String> = stored_file_hashes(&path)
+        .map(|file_hashes| verify_file_hashes(&antigravity_path, &file_hashes))
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|file| !file.matches)
+        .map(|file| file.path)
+        .collect();
+    let missing_config_keys = stale_config_keys(&antigravity_path);
+
+    let overall = if components.iter().all(|state| *state == PatchState::NotPatched)
+        && !workbench_html.marker_present
+    {
+        PatchState::NotPatched
+    } else if !changed_files.is_empty() {
+        PatchState::RevertedByUpdate
+    } else if components.contains(&PatchState::Partial) || !workbench_html.marker_present {
+        PatchState::Partial
+    } else if !missing_config_keys.is_empty() {
+        PatchState::ConfigPayloadMismatch
+    } else if is_patched_by_older_version(&path) {
+        PatchState::PatchedByOlderVersion
+    } else {
+        PatchState::Patched
+    };
+
+    Ok(PatchStatus {
+        overall,
+        files: vec![cascade_html, cascade_config, jetski_html, manager_config, workbench_html],
+        changed_files,
+        missing_config_keys,
+        enabled: editor_enabled(Editor::Antigravity),
+        status_summary: status_summary(overall, configured_language()),
+    })
+}
+
+/// 已安装的侧边栏与 Manager 配置中缺少的、安装的补丁内容需要的键
+pub fn stale_config_keys(install_path: &Path) -> Vec<String> {
+    let mut missing: Vec<String> = Vec::new();
+    for (relative_path, required) in [
+        (CASCADE_CONFIG_JSON, CASCADE_CONFIG_KEYS),
+        (MANAGER_CONFIG_JSON, MANAGER_CONFIG_KEYS),
+    ] {
+        let Some(config) = fs::read_to_string(resolve_relative(install_path, relative_path))
+            .ok()
+            .and_then(|content| serde_json::from_str(strip_bom(&content)).ok())
+        else {
+            continue;
+        };
+        for key in missing_config_keys(&config, required) {
+            if !missing.contains(&key) {
+                missing.push(key);
+            }
+        }
+    }
+    missing
+}
+
+/// 已安装配置中记录的补丁文件摘要, 侧边栏配置缺失时取 Manager 配置
+pub fn stored_file_hashes(path: &str) -> Result<BTreeMap<String, String>, String> {
+    let file_hashes = read_patch_config(path.to_string())?
+        .map(|config| config.file_hashes)
+        .unwrap_or_default();
+    if !file_hashes.is_empty() {
+        return Ok(file_hashes);
+    }
+
+    Ok(read_manager_config_at(Path::new(path))?
+        .map(|config| config.file_hashes)
+        .unwrap_or_default())
+}
+
+/// 已安装补丁是否由更旧的补丁器写入 (未记录版本的也视为旧版本)
+fn is_patched_by_older_version(path: &str) -> bool {
+    let stored = read_patch_config(path.to_string())
+        .ok()
+        .flatten()
+        .map(|config| config.patcher_version)
+        .filter(|version| !version.is_empty())
+        .or_else(|| {
+            read_manager_config_at(Path::new(path))
+                .ok()
+                .flatten()
+                .map(|config| config.patcher_version)
+        });
+
+    let running = Version::parse(PATCHER_VERSION);
+    match stored.as_deref().and_then(Version::parse) {
+        Some(stored) => running.is_some_and(|running| stored < running),
+        None => true,
+    }
+}
+
+/// 注入锚点检查结果
+#[derive(Debug, Serialize)]
+pub struct AnchorCheck {
+    /// 相对安装目录的文件路径
+    pub file: String,
+    pub anchor: String,
+    pub present: bool,
+}
+
+/// 补丁兼容性预检报告
+#[derive(Debug, Serialize)]
+pub struct CompatibilityReport {
+    /// 编辑器版本, 读取不到时为空
+    pub version: Option<String>,
+    pub anchors: Vec<AnchorCheck>,
+    /// 可以安全安装补丁
+    pub patchable: bool,
+    /// 不可安装时的原因
+    pub problem: Option<PatchError>,
+}
+
+/// 安装前预检: 检查安装完整性与注入锚点, 并按默认功能试算一次补丁计划 (不写入磁盘)
+#[tauri::command]
+pub fn check_patch_compatibility(path: String) -> CompatibilityReport {
+    let antigravity_path = PathBuf::from(&path);
+
+    let source = PatchSource::embedded(&antigravity_path);
+    let anchors = source
+        .as_ref()
+        .map(|source| anchor_checks(&antigravity_path, &source.cascade_anchors.all()))
+        .unwrap_or_default();
+
+    let problem = validate_antigravity_install(&antigravity_path)
+        .and(source)
+        .and_then(|source| {
+            plan_antigravity_patch(
+                DiskFs,
+                &antigravity_path,
+                &FeatureConfig::default(),
+                &ManagerFeatureConfig::default(),
+                &source,
+                None,
+            )
+        })
+        .err();
+
+    CompatibilityReport {
+        version: read_app_version(&antigravity_path).map(|version| version.to_string()),
+        anchors,
+        patchable: problem.is_none(),
+        problem,
+    }
+}
+
+/// 检查 workbench.html 中的注入锚点
+fn anchor_checks(install_path: &Path, anchors: &[InjectionAnchor]) -> Vec<AnchorCheck> {
+    let html = fs::read_to_string(install_path.join(WORKBENCH_HTML)).unwrap_or_default();
+    anchors
+        .iter()
+        .map(|anchor| AnchorCheck {
+            file: WORKBENCH_HTML.to_string(),
+            anchor: anchor.token.to_string(),
+            present: html.contains(anchor.token),
+        })
+        .collect()
+}
+
+/// 编辑器补丁依赖的注入锚点的检查结果; Antigravity 没有适用于当前版本的补丁内容时为空
+pub fn installed_anchor_checks(editor: Editor, install_path: &Path) -> Vec<AnchorCheck> {
+    match editor {
+        Editor::Antigravity => embedded::select_payload(read_app_version(install_path))
+            .map(|payload| anchor_checks(install_path, &payload.cascade_anchors.all()))
+            .unwrap_or_default(),
+        Editor::Windsurf | Editor::Cursor => {
+            anchor_checks(install_path, &[PANEL_CSS_ANCHOR, PANEL_JS_ANCHOR])
+        }
+    }
+}
+
+/// 安装中是否有该编辑器的补丁 (包括部分安装与被编辑器更新还原的补丁)
+pub fn is_patch_present(editor: Editor, install_path: &Path) -> bool {
+    match editor {
+        Editor::Antigravity => check_patch_status(install_path.to_string_lossy().to_string())
+            .is_ok_and(|status| status.overall != PatchState::NotPatched),
+        Editor::Windsurf | Editor::Cursor => panel_patch_present(install_path),
+    }
+}
+
+/// 校验补丁文件完整性
+///
+/// 重新计算安装时记录过摘要的文件, 标记出被外部改动 (如编辑器更新) 的文件
+#[tauri::command]
+pub fn verify_patch_integrity(path: String) -> Result<Vec<FileIntegrity>, String> {
+    let antigravity_path = PathBuf::from(&path);
+
+    let file_hashes = stored_file_hashes(&path)?;
+    if file_hashes.is_empty() {
+        return Err("未找到补丁校验信息, 请重新安装补丁".to_string());
+    }
+
+    Ok(verify_file_hashes(&antigravity_path, &file_hashes))
+}
+
+/// 嵌入的补丁内容版本
+#[derive(Debug, Serialize)]
+pub struct EmbeddedPatchVersion {
+    pub version: String,
+    pub changelog: String,
+}
+
+/// 获取补丁器内置的补丁内容版本, 可与 read_patch_config 返回的 payloadVersion 比较
+#[tauri::command]
+pub fn get_embedded_patch_version() -> EmbeddedPatchVersion {
+    EmbeddedPatchVersion {
+        version: embedded::patch_version().to_string(),
+        changelog: embedded::patch_changelog().to_string(),
+    }
+}
+
+/// 校验内置补丁内容与编译时记录的摘要一致, 在写入任何编辑器文件前发现损坏的构建
+#[tauri::command]
+pub fn verify_embedded_payload() -> Result<(), PatchError> {
+    let expected = embedded::expected_checksum();
+    let actual = embedded::compute_checksum();
+    if actual != expected {
+        log::error!("内置补丁摘要不一致: 期望 {}, 实际 {}", expected, actual);
+        return Err(PatchError::PayloadCorrupted { expected: expected.to_string(), actual });
+    }
+    Ok(())
 }
 
 /// 读取已安装的补丁配置
@@ -304,7 +1488,7 @@ pub fn read_patch_config(path: String) -> Result<Option<FeatureConfig>, String>
     let content = fs::read_to_string(&config_path)
         .map_err(|e| format!("读取配置失败: {}", e))?;
 
-    let raw: serde_json::Value = serde_json::from_str(&content)
+    let raw: serde_json::Value = serde_json::from_str(strip_bom(&content))
         .map_err(|e| format!("解析配置失败: {}", e))?;
     let is_legacy_config = raw.get("featureDefaultsVersion").is_none();
     let mut config: FeatureConfig = serde_json::from_value(raw)
@@ -316,12 +1500,248 @@ pub fn read_patch_config(path: String) -> Result<Option<FeatureConfig>, String>
     Ok(Some(config))
 }
 
-/// 读取已安装的 Manager 补丁配置
-#[tauri::command]
-pub fn read_manager_patch_config(path: String) -> Result<Option<ManagerFeatureConfig>, String> {
-    let antigravity_path = PathBuf::from(&path);
-    
-    let config_path = antigravity_path
+/// 补丁配置文件的绝对路径, 排查问题时确认补丁器读取的是哪个文件
+///
+/// 安装路径中的符号链接与短路径会先解析; Antigravity 返回 cascade-panel 的配置,
+/// Manager 配置由 read_manager_patch_config 返回所在安装
+#[tauri::command]
+pub fn get_patch_config_path(editor: Editor, path: String) -> String {
+    let install_path = PathBuf::from(canonical_path(&path));
+    let config_path = match editor {
+        Editor::Antigravity => resolve_relative(&install_path, CASCADE_CONFIG_JSON),
+        Editor::Windsurf | Editor::Cursor => panel_config_path(&install_path),
+    };
+    config_path.to_string_lossy().to_string()
+}
+
+/// 在系统文件管理器中打开检测到的安装目录, 返回打开的路径
+///
+/// patched 为 true 时打开补丁文件所在的子目录; 没有检测到安装或补丁未安装时返回错误, 不打开其他目录
+#[tauri::command]
+pub async fn reveal_install_dir(
+    cache: State<'_,
+    DetectionCache>,
+    editor: Editor,
+    patched: Option<bool>,
+) -> Result<String, String> {
+    let install_path = detect_in_background(&cache, editor)
+        .await
+        .into_iter()
+        .next()
+        .map(PathBuf::from)
+        .ok_or_else(|| format!("未检测到 {} 安装", editor.name()))?;
+
+    let dir = if patched.unwrap_or(false) {
+        let config_path = match editor {
+            Editor::Antigravity => resolve_relative(&install_path, CASCADE_CONFIG_JSON),
+            Editor::Windsurf | Editor::Cursor => panel_config_path(&install_path),
+        };
+        let dir = config_path.parent().map(Path::to_path_buf).unwrap_or(install_path);
+        if !dir.is_dir() {
+            return Err(format!("补丁未安装: {}", dir.display()));
+        }
+        dir
+    } else {
+        install_path
+    };
+
+    tauri_plugin_opener::open_path(&dir, None::<&str>)
+        .map_err(|e| format!("打开目录失败: {}", e))?;
+    Ok(dir.to_string_lossy().to_string())
+}
+
+/// 用系统默认程序打开检测到的安装中补丁器管理的配置文件 (Antigravity 为侧边栏配置), 返回文件路径
+///
+/// 配置文件缺失但补丁目录存在时先写入默认配置; 补丁目录不存在说明补丁未安装, 不创建文件,
+/// 以免单独的配置文件让补丁状态显示为部分安装
+#[tauri::command]
+pub async fn open_editor_config(
+    cache: State<'_,
+    DetectionCache>,
+    editor: Editor,
+) -> Result<String, String> {
+    let install_path = detect_in_background(&cache, editor)
+        .await
+        .into_iter()
+        .next()
+        .map(PathBuf::from)
+        .ok_or_else(|| format!("未检测到 {} 安装", editor.name()))?;
+
+    let (config_path, default_content) = match editor {
+        Editor::Antigravity => (
+            resolve_relative(&install_path, CASCADE_CONFIG_JSON),
+            cascade_config_content(&FeatureConfig::default(), &PatchMeta::default()),
+        ),
+        Editor::Windsurf | Editor::Cursor => (
+            panel_config_path(&install_path),
+            panel_config_content(&WindsurfFeatureConfig::default()),
+        ),
+    };
+    if !config_path.exists() {
+        if !config_path.parent().is_some_and(Path::is_dir) {
+            return Err(format!("补丁未安装: {}", install_path.display()));
+        }
+        write_replacing(&config_path, default_content.as_bytes())
+            .map_err(|e| format!("写入默认配置失败: {}", e))?;
+        log::info!("配置文件不存在, 已写入默认配置: {:?}", config_path);
+    }
+
+    tauri_plugin_opener::open_path(&config_path, None::<&str>)
+        .map_err(|e| format!("打开配置文件失败: {}", e))?;
+    Ok(config_path.to_string_lossy().to_string())
+}
+
+/// 手动备份未指定标签时使用的标签
+const MANUAL_BACKUP_LABEL: &str = "手动备份";
+
+/// 立即把检测到的安装中的补丁相关文件 (无论是否已打补丁) 存为一组备份, 返回备份集 ID
+///
+/// 与安装前自动创建的备份集一样出现在 list_backups 中, 可用 restore_backup 还原;
+/// 内容与最近一组备份相同时不重复创建, 返回那一组的 ID
+#[tauri::command]
+pub async fn backup_now(
+    cache: State<'_,
+    DetectionCache>,
+    editor: Editor,
+    label: Option<String>,
+) -> Result<String, PatchError> {
+    let install_path = detect_in_background(&cache, editor)
+        .await
+        .into_iter()
+        .next()
+        .map(PathBuf::from)
+        .ok_or(PatchError::PathNotFound)?;
+
+    let relative_paths = match editor {
+        Editor::Antigravity => ANTIGRAVITY_BACKUP_SET_FILES,
+        Editor::Windsurf | Editor::Cursor => PANEL_BACKUP_SET_FILES,
+    };
+    let label = label
+        .map(|label| label.trim().to_string())
+        .filter(|label| !label.is_empty())
+        .unwrap_or_else(|| MANUAL_BACKUP_LABEL.to_string());
+
+    let result = {
+        let _lock = PatchLock::acquire()?;
+        create_backup_set(&DiskFs, editor, &install_path, relative_paths, Some(&label))
+            .map_err(PatchError::BackupFailed)
+            .and_then(|id| id.ok_or(PatchError::PathNotFound))
+    };
+    log_result("手动备份", &install_path.to_string_lossy(), &result);
+    result
+}
+
+/// 找到的 Manager 补丁配置及其所在安装
+#[derive(Debug, Serialize)]
+pub struct ManagerConfigLookup {
+    pub config: ManagerFeatureConfig,
+    /// 配置所在的安装目录, 可能不是传入的路径
+    #[serde(rename = "sourcePath")]
+    pub source_path: String,
+}
+
+/// 读取已安装的 Manager 补丁配置
+///
+/// 依次在传入的路径和所有检测到的安装中查找 (多个安装并存时配置可能在另一份里),
+/// 返回第一个能解析的配置; 都没有时返回 NotFound
+#[tauri::command]
+pub async fn read_manager_patch_config(
+    cache: State<'_,
+    DetectionCache>,
+    path: String,
+) -> Result<ManagerConfigLookup, PatchError> {
+    let mut candidates = vec![path];
+    for detected in detect_in_background(&cache, Editor::Antigravity).await {
+        if !candidates.contains(&detected) {
+            candidates.push(detected);
+        }
+    }
+
+    for candidate in &candidates {
+        match read_manager_config_at(Path::new(candidate)) {
+            Ok(Some(config)) => {
+                return Ok(ManagerConfigLookup {
+                    config,
+                    source_path: candidate.clone(),
+                })
+            }
+            Ok(None) => {}
+            Err(e) => log::warn!("跳过无法读取的 Manager 配置: {}: {}", candidate, e),
+        }
+    }
+
+    Err(PatchError::NotFound { searched: candidates })
+}
+
+/// 读取指定安装中的 Manager 补丁配置, 未安装时返回 None
+fn read_manager_config_at(antigravity_path: &Path) -> Result<Option<ManagerFeatureConfig>, String> {
+    let config_path = resolve_relative(antigravity_path, MANAGER_CONFIG_JSON);
+
+    if !config_path.exists() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(&config_path)
+        .map_err(|e| format!("读取 Manager 配置失败: {}", e))?;
+
+    let raw: serde_json::Value = serde_json::from_str(strip_bom(&content))
+        .map_err(|e| format!("解析 Manager 配置失败: {}", e))?;
+    let is_legacy_config = raw.get("featureDefaultsVersion").is_none();
+    let mut config: ManagerFeatureConfig = serde_json::from_value(raw)
+        .map_err(|e| format!("解析 Manager 配置失败: {}", e))?;
+    if is_legacy_config {
+        config.feature_defaults_version = 0;
+    }
+    
+    Ok(Some(config))
+}
+
+/// 编辑器运行中时返回 EditorRunning (force 时不检查)
+fn ensure_editor_closed(editor: Editor, force: Option<bool>) -> Result<(), PatchError> {
+    if !force.unwrap_or(false) && is_editor_running(editor) {
+        return Err(PatchError::EditorRunning);
+    }
+    Ok(())
+}
+
+/// 检查安装目录是否可写, 供前端在点击安装前提示以管理员身份运行
+#[tauri::command]
+pub fn is_path_writable(path: String) -> bool {
+    let install_path = PathBuf::from(&path);
+    let dirs = write_dirs(&install_path);
+    if dirs.is_empty() {
+        return check_dir_writable(&install_path).is_ok();
+    }
+    ensure_writable(&dirs).is_ok()
+}
+
+/// 补丁会写入的已存在目录 (侧边栏, workbench, product.json 所在目录)
+fn write_dirs(install_path: &Path) -> Vec<PathBuf> {
+    let (extensions_dir, workbench_dir) = antigravity_dirs(install_path);
+    [extensions_dir, workbench_dir, install_path.join("resources").join("app")]
+        .into_iter()
+        .filter(|dir| dir.is_dir())
+        .collect()
+}
+
+/// 逐个检查目录可写, 返回第一个无权限的目录
+fn ensure_writable(dirs: &[PathBuf]) -> Result<(), PatchError> {
+    dirs.iter().try_for_each(|dir| check_dir_writable(dir))
+}
+
+/// 侧边栏目录 (extensions/antigravity) 与 Manager 目录 (workbench)
+fn antigravity_dirs(antigravity_path: &Path) -> (PathBuf, PathBuf) {
+    let extensions_dir = antigravity_path
+        .join("resources")
+        .join("app")
+        .join("extensions")
+        .join("antigravity");
+    (extensions_dir, workbench_dir(antigravity_path))
+}
+
+/// workbench 目录 (VS Code 系编辑器共用的目录结构)
+fn workbench_dir(install_path: &Path) -> PathBuf {
+    install_path
         .join("resources")
         .join("app")
         .join("out")
@@ -329,73 +1749,166 @@ pub fn read_manager_patch_config(path: String) -> Result<Option<ManagerFeatureCo
         .join("code")
         .join("electron-browser")
         .join("workbench")
-        .join("manager-panel")
-        .join("config.json");
+}
 
-    if !config_path.exists() {
-        return Ok(None);
-    }
+fn antigravity_json_path(antigravity_path: &Path) -> PathBuf {
+    antigravity_path
+        .join("resources")
+        .join("app")
+        .join("product.json")
+}
 
-    let content = fs::read_to_string(&config_path)
-        .map_err(|e| format!("读取 Manager 配置失败: {}", e))?;
+/// 安装前检查目标目录是否为完整的 Antigravity 安装
+fn validate_antigravity_install(antigravity_path: &Path) -> Result<(), PatchError> {
+    let (extensions_dir, workbench_dir) = antigravity_dirs(antigravity_path);
 
-    let raw: serde_json::Value = serde_json::from_str(&content)
-        .map_err(|e| format!("解析 Manager 配置失败: {}", e))?;
-    let is_legacy_config = raw.get("featureDefaultsVersion").is_none();
-    let mut config: ManagerFeatureConfig = serde_json::from_value(raw)
-        .map_err(|e| format!("解析 Manager 配置失败: {}", e))?;
-    if is_legacy_config {
-        config.feature_defaults_version = 0;
+    if is_packaged_as_asar(antigravity_path) {
+        return Err(PatchError::PackagedAsAsar);
+    }
+    // 侧边栏目录与 Manager 窗口目录都必须存在
+    if !extensions_dir.exists() || !workbench_dir.exists() {
+        return Err(PatchError::PathNotFound);
     }
-    
-    Ok(Some(config))
-}
 
-/// 备份侧边栏相关文件
-fn backup_cascade_files(extensions_dir: &PathBuf) -> Result<(), String> {
-    let cascade_panel = extensions_dir.join("cascade-panel.html");
-    let cascade_backup = extensions_dir.join("cascade-panel.html.bak");
-    if cascade_panel.exists() && !cascade_backup.exists() {
-        fs::copy(&cascade_panel, &cascade_backup)
-            .map_err(|e| format!("备份 cascade-panel.html 失败: {}", e))?;
+    // 拒绝给半残的安装打补丁 (例如自动更新中途失败)
+    match check_antigravity_install(antigravity_path) {
+        InstallCheck::Valid => Ok(()),
+        InstallCheck::NotFound => Err(PatchError::PathNotFound),
+        InstallCheck::Incomplete { missing } => Err(PatchError::IncompleteInstall { missing }),
+        InstallCheck::PackagedAsAsar => Err(PatchError::PackagedAsAsar),
     }
-    Ok(())
 }
 
-/// 备份 Manager 相关文件
-fn backup_manager_files(workbench_dir: &PathBuf) -> Result<(), String> {
-    // 备份 workbench-jetski-agent.html
-    let jetski_agent = workbench_dir.join("workbench-jetski-agent.html");
-    let jetski_backup = workbench_dir.join("workbench-jetski-agent.html.bak");
-    if jetski_agent.exists() && !jetski_backup.exists() {
-        fs::copy(&jetski_agent, &jetski_backup)
-            .map_err(|e| format!("备份 workbench-jetski-agent.html 失败: {}", e))?;
+/// 计算 Antigravity 补丁的全部改动
+/// 安装目录中的现有内容经由 file_system 读取
+fn plan_antigravity_patch<F: FileSystem>(
+    file_system: F,
+    antigravity_path: &Path,
+    features: &FeatureConfig,
+    manager_features: &ManagerFeatureConfig,
+    source: &PatchSource,
+    targets: Option<&[PatchTarget]>,
+) -> Result<PatchPlan<F>, PatchError> {
+    let (extensions_dir, workbench_dir) = antigravity_dirs(antigravity_path);
+    let patch_files = &source.files;
+    let mut plan = PatchPlan::with_file_system(file_system);
+    let use_backups = backups_match_version(plan.file_system(), antigravity_path);
+
+    // 根据 enabled 状态处理侧边栏补丁
+    if features.enabled {
+        // 安装侧边栏补丁（extensions + workbench 双路径写入文件）
+        plan_cascade_patches(&mut plan, &extensions_dir, &workbench_dir, patch_files);
+    } else {
+        // 禁用时还原侧边栏文件
+        plan_cascade_restore(&mut plan, antigravity_path, use_backups)?;
+    }
+
+    // 根据 enabled 状态处理 Manager 补丁
+    if manager_features.enabled {
+        plan_manager_patches(&mut plan, &workbench_dir, patch_files);
+
+        // 清空 product.json 的 checksums 字段，消除"安装损坏"提示
+        // 已清空时按原内容写入: 配置中的摘要始终包含 product.json, 重复安装时配置文件不变
+        let product_json_path = antigravity_json_path(antigravity_path);
+        if let Some(original) = plan.content_of(&product_json_path)? {
+            let content =
+                cleared_product_json(&product_json_path, Some(&original))?.unwrap_or(original);
+            plan.write(product_json_path, content);
+        }
+    } else {
+        // 禁用时还原 Manager 文件
+        plan_manager_restore(&mut plan, antigravity_path, use_backups)?;
     }
 
-    // 备份 workbench.html
-    let workbench_html = workbench_dir.join("workbench.html");
-    let workbench_backup = workbench_dir.join("workbench.html.bak");
-    if workbench_html.exists() && !workbench_backup.exists() {
-        fs::copy(&workbench_html, &workbench_backup)
-            .map_err(|e| format!("备份 workbench.html 失败: {}", e))?;
+    // 最后注入 cascade 到 workbench.html（必须在 manager 覆盖之后）
+    if features.enabled {
+        let workbench_html = workbench_dir.join("workbench.html");
+        if let Some(content) = plan.content_of(&workbench_html)? {
+            let injected = inject_cascade_into_html(&content, &source.cascade_anchors)?;
+            plan.write(workbench_html, injected);
+        }
+    }
+
+    // 只安装部分文件时, 配置中的摘要只记录实际写入的文件
+    limit_to_targets(&mut plan, antigravity_path, targets);
+
+    // 配置文件最后生成, 附带补丁器版本与其余补丁文件的摘要供完整性校验
+    let meta = PatchMeta {
+        patcher_version: PATCHER_VERSION.to_string(),
+        payload_version: source.version.clone(),
+        file_hashes: planned_file_hashes(&plan, antigravity_path),
+    };
+    if features.enabled {
+        let config_content = cascade_config_content(features, &meta);
+        plan.write(
+            extensions_dir.join("cascade-panel").join("config.json"),
+            config_content.as_str(),
+        );
+        plan.write(workbench_dir.join("cascade-panel").join("config.json"), config_content);
+    }
+    if manager_features.enabled {
+        plan.write(
+            workbench_dir.join("manager-panel").join("config.json"),
+            manager_config_content(manager_features, &meta),
+        );
+    }
+    limit_to_targets(&mut plan, antigravity_path, targets);
+
+    Ok(plan)
+}
+
+/// 文件存在且还没有同级的 .bak 副本时复制一份
+fn copy_to_bak(file_system: &impl FileSystem, path: &Path) -> Result<(), String> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let backup = path.with_file_name(format!("{}.bak", name));
+    if file_system.exists(path) && !file_system.exists(&backup) {
+        let content =
+            file_system.read(path).map_err(|e| format!("备份 {} 失败: {}", name, e))?;
+        file_system
+            .write(&backup, &content)
+            .map_err(|e| format!("备份 {} 失败: {}", name, e))?;
     }
     Ok(())
 }
 
-/// 将 cascade CSS + JS 注入到 HTML 文件（幂等，不处理 .bak）
-fn inject_cascade_into_html(html_path: &PathBuf) -> Result<(), String> {
-    let content = fs::read_to_string(html_path)
-        .map_err(|e| format!("读取 HTML 失败: {}", e))?;
-    let mut result = content;
+/// 在锚点处插入内容; 找不到锚点说明文件结构已变, 返回错误而不是静默跳过注入
+/// 按字节查找与插入, 锚点之外的字节 (包括非法的 UTF-8) 原样保留
+fn insert_at_anchor(
+    content: &mut Vec<u8>,
+    anchor: &InjectionAnchor,
+    insertion: &str,
+) -> Result<(), PatchError> {
+    let index = bytes::find(content, anchor.token.as_bytes())
+        .ok_or_else(|| PatchError::AnchorNotFound { anchor: anchor.token.to_string() })?;
+    let index = match anchor.position {
+        AnchorPosition::Before => index,
+        AnchorPosition::After => index + anchor.token.len(),
+    };
+    bytes::insert_text(content, index, insertion);
+    Ok(())
+}
+
+/// 将 cascade CSS + JS 注入到 HTML 内容（幂等，不处理 .bak）
+fn inject_cascade_into_html(
+    content: &[u8],
+    anchors: &CascadeAnchors,
+) -> Result<Vec<u8>, PatchError> {
+    let mut result = content.to_vec();
 
     // TrustedTypes bypass
-    let marker = "[Antigravity-Power-Pro] Cascade Inject";
-    if !result.contains(marker) {
-        let tt_bypass = format!(
-            "<script>\n/* {} */\nif(window.trustedTypes&&!window.trustedTypes.defaultPolicy){{try{{window.trustedTypes.createPolicy(\"default\",{{createHTML:s=>s,createScript:s=>s,createScriptURL:s=>s}})}}catch(e){{}}}}\n</script>",
-            marker
-        );
-        result = result.replacen("<head>", &format!("<head>{}", tt_bypass), 1);
+    // 已有注入块 (可能来自旧版补丁) 时原位替换为当前内容, 重复注入的多余块一并删除
+    let tt_bypass = format!(
+        "<script>\n/* {} */\nif(window.trustedTypes&&!window.trustedTypes.defaultPolicy){{try{{window.trustedTypes.createPolicy(\"default\",{{createHTML:s=>s,createScript:s=>s,createScriptURL:s=>s}})}}catch(e){{}}}}\n</script>",
+        CASCADE_INJECT_MARKER
+    );
+    let mut existing_at = None;
+    while let Some(range) = cascade_inject_block(&result) {
+        existing_at.get_or_insert(range.start);
+        result.drain(range);
+    }
+    match existing_at {
+        Some(index) => bytes::insert_text(&mut result, index, &tt_bypass),
+        None => insert_at_anchor(&mut result, &anchors.bypass, &tt_bypass)?,
     }
 
     // Keep repeated installs idempotent even when older templates used a different tag spelling.
@@ -404,58 +1917,43 @@ fn inject_cascade_into_html(html_path: &PathBuf) -> Result<(), String> {
         "<link rel=\"stylesheet\" href=\"./cascade-panel/cascade-panel.css\"/>",
         "<link rel=\"stylesheet\" href=\"./cascade-panel/cascade-panel.css\" />",
     ] {
-        result = result.replace(tag, "");
+        bytes::remove_all(&mut result, tag.as_bytes());
     }
 
     for tag in [
         "<script src=\"./cascade-panel/cascade-panel.js\" type=\"module\"></script>",
         "<script type=\"module\" src=\"./cascade-panel/cascade-panel.js\"></script>",
     ] {
-        result = result.replace(tag, "");
+        bytes::remove_all(&mut result, tag.as_bytes());
     }
 
     // CSS
     let css_tag = "<link rel=\"stylesheet\" href=\"./cascade-panel/cascade-panel.css\">";
-    result = result.replacen("</head>", &format!("{}</head>", css_tag), 1);
+    insert_at_anchor(&mut result, &anchors.css, css_tag)?;
 
     // JS
     let js_tag = "<script src=\"./cascade-panel/cascade-panel.js\" type=\"module\"></script>";
-    result = result.replacen("</body>", &format!("{}</body>", js_tag), 1);
+    insert_at_anchor(&mut result, &anchors.js, js_tag)?;
 
-    fs::write(html_path, result)
-        .map_err(|e| format!("写入 HTML 失败: {}", e))?;
-    Ok(())
+    Ok(result)
 }
 
-/// 写入侧边栏补丁文件（extensions 目录 + workbench 目录双路径注入）
-fn write_cascade_patches(extensions_dir: &PathBuf, workbench_dir: &PathBuf, features: &FeatureConfig) -> Result<(), String> {
+/// 计划写入侧边栏补丁文件（extensions 目录 + workbench 目录双路径注入）
+fn plan_cascade_patches<F: FileSystem>(
+    plan: &mut PatchPlan<F>,
+    extensions_dir: &Path,
+    workbench_dir: &Path,
+    patch_files: &[(String, String)],
+) {
     let cascade_panel_dir = extensions_dir.join("cascade-panel");
     let wb_cascade_dir = workbench_dir.join("cascade-panel");
-    let wb_shared_dir = workbench_dir.join("shared");
 
     // 清理旧目录
-    if cascade_panel_dir.exists() {
-        fs::remove_dir_all(&cascade_panel_dir)
-            .map_err(|e| format!("删除旧 cascade-panel 目录失败: {}", e))?;
-    }
-    if wb_cascade_dir.exists() {
-        fs::remove_dir_all(&wb_cascade_dir)
-            .map_err(|e| format!("删除 workbench cascade-panel 目录失败: {}", e))?;
-    }
-    if wb_shared_dir.exists() {
-        fs::remove_dir_all(&wb_shared_dir)
-            .map_err(|e| format!("删除 workbench shared 目录失败: {}", e))?;
-    }
-
-    fs::create_dir_all(&cascade_panel_dir)
-        .map_err(|e| format!("创建 cascade-panel 目录失败: {}", e))?;
-    fs::create_dir_all(&wb_cascade_dir)
-        .map_err(|e| format!("创建 workbench cascade-panel 目录失败: {}", e))?;
-    fs::create_dir_all(&wb_shared_dir)
-        .map_err(|e| format!("创建 workbench shared 目录失败: {}", e))?;
+    plan.remove_dir(cascade_panel_dir);
+    plan.remove_dir(wb_cascade_dir);
+    plan.remove_dir(workbench_dir.join("shared"));
 
     // 写入补丁文件到两个目录
-    let patch_files = embedded::get_all_files_runtime()?;
     for (relative_path, content) in patch_files {
         let is_cascade = relative_path == "cascade-panel.html"
             || relative_path.starts_with("cascade-panel/")
@@ -465,58 +1963,30 @@ fn write_cascade_patches(extensions_dir: &PathBuf, workbench_dir: &PathBuf, feat
         }
 
         // 写到 extensions 目录
-        let ext_path = extensions_dir.join(&relative_path);
-        if let Some(p) = ext_path.parent() { fs::create_dir_all(p).ok(); }
-        fs::write(&ext_path, &content)
-            .map_err(|e| format!("写入 extensions 失败 {:?}: {}", ext_path, e))?;
+        plan.write(extensions_dir.join(relative_path), content.as_str());
 
-        // cascade-panel/ 同步写到 workbench 目录
-        if relative_path.starts_with("cascade-panel/") {
-            let wb_path = workbench_dir.join(&relative_path);
-            if let Some(p) = wb_path.parent() { fs::create_dir_all(p).ok(); }
-            fs::write(&wb_path, &content)
-                .map_err(|e| format!("写入 workbench 失败 {:?}: {}", wb_path, e))?;
-        }
-        if relative_path.starts_with("shared/") {
-            let wb_path = workbench_dir.join(&relative_path);
-            if let Some(p) = wb_path.parent() { fs::create_dir_all(p).ok(); }
-            fs::write(&wb_path, &content).ok();
+        // cascade-panel/ 与 shared/ 同步写到 workbench 目录
+        if relative_path.starts_with("cascade-panel/") || relative_path.starts_with("shared/") {
+            plan.write(workbench_dir.join(relative_path), content.as_str());
         }
     }
 
-    // 生成配置文件（两处）
-    let cascade_config_path = cascade_panel_dir.join("config.json");
-    write_config_file(&cascade_config_path, features)?;
-    let wb_cascade_config = wb_cascade_dir.join("config.json");
-    write_config_file(&wb_cascade_config, features)?;
-
-    // 注意: workbench.html 注入已移到 install_patch 末尾执行（在 manager 覆盖之后）
-    Ok(())
+    // 注意: workbench.html 注入与配置文件生成在 plan_antigravity_patch 末尾执行（在 manager 覆盖之后）
 }
 
-/// 写入 Manager 补丁文件
-fn write_manager_patches(workbench_dir: &PathBuf, manager_features: &ManagerFeatureConfig) -> Result<(), String> {
+/// 计划写入 Manager 补丁文件
+fn plan_manager_patches<F: FileSystem>(
+    plan: &mut PatchPlan<F>,
+    workbench_dir: &Path,
+    patch_files: &[(String, String)],
+) {
     let manager_panel_dir = workbench_dir.join("manager-panel");
-    let shared_dir = workbench_dir.join("shared");
-    
+
     // 先删除旧目录, 确保文件结构干净
-    if manager_panel_dir.exists() {
-        fs::remove_dir_all(&manager_panel_dir)
-            .map_err(|e| format!("删除旧 manager-panel 目录失败: {}", e))?;
-    }
-    if shared_dir.exists() {
-        fs::remove_dir_all(&shared_dir)
-            .map_err(|e| format!("删除旧 shared 目录失败: {}", e))?;
-    }
-    
-    // 创建目录
-    fs::create_dir_all(&manager_panel_dir)
-        .map_err(|e| format!("创建 manager-panel 目录失败: {}", e))?;
-    fs::create_dir_all(&shared_dir)
-        .map_err(|e| format!("创建 shared 目录失败: {}", e))?;
-    
+    plan.remove_dir(manager_panel_dir);
+    plan.remove_dir(workbench_dir.join("shared"));
+
     // 写入 Manager 相关补丁文件
-    let patch_files = embedded::get_all_files_runtime()?;
     for (relative_path, content) in patch_files {
         // 只处理 Manager 相关文件 (Antigravity 用 workbench-antigravity.html 覆盖 workbench.html)
         if relative_path != "workbench-jetski-agent.html" && 
@@ -529,32 +1999,27 @@ fn write_manager_patches(workbench_dir: &PathBuf, manager_features: &ManagerFeat
         let target_name = if relative_path == "workbench-antigravity.html" {
             "workbench.html"
         } else {
-            &relative_path
+            relative_path
         };
 
-        let full_path = workbench_dir.join(target_name);
-        
-        // 确保父目录存在
-        if let Some(parent) = full_path.parent() {
-            if !parent.exists() {
-                fs::create_dir_all(parent)
-                    .map_err(|e| format!("创建目录失败: {}", e))?;
-            }
-        }
-        
-        fs::write(&full_path, content)
-            .map_err(|e| format!("写入文件失败 {:?}: {}", full_path, e))?;
+        plan.write(workbench_dir.join(target_name), content.as_str());
     }
-    
-    // 生成 Manager 配置文件
-    let manager_config_path = manager_panel_dir.join("config.json");
-    write_manager_config_file(&manager_config_path, manager_features)?;
+}
 
-    Ok(())
+/// 安装时写入配置文件的补丁元信息, 更新配置时原样沿用
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct PatchMeta {
+    #[serde(rename = "patcherVersion")]
+    patcher_version: String,
+    #[serde(rename = "payloadVersion")]
+    payload_version: String,
+    #[serde(rename = "fileHashes")]
+    file_hashes: BTreeMap<String, String>,
 }
 
-/// 侧边栏配置文件生成
-fn write_config_file(config_path: &PathBuf, features: &FeatureConfig) -> Result<(), String> {
+/// 侧边栏配置文件内容
+fn cascade_config_content(features: &FeatureConfig, meta: &PatchMeta) -> String {
     let config_content = serde_json::json!({
         "featureDefaultsVersion": FEATURE_DEFAULTS_VERSION,
         "scrollToBottom": features.scroll_to_bottom,
@@ -569,11 +2034,26 @@ fn write_config_file(config_path: &PathBuf, features: &FeatureConfig) -> Result<
             "apiKey": features.prompt_enhance.api_key,
             "model": features.prompt_enhance.model,
             "systemPrompt": features.prompt_enhance.system_prompt
-        }
+        },
+        "patcherVersion": meta.patcher_version,
+        "payloadVersion": meta.payload_version,
+        "fileHashes": meta.file_hashes
     });
-    
-    fs::write(config_path, serde_json::to_string_pretty(&config_content).unwrap())
-        .map_err(|e| format!("写入侧边栏配置失败: {}", e))?;
+
+    serde_json::to_string_pretty(&config_content).unwrap()
+}
+
+/// 写入侧边栏配置文件
+fn write_config_file(
+    config_path: &Path,
+    features: &FeatureConfig,
+    meta: &PatchMeta,
+    mode: ConfigMergeMode,
+) -> Result<(), PatchError> {
+    let content =
+        merged_config_content(config_path, &cascade_config_content(features, meta), mode);
+    write_replacing(config_path, content.as_bytes())
+        .map_err(|e| PatchError::io(config_path, "写入侧边栏配置失败", e))?;
     
     Ok(())
 }
@@ -582,8 +2062,8 @@ fn clamp_side_padding(value: f32) -> f32 {
     value.clamp(0.0, 48.0)
 }
 
-/// Manager 配置文件生成
-fn write_manager_config_file(config_path: &PathBuf, features: &ManagerFeatureConfig) -> Result<(), String> {
+/// Manager 配置文件内容
+fn manager_config_content(features: &ManagerFeatureConfig, meta: &PatchMeta) -> String {
     let config_content = serde_json::json!({
         "featureDefaultsVersion": FEATURE_DEFAULTS_VERSION,
         "scrollToBottom": features.scroll_to_bottom,
@@ -596,73 +2076,141 @@ fn write_manager_config_file(config_path: &PathBuf, features: &ManagerFeatureCon
             "apiKey": features.prompt_enhance.api_key,
             "model": features.prompt_enhance.model,
             "systemPrompt": features.prompt_enhance.system_prompt
-        }
+        },
+        "patcherVersion": meta.patcher_version,
+        "payloadVersion": meta.payload_version,
+        "fileHashes": meta.file_hashes
     });
-    
-    fs::write(config_path, serde_json::to_string_pretty(&config_content).unwrap())
-        .map_err(|e| format!("写入 Manager 配置失败: {}", e))?;
+
+    serde_json::to_string_pretty(&config_content).unwrap()
+}
+
+/// 写入 Manager 配置文件
+fn write_manager_config_file(
+    config_path: &Path,
+    features: &ManagerFeatureConfig,
+    meta: &PatchMeta,
+    mode: ConfigMergeMode,
+) -> Result<(), PatchError> {
+    let content =
+        merged_config_content(config_path, &manager_config_content(features, meta), mode);
+    write_replacing(config_path, content.as_bytes())
+        .map_err(|e| PatchError::io(config_path, "写入 Manager 配置失败", e))?;
     
     Ok(())
 }
 
-/// 恢复侧边栏文件 (禁用补丁时调用)
-fn restore_cascade_files(extensions_dir: &PathBuf) -> Result<(), String> {
-    // 恢复 cascade-panel.html
-    let cascade_panel = extensions_dir.join("cascade-panel.html");
-    let cascade_backup = extensions_dir.join("cascade-panel.html.bak");
-    if cascade_backup.exists() {
-        fs::copy(&cascade_backup, &cascade_panel)
-            .map_err(|e| format!("恢复 cascade-panel.html 失败: {}", e))?;
+/// 按 mode 把补丁器管理的配置项合并进已有的配置文件
+/// 已有文件缺失或无法解析时直接使用新内容
+fn merged_config_content(
+    config_path: &Path,
+    managed_content: &str,
+    mode: ConfigMergeMode,
+) -> String {
+    let existing = fs::read_to_string(config_path).ok();
+    merge_config_text(existing.as_deref(), managed_content, mode)
+}
+
+/// merged_config_content 的合并部分: existing 为已有文件的内容
+fn merge_config_text(existing: Option<&str>, managed_content: &str, mode: ConfigMergeMode) -> String {
+    if mode == ConfigMergeMode::Replace {
+        return managed_content.to_string();
     }
+    let existing = existing
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(strip_bom(content)).ok())
+        .filter(serde_json::Value::is_object);
+    let managed = serde_json::from_str::<serde_json::Value>(managed_content);
+    let (Some(mut existing), Ok(managed)) = (existing, managed) else {
+        return managed_content.to_string();
+    };
+
+    match (mode, existing.as_object_mut(), managed) {
+        (ConfigMergeMode::ManagedKeysOnly, Some(target), serde_json::Value::Object(managed)) => {
+            target.extend(managed);
+        }
+        (_, _, managed) => merge_json(&mut existing, managed),
+    }
+    serde_json::to_string_pretty(&existing).unwrap()
+}
 
-    // 删除侧边栏补丁目录
-    let cascade_dir = extensions_dir.join("cascade-panel");
-    if cascade_dir.exists() {
-        fs::remove_dir_all(&cascade_dir)
-            .map_err(|e| format!("删除 cascade-panel 目录失败: {}", e))?;
+/// 递归合并对象: target 中同名字段被覆盖, 只存在于 target 的字段保留
+fn merge_json(target: &mut serde_json::Value, source: serde_json::Value) {
+    match (target, source) {
+        (serde_json::Value::Object(target), serde_json::Value::Object(source)) => {
+            for (key, value) in source {
+                match target.get_mut(&key) {
+                    // fileHashes 是补丁文件清单, 整体替换以免残留已不存在的文件
+                    Some(existing) if key != "fileHashes" => merge_json(existing, value),
+                    _ => {
+                        target.insert(key, value);
+                    }
+                }
+            }
+        }
+        (target, source) => *target = source,
     }
+}
 
-    Ok(())
+/// 读取已安装配置中记录的补丁元信息 (更新配置时沿用, 不信任前端传入的值)
+fn stored_patch_meta(file_system: &impl FileSystem, config_path: &Path) -> PatchMeta {
+    file_system
+        .read(config_path)
+        .ok()
+        .and_then(|content| String::from_utf8(content).ok())
+        .and_then(|content| serde_json::from_str(strip_bom(&content)).ok())
+        .unwrap_or_default()
 }
 
-/// 恢复 Manager 文件 (禁用补丁时调用)
-fn restore_manager_files(workbench_dir: &PathBuf) -> Result<(), String> {
-    // 恢复 workbench-jetski-agent.html
-    let jetski_agent = workbench_dir.join("workbench-jetski-agent.html");
-    let jetski_backup = workbench_dir.join("workbench-jetski-agent.html.bak");
-    if jetski_backup.exists() {
-        fs::copy(&jetski_backup, &jetski_agent)
-            .map_err(|e| format!("恢复 workbench-jetski-agent.html 失败: {}", e))?;
+/// 备份与当前编辑器版本一致 (或版本未知); 不一致时还原改为剥离补丁标记, 避免把文件降级
+///
+/// 用于不让用户选择的场景: 重新安装, 禁用组件, 卸载面板补丁
+fn backups_match_version(file_system: &impl FileSystem, install_path: &Path) -> bool {
+    match check_backup_version(file_system, install_path) {
+        Ok(()) => true,
+        Err(e) => {
+            log::warn!("备份与当前编辑器版本不一致, 改为剥离补丁标记: {:?}", e);
+            false
+        }
     }
+}
 
-    // 恢复 workbench.html
-    let workbench_html = workbench_dir.join("workbench.html");
-    let workbench_backup = workbench_dir.join("workbench.html.bak");
-    if workbench_backup.exists() {
-        fs::copy(&workbench_backup, &workbench_html)
-            .map_err(|e| format!("恢复 workbench.html 失败: {}", e))?;
-    }
+/// 计划还原侧边栏文件 (禁用补丁或卸载时调用)
+fn plan_cascade_restore<F: FileSystem>(
+    plan: &mut PatchPlan<F>,
+    antigravity_path: &Path,
+    use_backups: bool,
+) -> Result<Vec<RestoredFile>, PatchError> {
+    let (extensions_dir, _) = antigravity_dirs(antigravity_path);
+    let restored = vec![plan_restore(plan, antigravity_path, CASCADE_PANEL_HTML, use_backups)?];
 
-    // 删除 Manager 补丁目录
-    let manager_dir = workbench_dir.join("manager-panel");
-    if manager_dir.exists() {
-        fs::remove_dir_all(&manager_dir)
-            .map_err(|e| format!("删除 manager-panel 目录失败: {}", e))?;
-    }
+    // 删除侧边栏补丁目录
+    plan.remove_dir(extensions_dir.join("cascade-panel"));
 
-    Ok(())
+    Ok(restored)
 }
 
-/// 恢复所有备份文件 (完全卸载时调用)
-fn restore_backup_files(extensions_dir: &PathBuf, workbench_dir: &PathBuf) -> Result<(), String> {
-    restore_cascade_files(extensions_dir)?;
-    restore_manager_files(workbench_dir)?;
-    Ok(())
+/// 计划还原 Manager 文件 (禁用补丁或卸载时调用)
+fn plan_manager_restore<F: FileSystem>(
+    plan: &mut PatchPlan<F>,
+    antigravity_path: &Path,
+    use_backups: bool,
+) -> Result<Vec<RestoredFile>, PatchError> {
+    let (_, workbench_dir) = antigravity_dirs(antigravity_path);
+    let restored = vec![
+        plan_restore(plan, antigravity_path, JETSKI_AGENT_HTML, use_backups)?,
+        plan_restore(plan, antigravity_path, WORKBENCH_HTML, use_backups)?,
+    ];
+
+    // 删除 Manager 补丁目录
+    plan.remove_dir(workbench_dir.join("manager-panel"));
+
+    Ok(restored)
 }
 
 // ============================================
-// Windsurf IDE 补丁
+// Windsurf / Cursor 补丁
 // ============================================
+// 两者都是 VS Code 系编辑器, 共用 windsurf-panel 面板, 差异由 Editor 描述
 
 /// Windsurf 功能配置
 #[derive(Debug, Serialize, Deserialize)]
@@ -692,113 +2240,299 @@ impl Default for WindsurfFeatureConfig {
     }
 }
 
-/// 安装 Windsurf 补丁
+impl WindsurfFeatureConfig {
+    fn disable_all(&mut self) {
+        self.scroll_to_bottom = false;
+        self.font_size_enabled = false;
+        self.prompt_enhance.enabled = false;
+    }
+}
+
+/// Cursor 复用 Windsurf 面板, 配置结构相同
+pub type CursorFeatureConfig = WindsurfFeatureConfig;
+
+/// 没有 workbench.html 模板的编辑器 (Cursor) 注入的面板标签
+const PANEL_CSS_TAG: &str =
+    "<link rel=\"stylesheet\" href=\"./windsurf-panel/windsurf-panel.css\" />";
+const PANEL_JS_TAG: &str =
+    "<script src=\"./windsurf-panel/windsurf-panel.js\" type=\"module\"></script>";
+
+/// 安装 Windsurf 补丁; Windsurf 运行中时返回 EditorRunning, force 为 true 时跳过检查
+#[tauri::command(async)]
+pub fn install_windsurf_patch(
+    path: String,
+    features: WindsurfFeatureConfig,
+    on_progress: Channel<PatchProgress>,
+    force: Option<bool>,
+) -> Result<(), PatchError> {
+    install_panel_patch(Editor::Windsurf, &path, &features, force, &on_progress)
+}
+
+/// 卸载 Windsurf 补丁 (force 同安装)
 #[tauri::command]
-pub fn install_windsurf_patch(path: String, features: WindsurfFeatureConfig) -> Result<(), String> {
-    let windsurf_path = PathBuf::from(&path);
-    let workbench_dir = windsurf_path
-        .join("resources").join("app").join("out")
-        .join("vs").join("code").join("electron-browser").join("workbench");
+pub fn uninstall_windsurf_patch(
+    path: String,
+    force: Option<bool>,
+) -> Result<UninstallReport, PatchError> {
+    uninstall_panel_patch(Editor::Windsurf, &path, force)
+}
 
-    if !workbench_dir.exists() {
-        return Err("无效的 Windsurf 安装目录".to_string());
-    }
+/// 更新 Windsurf 配置, mode 缺省为 ManagedKeysOnly
+#[tauri::command]
+pub fn update_windsurf_config(
+    path: String,
+    features: WindsurfFeatureConfig,
+    mode: Option<ConfigMergeMode>,
+) -> Result<(), PatchError> {
+    update_panel_config(Editor::Windsurf, &path, features, mode.unwrap_or_default())
+}
+
+/// 检测 Windsurf 补丁状态
+#[tauri::command]
+pub fn check_windsurf_patch_status(path: String) -> Result<bool, PatchError> {
+    Ok(panel_patch_present(Path::new(&path)))
+}
 
-    backup_windsurf_files(&workbench_dir)?;
-    write_windsurf_patches(&workbench_dir, &features)?;
+/// 读取 Windsurf 补丁配置
+#[tauri::command]
+pub fn read_windsurf_patch_config(
+    path: String,
+) -> Result<Option<WindsurfFeatureConfig>, PatchError> {
+    read_panel_config(Editor::Windsurf, &path)
+}
 
-    let product_json = windsurf_path
-        .join("resources").join("app").join("product.json");
-    clear_product_checksums(&product_json)?;
+/// 安装 Cursor 补丁
+///
+/// Cursor 没有对应的 workbench.html 模板, 在原文件中注入面板标签而不是整体覆盖;
+/// Cursor 运行中时返回 EditorRunning, force 为 true 时跳过检查
+#[tauri::command(async)]
+pub fn install_cursor_patch(
+    path: String,
+    features: CursorFeatureConfig,
+    on_progress: Channel<PatchProgress>,
+    force: Option<bool>,
+) -> Result<(), PatchError> {
+    install_panel_patch(Editor::Cursor, &path, &features, force, &on_progress)
+}
 
-    Ok(())
+/// 卸载 Cursor 补丁 (force 同安装)
+#[tauri::command]
+pub fn uninstall_cursor_patch(
+    path: String,
+    force: Option<bool>,
+) -> Result<UninstallReport, PatchError> {
+    uninstall_panel_patch(Editor::Cursor, &path, force)
 }
 
-/// 卸载 Windsurf 补丁
+/// 更新 Cursor 配置, mode 缺省为 ManagedKeysOnly
 #[tauri::command]
-pub fn uninstall_windsurf_patch(path: String) -> Result<(), String> {
-    let windsurf_path = PathBuf::from(&path);
-    let workbench_dir = windsurf_path
-        .join("resources").join("app").join("out")
-        .join("vs").join("code").join("electron-browser").join("workbench");
+pub fn update_cursor_config(
+    path: String,
+    features: CursorFeatureConfig,
+    mode: Option<ConfigMergeMode>,
+) -> Result<(), PatchError> {
+    update_panel_config(Editor::Cursor, &path, features, mode.unwrap_or_default())
+}
 
-    if !workbench_dir.exists() {
-        return Err("无效的 Windsurf 安装目录".to_string());
+/// 检测 Cursor 补丁状态
+#[tauri::command]
+pub fn check_cursor_patch_status(path: String) -> Result<bool, PatchError> {
+    Ok(panel_patch_present(Path::new(&path)))
+}
+
+/// 面板补丁是否存在: 有补丁配置, 或配置被删除但 workbench.html 中仍有补丁标记
+fn panel_patch_present(install_path: &Path) -> bool {
+    panel_config_path(install_path).exists()
+        || file_state(install_path, WORKBENCH_HTML).marker_present
+}
+
+/// 面板配置文件路径
+pub fn panel_config_path(install_path: &Path) -> PathBuf {
+    resolve_relative(install_path, PANEL_CONFIG_JSON)
+}
+
+/// 安装面板补丁
+fn install_panel_patch(
+    editor: Editor,
+    path: &str,
+    features: &WindsurfFeatureConfig,
+    force: Option<bool>,
+    on_progress: &Channel<PatchProgress>,
+) -> Result<(), PatchError> {
+    let result = ensure_editor_closed(editor, force)
+        .and_then(|()| apply_panel_patch(editor, path, features, on_progress));
+    log_result(&format!("安装 {} 补丁", editor.name()), path, &result);
+    result
+}
+
+fn apply_panel_patch(
+    editor: Editor,
+    path: &str,
+    features: &WindsurfFeatureConfig,
+    on_progress: &Channel<PatchProgress>,
+) -> Result<(), PatchError> {
+    verify_embedded_payload()?;
+    let _lock = PatchLock::acquire()?;
+    report(on_progress, 0, "检测安装目录");
+    let install_path = PathBuf::from(path);
+    let workbench_dir = workbench_dir(&install_path);
+
+    if !install_path.join(editor.hook_file()).exists() {
+        return Err(if is_packaged_as_asar(&install_path) {
+            PatchError::PackagedAsAsar
+        } else {
+            PatchError::PathNotFound
+        });
     }
 
-    restore_windsurf_files(&workbench_dir)?;
+    ensure_writable(&write_dirs(&install_path))?;
+    ensure_no_legacy_patch(&install_path)?;
+    let product_json = install_path.join(PRODUCT_JSON);
 
-    // 恢复 product.json 备份
-    let product_json = windsurf_path
-        .join("resources").join("app").join("product.json");
-    let product_backup = product_json.with_extension("json.bak");
-    if product_backup.exists() {
-        fs::copy(&product_backup, &product_json)
-            .map_err(|e| format!("恢复 product.json 失败: {}", e))?;
-        fs::remove_file(&product_backup)
-            .map_err(|e| format!("删除 product.json 备份失败: {}", e))?;
+    // 先生成计划: 找不到锚点等错误不留下备份, 已是同样的补丁内容时不备份也不重写
+    let mut plan = PatchPlan::default();
+    plan_panel_patches(editor, &mut plan, &install_path, features)?;
+    let original = plan.content_of(&product_json)?;
+    if let Some(content) = cleared_product_json(&product_json, original.as_deref())? {
+        plan.write(product_json.clone(), content);
+    }
+    if plan.is_noop() {
+        log::info!("{} 补丁已是最新, 无需重复安装: {}", editor.name(), path);
+        report(on_progress, 100, "补丁已是最新");
+        return Ok(());
     }
 
+    report(on_progress, 5, "备份原版文件");
+    backup_files(&DiskFs, &install_path, PANEL_OVERWRITTEN_FILES)
+        .map_err(PatchError::BackupFailed)?;
+    create_backup_set(&DiskFs, editor, &install_path, PANEL_BACKUP_SET_FILES, None)
+        .map_err(PatchError::BackupFailed)?;
+    backup_workbench_html(&workbench_dir).map_err(PatchError::BackupFailed)?;
+    backup_product_json(&DiskFs, &product_json).map_err(PatchError::BackupFailed)?;
+
+    plan.apply_with_progress(|index, total, path| {
+        report_write(on_progress, index, total, path)
+    })?;
+    report(on_progress, 100, "安装完成");
     Ok(())
 }
 
-/// 更新 Windsurf 配置
-#[tauri::command]
-pub fn update_windsurf_config(path: String, features: WindsurfFeatureConfig) -> Result<(), String> {
-    let windsurf_path = PathBuf::from(&path);
-    let config_path = windsurf_path
-        .join("resources").join("app").join("out")
-        .join("vs").join("code").join("electron-browser").join("workbench")
-        .join("windsurf-panel").join("config.json");
+/// 卸载面板补丁, 编辑器运行中时返回 EditorRunning (force 时不检查)
+fn uninstall_panel_patch(
+    editor: Editor,
+    path: &str,
+    force: Option<bool>,
+) -> Result<UninstallReport, PatchError> {
+    let result = ensure_editor_closed(editor, force).and_then(|()| restore_panel_files(path));
+    log_result("卸载面板补丁", path, &result);
+    result
+}
 
-    if !config_path.parent().map(|p| p.exists()).unwrap_or(false) {
-        return Err("Windsurf 补丁尚未安装".to_string());
+fn restore_panel_files(path: &str) -> Result<UninstallReport, PatchError> {
+    let install_path = PathBuf::from(path);
+    let workbench_dir = workbench_dir(&install_path);
+
+    ensure_install_exists(&install_path)?;
+    if !workbench_dir.exists() {
+        return Err(PatchError::PathNotFound);
+    }
+    let _lock = PatchLock::acquire()?;
+    ensure_writable(&write_dirs(&install_path))?;
+
+    let use_backups = backups_match_version(&DiskFs, &install_path);
+    let (plan, report) = plan_panel_restore(DiskFs, &install_path, use_backups)?;
+    plan.apply()?;
+    ensure_no_residual_markers(&DiskFs, &report)?;
+    Ok(report)
+}
+
+/// 计算卸载面板补丁的全部改动
+fn plan_panel_restore<F: FileSystem>(
+    file_system: F,
+    install_path: &Path,
+    use_backups: bool,
+) -> Result<(PatchPlan<F>, UninstallReport), PatchError> {
+    let workbench_dir = workbench_dir(install_path);
+    let mut plan = PatchPlan::with_file_system(file_system);
+    let mut report = UninstallReport::default();
+    for relative_path in PANEL_OVERWRITTEN_FILES {
+        let restored = plan_restore(&mut plan, install_path, relative_path, use_backups)?;
+        // 卸载后不保留同级 .bak
+        plan.remove_file(PathBuf::from(format!("{}.bak", restored.path)));
+        report.files.push(restored);
     }
+    report.mark_best_effort(plan.file_system().exists(&panel_config_path(install_path)));
 
-    write_windsurf_config_file(&config_path, &features)?;
-    Ok(())
+    plan.remove_dir(workbench_dir.join("windsurf-panel"));
+    plan.remove_dir(workbench_dir.join("shared"));
+
+    Ok((plan, report))
 }
 
-/// 检测 Windsurf 补丁状态
-#[tauri::command]
-pub fn check_windsurf_patch_status(path: String) -> Result<bool, String> {
-    let windsurf_path = PathBuf::from(&path);
-    let config_path = windsurf_path
-        .join("resources").join("app").join("out")
-        .join("vs").join("code").join("electron-browser").join("workbench")
-        .join("windsurf-panel").join("config.json");
+/// 更新面板配置
+fn update_panel_config(
+    editor: Editor,
+    path: &str,
+    features: WindsurfFeatureConfig,
+    mode: ConfigMergeMode,
+) -> Result<(), PatchError> {
+    let result = write_panel_config(editor, path, features, mode);
+    log_result(&format!("更新 {} 配置", editor.name()), path, &result);
+    result
+}
+
+fn write_panel_config(
+    editor: Editor,
+    path: &str,
+    mut features: WindsurfFeatureConfig,
+    mode: ConfigMergeMode,
+) -> Result<(), PatchError> {
+    let config_path = panel_config_path(Path::new(path));
 
-    Ok(config_path.exists())
+    if !config_path.parent().map(|p| p.exists()).unwrap_or(false) {
+        return Err(PatchError::NotPatched);
+    }
+
+    features.prompt_enhance.normalize()?;
+    if !editor_enabled(editor) {
+        log::info!("{} 补丁已在配置中停用, 写入关闭全部功能的配置", editor.name());
+        features.disable_all();
+    }
+    let content = merged_config_content(&config_path, &panel_config_content(&features), mode);
+    write_replacing(&config_path, content.as_bytes()).map_err(|e| {
+        PatchError::io(&config_path, format!("写入 {} 配置失败", editor.name()), e)
+    })?;
+    Ok(())
 }
 
-/// 读取 Windsurf 补丁配置
-#[tauri::command]
-pub fn read_windsurf_patch_config(path: String) -> Result<Option<WindsurfFeatureConfig>, String> {
-    let windsurf_path = PathBuf::from(&path);
-    let config_path = windsurf_path
-        .join("resources").join("app").join("out")
-        .join("vs").join("code").join("electron-browser").join("workbench")
-        .join("windsurf-panel").join("config.json");
+/// 读取面板配置
+pub fn read_panel_config(
+    editor: Editor,
+    path: &str,
+) -> Result<Option<WindsurfFeatureConfig>, PatchError> {
+    let config_path = panel_config_path(Path::new(path));
 
     if !config_path.exists() {
         return Ok(None);
     }
 
-    let content = fs::read_to_string(&config_path)
-        .map_err(|e| format!("读取 Windsurf 配置失败: {}", e))?;
-    let raw: serde_json::Value = serde_json::from_str(&content)
-        .map_err(|e| format!("解析 Windsurf 配置失败: {}", e))?;
+    let content = fs::read_to_string(&config_path).map_err(|e| {
+        PatchError::io(&config_path, format!("读取 {} 配置失败", editor.name()), e)
+    })?;
+    let parse_error =
+        |e: serde_json::Error| PatchError::Io(format!("解析 {} 配置失败: {}", editor.name(), e));
+    let raw: serde_json::Value =
+        serde_json::from_str(strip_bom(&content)).map_err(parse_error)?;
     let is_legacy_config = raw.get("featureDefaultsVersion").is_none();
-    let mut config: WindsurfFeatureConfig = serde_json::from_value(raw)
-        .map_err(|e| format!("解析 Windsurf 配置失败: {}", e))?;
+    let mut config: WindsurfFeatureConfig = serde_json::from_value(raw).map_err(parse_error)?;
     if is_legacy_config {
         config.feature_defaults_version = 0;
     }
     Ok(Some(config))
 }
 
-/// 备份 Windsurf workbench.html
-fn backup_windsurf_files(workbench_dir: &PathBuf) -> Result<(), String> {
+/// 备份 workbench.html（仅首次）
+fn backup_workbench_html(workbench_dir: &Path) -> Result<(), String> {
     let workbench_html = workbench_dir.join("workbench.html");
     let backup = workbench_dir.join("workbench.html.bak");
     if workbench_html.exists() && !backup.exists() {
@@ -808,59 +2542,44 @@ fn backup_windsurf_files(workbench_dir: &PathBuf) -> Result<(), String> {
     Ok(())
 }
 
-/// 写入 Windsurf 补丁文件
-fn write_windsurf_patches(workbench_dir: &PathBuf, features: &WindsurfFeatureConfig) -> Result<(), String> {
+/// 计划写入面板补丁文件
+/// 有模板的编辑器覆盖 workbench.html, 否则在原文件中注入面板标签
+fn plan_panel_patches(
+    editor: Editor,
+    plan: &mut PatchPlan,
+    install_path: &Path,
+    features: &WindsurfFeatureConfig,
+) -> Result<(), PatchError> {
+    let workbench_dir = workbench_dir(install_path);
+    let workbench_html = workbench_dir.join("workbench.html");
     let panel_dir = workbench_dir.join("windsurf-panel");
-    let shared_dir = workbench_dir.join("shared");
-
-    if panel_dir.exists() {
-        fs::remove_dir_all(&panel_dir)
-            .map_err(|e| format!("删除旧 windsurf-panel 目录失败: {}", e))?;
-    }
-    if shared_dir.exists() {
-        fs::remove_dir_all(&shared_dir)
-            .map_err(|e| format!("删除旧 shared 目录失败: {}", e))?;
-    }
-    fs::create_dir_all(&panel_dir)
-        .map_err(|e| format!("创建 windsurf-panel 目录失败: {}", e))?;
-    fs::create_dir_all(&shared_dir)
-        .map_err(|e| format!("创建 shared 目录失败: {}", e))?;
-
-    let patch_files = embedded::get_all_files_runtime()?;
-    for (relative_path, content) in patch_files {
-        if relative_path != "workbench-windsurf.html"
-            && !relative_path.starts_with("windsurf-panel/")
-            && !relative_path.starts_with("shared/")
-        {
-            continue;
-        }
+    let template = editor.workbench_template();
 
-        // workbench-windsurf.html 写入为 workbench.html
-        let target_path = if relative_path == "workbench-windsurf.html" {
-            workbench_dir.join("workbench.html")
-        } else {
-            workbench_dir.join(&relative_path)
-        };
+    plan.remove_dir(panel_dir.clone());
+    plan.remove_dir(workbench_dir.join("shared"));
 
-        if let Some(parent) = target_path.parent() {
-            if !parent.exists() {
-                fs::create_dir_all(parent)
-                    .map_err(|e| format!("创建目录失败: {}", e))?;
-            }
+    for (relative_path, content) in embedded::get_all_files_runtime().map_err(PatchError::Io)? {
+        if template == Some(relative_path.as_str()) {
+            // 模板写入为 workbench.html
+            plan.write(workbench_html.clone(), content);
+        } else if relative_path.starts_with("windsurf-panel/") || relative_path.starts_with("shared/") {
+            plan.write(workbench_dir.join(&relative_path), content);
         }
+    }
 
-        fs::write(&target_path, content)
-            .map_err(|e| format!("写入文件失败 {:?}: {}", target_path, e))?;
+    if template.is_none() {
+        let original = fs::read(&workbench_html)
+            .map_err(|e| PatchError::io(&workbench_html, "读取 workbench.html 失败", e))?;
+        plan.write(workbench_html, inject_panel_into_html(&original)?);
     }
 
-    let config_path = panel_dir.join("config.json");
-    write_windsurf_config_file(&config_path, features)?;
+    plan.write(panel_dir.join("config.json"), panel_config_content(features));
 
     Ok(())
 }
 
-/// 写入 Windsurf 配置文件
-fn write_windsurf_config_file(config_path: &PathBuf, features: &WindsurfFeatureConfig) -> Result<(), String> {
+/// 面板配置文件内容
+fn panel_config_content(features: &WindsurfFeatureConfig) -> String {
     let config_content = serde_json::json!({
         "featureDefaultsVersion": FEATURE_DEFAULTS_VERSION,
         "scrollToBottom": features.scroll_to_bottom,
@@ -876,69 +2595,392 @@ fn write_windsurf_config_file(config_path: &PathBuf, features: &WindsurfFeatureC
         }
     });
 
-    fs::write(config_path, serde_json::to_string_pretty(&config_content).unwrap())
-        .map_err(|e| format!("写入 Windsurf 配置失败: {}", e))?;
-    Ok(())
+    serde_json::to_string_pretty(&config_content).unwrap()
 }
 
-/// 恢复 Windsurf 文件
-fn restore_windsurf_files(workbench_dir: &PathBuf) -> Result<(), String> {
-    let workbench_html = workbench_dir.join("workbench.html");
-    let backup = workbench_dir.join("workbench.html.bak");
-    if backup.exists() {
-        fs::copy(&backup, &workbench_html)
-            .map_err(|e| format!("恢复 workbench.html 失败: {}", e))?;
-        fs::remove_file(&backup)
-            .map_err(|e| format!("删除备份失败: {}", e))?;
+/// 将面板 CSS + JS 注入 workbench.html（幂等）
+fn inject_panel_into_html(content: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let mut result = allow_default_trusted_type(content);
+
+    if !bytes::contains(&result, PANEL_CSS_TAG.as_bytes()) {
+        insert_at_anchor(&mut result, &PANEL_CSS_ANCHOR, &format!("\t{}\n\t", PANEL_CSS_TAG))?;
+    }
+    if !bytes::contains(&result, PANEL_JS_TAG.as_bytes()) {
+        insert_at_anchor(&mut result, &PANEL_JS_ANCHOR, &format!("\t{}\n", PANEL_JS_TAG))?;
     }
 
-    let panel_dir = workbench_dir.join("windsurf-panel");
-    if panel_dir.exists() {
-        fs::remove_dir_all(&panel_dir)
-            .map_err(|e| format!("删除 windsurf-panel 目录失败: {}", e))?;
+    Ok(result)
+}
+
+/// 在 CSP 的 trusted-types 列表中加入 default 策略 (面板脚本依赖)
+fn allow_default_trusted_type(content: &[u8]) -> Vec<u8> {
+    // 跳过 require-trusted-types-for 指令
+    let directive = memchr::memmem::find_iter(content, b"trusted-types").find(|index| {
+        content[..*index]
+            .last()
+            .is_none_or(|c| c.is_ascii_whitespace() || *c == b';' || *c == b'"')
+    });
+    let Some(start) = directive else {
+        return content.to_vec();
+    };
+    let Some(end) = memchr::memchr2(b';', b'"', &content[start..]).map(|offset| start + offset)
+    else {
+        return content.to_vec();
+    };
+
+    if content[start..end]
+        .split(|c| c.is_ascii_whitespace())
+        .any(|token| token == b"default")
+    {
+        return content.to_vec();
     }
 
-    Ok(())
+    let mut result = content.to_vec();
+    result.splice(end..end, b" default ".iter().copied());
+    result
+}
+
+/// 备份 product.json（仅首次）
+fn backup_product_json(file_system: &impl FileSystem, product_json_path: &Path) -> Result<(), String> {
+    copy_to_bak(file_system, product_json_path)
 }
 
 /// 清空 product.json 的 checksums 字段
 /// 
 /// Antigravity 启动时会校验文件的 checksums，修改 workbench-jetski-agent.html 后
 /// 校验和不匹配会导致"安装似乎损坏"提示。清空 checksums 字段可以绕过此校验。
-fn clear_product_checksums(product_json_path: &PathBuf) -> Result<(), String> {
-    if !product_json_path.exists() {
-        // product.json 不存在时跳过，不报错
-        return Ok(());
+/// 按键修改, 其余字段与格式保持不变; 原文件已在备份中, 卸载时按原始字节还原。
+/// 返回需要写回的新内容; 文件不存在或无 checksums 字段时返回 None
+fn cleared_product_json(
+    product_json_path: &Path,
+    original: Option<&[u8]>,
+) -> Result<Option<Vec<u8>>, PatchError> {
+    let Some(original) = original else {
+        return Ok(None);
+    };
+    let edits = [JsonEdit {
+        path: &["checksums"],
+        value: serde_json::json!({}),
+    }];
+    Ok(edited_json(product_json_path, original, &edits)
+        .map_err(PatchError::Io)?
+        .map(|content| {
+            // 重新序列化的整个文件都是新内容, 统一使用原文件的主要换行符
+            bytes::with_line_ending(content.as_bytes(), bytes::dominant_line_ending(original))
+        }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::filesystem::memory::MemoryFs;
+    use crate::embedded::CASCADE_ANCHORS;
+    use crate::commands::backup::list_backup_sets;
+    use serde_json::{json, Value};
+
+    const ROOT: &str = "/fixture/Antigravity";
+    const ORIGINAL_WORKBENCH: &str = "<html>\n<head>\n</head>\n<body>\n</body>\n</html>\n";
+
+    fn install_path() -> PathBuf {
+        PathBuf::from(ROOT)
     }
 
-    // 备份 product.json（仅首次）
-    let backup_path = product_json_path.with_extension("json.bak");
-    if !backup_path.exists() {
-        fs::copy(product_json_path, &backup_path)
-            .map_err(|e| format!("备份 product.json 失败: {}", e))?;
+    fn at(relative_path: &str) -> PathBuf {
+        resolve_relative(&install_path(), relative_path)
     }
 
-    // 读取并解析 JSON (跳过 UTF-8 BOM)
-    let content = fs::read_to_string(product_json_path)
-        .map_err(|e| format!("读取 product.json 失败: {}", e))?;
-    let content = content.trim_start_matches('\u{feff}');
-    
-    let mut json: serde_json::Value = serde_json::from_str(content)
-        .map_err(|e| format!("解析 product.json 失败: {}", e))?;
+    /// 未打补丁的安装: 只有补丁会覆盖的原始文件
+    fn fixture_install() -> MemoryFs {
+        MemoryFs::with_files([
+            (at(CASCADE_PANEL_HTML), "<html>original cascade</html>"),
+            (at(JETSKI_AGENT_HTML), "<html>original agent</html>"),
+            (at(WORKBENCH_HTML), ORIGINAL_WORKBENCH),
+            (at(PRODUCT_JSON), "{\n  \"checksums\": {\n    \"a\": \"b\"\n  }\n}\n"),
+        ])
+    }
 
-    // 清空 checksums 字段
-    if let Some(obj) = json.as_object_mut() {
-        if obj.contains_key("checksums") {
-            obj.insert("checksums".to_string(), serde_json::json!({}));
-            
-            // 写回文件（保持格式化）
-            let formatted = serde_json::to_string_pretty(&json)
-                .map_err(|e| format!("序列化 product.json 失败: {}", e))?;
-            
-            fs::write(product_json_path, formatted)
-                .map_err(|e| format!("写入 product.json 失败: {}", e))?;
+    fn fixture_source(workbench: &str) -> PatchSource {
+        let files = [
+            ("cascade-panel.html", "<html>patched cascade</html>"),
+            ("cascade-panel/cascade-panel.js", "cascade();"),
+            ("shared/shared.js", "shared();"),
+            ("workbench-jetski-agent.html", "<html>patched agent</html>"),
+            ("workbench-antigravity.html", workbench),
+            ("manager-panel/manager-panel.js", "manager();"),
+        ];
+        PatchSource {
+            version: "1".to_string(),
+            files: files
+                .iter()
+                .map(|(path, content)| (path.to_string(), content.to_string()))
+                .collect(),
+            cascade_anchors: CASCADE_ANCHORS,
         }
     }
 
-    Ok(())
+    fn plan_install(file_system: &MemoryFs) -> Result<PatchPlan<&MemoryFs>, PatchError> {
+        plan_antigravity_patch(
+            file_system,
+            &install_path(),
+            &FeatureConfig::default(),
+            &ManagerFeatureConfig::default(),
+            &fixture_source(ORIGINAL_WORKBENCH),
+            None,
+        )
+    }
+
+    fn merged(existing: Value, managed: Value, mode: ConfigMergeMode) -> Value {
+        let content = merge_config_text(Some(&existing.to_string()), &managed.to_string(), mode);
+        serde_json::from_str(&content).unwrap()
+    }
+
+    #[test]
+    fn default_merge_keeps_unknown_third_party_keys() {
+        let existing = json!({ "scrollToBottom": false, "thirdParty": { "theme": "dark" } });
+        let managed = json!({ "scrollToBottom": true });
+
+        let result = merged(existing, managed, ConfigMergeMode::default());
+
+        assert_eq!(result["scrollToBottom"], true);
+        assert_eq!(result["thirdParty"], json!({ "theme": "dark" }));
+    }
+
+    #[test]
+    fn configs_written_with_a_bom_are_still_read() {
+        let existing = "\u{feff}{ \"thirdParty\": 1, \"fileHashes\": { \"a.js\": \"1\" } }";
+
+        let managed = "{ \"scrollToBottom\": true }";
+        let content = merge_config_text(Some(existing), managed, ConfigMergeMode::DeepMerge);
+        let result: Value = serde_json::from_str(&content).unwrap();
+        assert_eq!(result["thirdParty"], 1);
+        assert_eq!(result["scrollToBottom"], true);
+
+        let file_system = MemoryFs::with_files([(at(CASCADE_CONFIG_JSON), existing)]);
+        let meta = stored_patch_meta(&file_system, &at(CASCADE_CONFIG_JSON));
+        assert_eq!(meta.file_hashes.get("a.js").map(String::as_str), Some("1"));
+    }
+
+    fn nested_existing() -> Value {
+        json!({
+            "promptEnhance": { "model": "old", "temperature": 0.2 },
+            "fileHashes": { "stale.js": "1" },
+            "thirdParty": true
+        })
+    }
+
+    fn nested_managed() -> Value {
+        json!({ "promptEnhance": { "model": "new" }, "fileHashes": { "a.js": "2" } })
+    }
+
+    #[test]
+    fn replace_drops_every_unmanaged_key() {
+        let result = merged(nested_existing(), nested_managed(), ConfigMergeMode::Replace);
+
+        assert_eq!(result, nested_managed());
+    }
+
+    #[test]
+    fn deep_merge_keeps_nested_sibling_keys() {
+        let result = merged(nested_existing(), nested_managed(), ConfigMergeMode::DeepMerge);
+
+        assert_eq!(result["promptEnhance"], json!({ "model": "new", "temperature": 0.2 }));
+        assert_eq!(result["thirdParty"], true);
+        // fileHashes 整体替换, 不残留已删除的文件
+        assert_eq!(result["fileHashes"], json!({ "a.js": "2" }));
+    }
+
+    #[test]
+    fn managed_keys_only_replaces_nested_objects_whole() {
+        let result = merged(nested_existing(), nested_managed(), ConfigMergeMode::ManagedKeysOnly);
+
+        assert_eq!(result["promptEnhance"], json!({ "model": "new" }));
+        assert_eq!(result["thirdParty"], true);
+        assert_eq!(result["fileHashes"], json!({ "a.js": "2" }));
+    }
+
+    #[test]
+    fn panel_injection_uses_crlf_only_for_inserted_tags() {
+        let original: &[u8] =
+            b"<html>\r\n<head>\r\n<script>a\nb</script>\r\n</head>\r\n<body></body>\r\n</html>\r\n";
+
+        let result = inject_panel_into_html(original).unwrap();
+
+        // 原文件中单独的 LF 保持不变
+        assert!(bytes::contains(&result, b"<script>a\nb</script>\r\n"));
+        assert!(bytes::contains(&result, format!("{}\r\n", PANEL_CSS_TAG).as_bytes()));
+        assert!(bytes::contains(&result, format!("{}\r\n", PANEL_JS_TAG).as_bytes()));
+        let lone_lf = result
+            .iter()
+            .enumerate()
+            .filter(|(index, byte)| **byte == b'\n' && (*index == 0 || result[index - 1] != b'\r'))
+            .count();
+        assert_eq!(lone_lf, 1);
+    }
+
+    #[test]
+    fn reinstalling_the_same_patch_is_noop() {
+        let file_system = fixture_install();
+        let plan = plan_install(&file_system).unwrap();
+        assert!(!plan.is_noop());
+        plan.apply().unwrap();
+
+        let product: Value = serde_json::from_slice(&file_system.file(at(PRODUCT_JSON)).unwrap()).unwrap();
+        assert_eq!(product["checksums"], json!({}));
+        assert!(plan_install(&file_system).unwrap().is_noop());
+    }
+
+    #[test]
+    fn reinstall_skips_files_that_hold_the_current_patch() {
+        let file_system = fixture_install();
+        plan_install(&file_system).unwrap().apply().unwrap();
+
+        let mut plan = plan_install(&file_system).unwrap();
+        let mut skipped = skip_current_files(&mut plan, &install_path());
+        skipped.sort();
+
+        // extensions 目录下的 shared 不会被整体清理
+        let shared = "resources/app/extensions/antigravity/shared/shared.js";
+        let mut expected =
+            vec![CASCADE_PANEL_HTML, JETSKI_AGENT_HTML, WORKBENCH_HTML, PRODUCT_JSON, shared];
+        expected.sort();
+        assert_eq!(skipped, expected);
+        assert!(plan.files.iter().all(|file| !file.path.ends_with("workbench.html")));
+        // 整体清理重写的补丁目录不跳过
+        assert!(plan.files.iter().any(|file| file.path.ends_with("cascade-panel.js")));
+    }
+
+    #[test]
+    fn failed_write_rolls_back_every_file() {
+        let file_system = fixture_install();
+        let before: Vec<(PathBuf, Vec<u8>)> = file_system
+            .paths()
+            .into_iter()
+            .map(|path| {
+                let content = file_system.file(&path).unwrap();
+                (path, content)
+            })
+            .collect();
+        // 配置文件最后写入, 此时其余补丁文件都已写入
+        file_system.fail_writes_to(at(MANAGER_CONFIG_JSON));
+
+        let result = plan_install(&file_system).unwrap().apply();
+
+        assert!(matches!(result, Err(ApplyError::RolledBack { ref rollback_errors, .. }) if rollback_errors.is_empty()));
+        let after: Vec<(PathBuf, Vec<u8>)> = file_system
+            .paths()
+            .into_iter()
+            .map(|path| {
+                let content = file_system.file(&path).unwrap();
+                (path, content)
+            })
+            .collect();
+        assert_eq!(after, before);
+    }
+
+    #[test]
+    fn missing_anchor_fails_before_planning_writes() {
+        let file_system = fixture_install();
+        let plan = plan_antigravity_patch(
+            &file_system,
+            &install_path(),
+            &FeatureConfig::default(),
+            &ManagerFeatureConfig::default(),
+            &fixture_source("<html>\n<head>\n</head>\n</html>\n"),
+            None,
+        );
+
+        assert!(matches!(plan, Err(PatchError::AnchorNotFound { ref anchor }) if anchor == "</body>"));
+    }
+
+    #[test]
+    fn cascade_injection_is_idempotent() {
+        let once = inject_cascade_into_html(ORIGINAL_WORKBENCH.as_bytes(), &CASCADE_ANCHORS).unwrap();
+        let twice = inject_cascade_into_html(&once, &CASCADE_ANCHORS).unwrap();
+
+        assert_eq!(once, twice);
+        let bypass = bytes::find(&once, CASCADE_INJECT_MARKER.as_bytes()).unwrap();
+        assert!(bytes::find(&once, b"<head>").unwrap() < bypass);
+        let css = bytes::find(&once, b"cascade-panel.css").unwrap();
+        assert!(css < bytes::find(&once, b"</head>").unwrap());
+        let js = bytes::find(&once, b"cascade-panel.js").unwrap();
+        assert!(js < bytes::find(&once, b"</body>").unwrap());
+    }
+
+    #[test]
+    fn skipped_files_are_not_backed_up() {
+        let file_system = fixture_install();
+        plan_install(&file_system).unwrap().apply().unwrap();
+        let mut plan = plan_install(&file_system).unwrap();
+        let skipped = skip_current_files(&mut plan, &install_path());
+
+        let features = FeatureConfig::default();
+        let manager_features = ManagerFeatureConfig::default();
+        backup_originals(&file_system, &install_path(), &features, &manager_features, &skipped)
+            .unwrap();
+
+        // 已是补丁内容的文件既不进入备份目录, 也不留 .bak 副本
+        let backups = backup_dir(&install_path());
+        for relative_path in &skipped {
+            assert_eq!(file_system.file(resolve_relative(&backups, relative_path)), None);
+        }
+        assert!(file_system.paths().iter().all(|path| path.extension() != Some("bak".as_ref())));
+    }
+
+    #[test]
+    fn backups_keep_the_first_original_and_restore_it() {
+        let file_system = fixture_install();
+        let features = FeatureConfig::default();
+        let manager_features = ManagerFeatureConfig::default();
+        backup_originals(&file_system, &install_path(), &features, &manager_features, &[]).unwrap();
+        plan_install(&file_system).unwrap().apply().unwrap();
+        // 再次安装前的备份不能用已打补丁的文件覆盖原版
+        backup_originals(&file_system, &install_path(), &features, &manager_features, &[]).unwrap();
+        // 已打补丁的文件也不另存为备份集, 原版所在的那一组不会被挤出保留数量
+        assert_eq!(list_backup_sets(&file_system, Editor::Antigravity).len(), 1);
+
+        let workbench_backup = resolve_relative(&backup_dir(&install_path()), WORKBENCH_HTML);
+        assert_eq!(file_system.file(&workbench_backup).unwrap(), ORIGINAL_WORKBENCH.as_bytes());
+        let legacy_backup = PathBuf::from(format!("{}.bak", at(WORKBENCH_HTML).to_string_lossy()));
+        assert_eq!(file_system.file(&legacy_backup).unwrap(), ORIGINAL_WORKBENCH.as_bytes());
+
+        let mut plan = PatchPlan::with_file_system(&file_system);
+        let restored = plan_manager_restore(&mut plan, &install_path(), true).unwrap();
+        assert!(restored.iter().all(|file| file.strategy == RestoreStrategy::Backup));
+        plan.apply().unwrap();
+        assert_eq!(file_system.file(at(WORKBENCH_HTML)).unwrap(), ORIGINAL_WORKBENCH.as_bytes());
+        assert_eq!(file_system.file(at(JETSKI_AGENT_HTML)).unwrap(), b"<html>original agent</html>");
+        assert!(!file_system.is_dir(&workbench_dir(&install_path()).join("manager-panel")));
+    }
+
+    #[test]
+    fn uninstall_restores_the_originals_byte_for_byte() {
+        let file_system = fixture_install();
+        let originals: Vec<(PathBuf, Vec<u8>)> = file_system
+            .paths()
+            .into_iter()
+            .map(|path| {
+                let content = file_system.file(&path).unwrap();
+                (path, content)
+            })
+            .collect();
+        let features = FeatureConfig::default();
+        let manager_features = ManagerFeatureConfig::default();
+        backup_originals(&file_system, &install_path(), &features, &manager_features, &[]).unwrap();
+        plan_install(&file_system).unwrap().apply().unwrap();
+
+        let (plan, report) = plan_uninstall(&file_system, &install_path(), true).unwrap();
+        plan.apply().unwrap();
+        ensure_no_residual_markers(&file_system, &report).unwrap();
+
+        for (path, content) in &originals {
+            assert_eq!(file_system.file(path).as_ref(), Some(content), "{:?}", path);
+        }
+        let patched: Vec<PathBuf> = file_system
+            .paths()
+            .into_iter()
+            .filter(|path| path.starts_with(ROOT))
+            .filter(|path| is_patched_content(&file_system.file(path).unwrap()))
+            .collect();
+        assert!(patched.is_empty(), "{:?}", patched);
+    }
 }
@@ -0,0 +1,78 @@
+// 外部补丁包 (仅 dev feature)
+// 开发补丁时从 JSON 文件读取补丁内容, 不必为每次修改重新编译补丁器
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path};
+
+use crate::embedded::CASCADE_ANCHORS;
+use super::json_edit::strip_bom;
+use super::patch::PatchSource;
+
+/// Antigravity 补丁包必须包含的文件
+const REQUIRED_FILES: &[&str] = &[
+    "cascade-panel.html",
+    "workbench-jetski-agent.html",
+    "workbench-antigravity.html",
+];
+
+/// 补丁包文件格式
+/// { "payloadVersion": "dev", "files": { "cascade-panel.html": "...", "shared/xxx.js": "..." } }
+#[derive(Debug, Deserialize)]
+pub struct PatchPayload {
+    #[serde(rename = "payloadVersion")]
+    pub payload_version: String,
+    /// 相对 patches 目录的路径 (使用 /) -> 文件内容
+    pub files: BTreeMap<String, String>,
+}
+
+impl PatchPayload {
+    /// 读取并校验补丁包
+    pub fn load(payload_path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(payload_path)
+            .map_err(|e| format!("读取补丁包失败 {:?}: {}", payload_path, e))?;
+        let payload: PatchPayload = serde_json::from_str(strip_bom(&content))
+            .map_err(|e| format!("解析补丁包失败: {}", e))?;
+        payload.validate()?;
+        Ok(payload)
+    }
+
+    /// 转换为补丁来源, 与内置补丁走同一安装流程
+    pub fn into_source(self) -> PatchSource {
+        PatchSource {
+            version: self.payload_version,
+            files: self.files.into_iter().collect(),
+            cascade_anchors: CASCADE_ANCHORS,
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.payload_version.trim().is_empty() {
+            return Err("补丁包缺少 payloadVersion".to_string());
+        }
+
+        let missing: Vec<&str> = REQUIRED_FILES
+            .iter()
+            .copied()
+            .filter(|file| !self.files.contains_key(*file))
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!("补丁包缺少文件: {}", missing.join(", ")));
+        }
+
+        // 只允许写入安装目录内的相对路径
+        for relative_path in self.files.keys() {
+            let is_safe = !relative_path.is_empty()
+                && !relative_path.contains('\\')
+                && Path::new(relative_path)
+                    .components()
+                    .all(|part| matches!(part, Component::Normal(_)));
+            if !is_safe {
+                return Err(format!("补丁包包含非法路径: {}", relative_path));
+            }
+        }
+
+        Ok(())
+    }
+}
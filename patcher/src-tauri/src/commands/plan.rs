@@ -0,0 +1,490 @@
+// 补丁执行计划
+// 先计算出所有要删除的目录和要写入的文件内容, 再统一落盘; 预览 (dry run) 只生成报告不写入
+// 读写经由 FileSystem 进行, 默认为真实磁盘
+
+use serde::Serialize;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use super::audit::{changed_blocks, ChangedBlock, FileChanges};
+use super::cancel::is_cancelled;
+use super::error::PatchError;
+use super::filesystem::{DiskFs, FileSystem};
+use super::marker::is_patched_content;
+
+/// 文件被短暂占用时各次重试前的等待, 加上首次尝试共 3 次
+const LOCK_RETRY_DELAYS: [Duration; 2] = [Duration::from_millis(100), Duration::from_millis(300)];
+
+/// 计划中的单个文件写入
+pub struct PlannedFile {
+    pub path: PathBuf,
+    pub content: Vec<u8>,
+}
+
+/// 一次补丁操作的全部磁盘改动: 先删除目录, 再按顺序写入文件, 最后删除多余文件
+pub struct PatchPlan<F: FileSystem = DiskFs> {
+    pub remove_dirs: Vec<PathBuf>,
+    pub files: Vec<PlannedFile>,
+    pub remove_files: Vec<PathBuf>,
+    file_system: F,
+}
+
+impl Default for PatchPlan {
+    fn default() -> Self {
+        Self::with_file_system(DiskFs)
+    }
+}
+
+impl<F: FileSystem> PatchPlan<F> {
+    /// 在指定文件系统上执行的空计划
+    pub fn with_file_system(file_system: F) -> Self {
+        Self {
+            remove_dirs: Vec::new(),
+            files: Vec::new(),
+            remove_files: Vec::new(),
+            file_system,
+        }
+    }
+
+    /// 计划读写的文件系统
+    pub fn file_system(&self) -> &F {
+        &self.file_system
+    }
+
+    /// 计划写入文件, 同一路径多次写入时以最后一次为准
+    pub fn write(&mut self, path: PathBuf, content: impl Into<Vec<u8>>) {
+        self.files.retain(|file| file.path != path);
+        self.files.push(PlannedFile {
+            path,
+            content: content.into(),
+        });
+    }
+
+    /// 计划在写入前删除整个目录
+    pub fn remove_dir(&mut self, dir: PathBuf) {
+        if !self.remove_dirs.contains(&dir) {
+            self.remove_dirs.push(dir);
+        }
+    }
+
+    /// 计划在写入完成后删除单个文件 (如已用过的旧备份)
+    pub fn remove_file(&mut self, path: PathBuf) {
+        if !self.remove_files.contains(&path) {
+            self.remove_files.push(path);
+        }
+    }
+
+    /// 只保留路径满足条件的改动 (写入, 删除的目录与文件)
+    pub fn retain(&mut self, keep: impl Fn(&Path) -> bool) {
+        self.files.retain(|file| keep(&file.path));
+        self.remove_dirs.retain(|dir| keep(dir));
+        self.remove_files.retain(|path| keep(path));
+    }
+
+    /// 计划执行后文件的内容: 优先取计划中的写入, 否则读取磁盘现有内容
+    pub fn content_of(&self, path: &Path) -> Result<Option<Vec<u8>>, PatchError> {
+        if let Some(file) = self.files.iter().find(|file| file.path == path) {
+            return Ok(Some(file.content.clone()));
+        }
+        let removed = self.remove_dirs.iter().any(|dir| path.starts_with(dir));
+        if removed || !self.file_system.exists(path) {
+            return Ok(None);
+        }
+        self.file_system
+            .read(path)
+            .map(Some)
+            .map_err(|e| PatchError::io(path, "读取文件失败", e))
+    }
+
+    /// 执行计划
+    ///
+    /// 先确认所有目标文件都可写, 再依次落盘; 中途任何一步失败都会撤销已做的改动,
+    /// 让安装目录回到执行前的状态。
+    pub fn apply(&self) -> Result<(), ApplyError> {
+        self.apply_with_progress(|_, _, _| {})
+    }
+
+    /// 执行计划, 每写入一个文件前回调 (序号从 1 开始, 总数, 路径)
+    /// 每个文件写入前检查取消请求, 已取消时撤销已做的改动并返回 Cancelled
+    pub fn apply_with_progress(
+        &self,
+        mut on_write: impl FnMut(usize, usize, &Path),
+    ) -> Result<(), ApplyError> {
+        self.check_writable().map_err(ApplyError::BeforeWrite)?;
+
+        let mut journal = Journal::new(&self.file_system);
+        match self.apply_with(&mut journal, &mut on_write) {
+            Ok(()) => {
+                journal.commit();
+                Ok(())
+            }
+            Err(Interrupted::Failed(error)) => Err(ApplyError::RolledBack {
+                error,
+                rollback_errors: journal.rollback(),
+            }),
+            Err(Interrupted::Cancelled) => Err(ApplyError::Cancelled {
+                rollback_errors: journal.rollback(),
+            }),
+        }
+    }
+
+    /// 写入前检查: 已存在的目标文件必须能以写方式打开 (未被占用, 非只读)
+    fn check_writable(&self) -> Result<(), PatchError> {
+        let targets = self.files.iter().map(|file| &file.path).chain(&self.remove_files);
+        for path in targets {
+            let removed = self.remove_dirs.iter().any(|dir| path.starts_with(dir));
+            if removed || !self.file_system.exists(path) {
+                continue;
+            }
+            self.file_system
+                .check_writable(path)
+                .map_err(|e| PatchError::io(path, "文件不可写", e))?;
+        }
+        Ok(())
+    }
+
+    fn apply_with(
+        &self,
+        journal: &mut Journal<F>,
+        on_write: &mut impl FnMut(usize, usize, &Path),
+    ) -> Result<(), Interrupted> {
+        // 旧目录先移到一旁, 成功后再删除, 失败时可以原样移回
+        let file_system = &self.file_system;
+        for dir in &self.remove_dirs {
+            if !file_system.exists(dir) {
+                continue;
+            }
+            let aside = PathBuf::from(format!("{}{}", dir.to_string_lossy(), ASIDE_SUFFIX));
+            if file_system.exists(&aside) {
+                file_system
+                    .remove_dir_all(&aside)
+                    .map_err(|e| format!("删除目录失败 {:?}: {}", aside, e))?;
+            }
+            file_system
+                .rename(dir, &aside)
+                .map_err(|e| format!("删除目录失败 {:?}: {}", dir, e))?;
+            journal.moved_dirs.push((dir.clone(), aside));
+        }
+
+        for (index, file) in self.files.iter().enumerate() {
+            if is_cancelled() {
+                return Err(Interrupted::Cancelled);
+            }
+            on_write(index + 1, self.files.len(), &file.path);
+            if let Some(parent) = file.path.parent() {
+                journal.create_dir_all(parent)?;
+            }
+            journal.snapshot(&file.path)?;
+            write_with(file_system, &file.path, &file.content)?;
+        }
+
+        for path in &self.remove_files {
+            if file_system.exists(path) {
+                journal.snapshot(path)?;
+                file_system
+                    .remove_file(path)
+                    .map_err(|e| format!("删除文件失败 {:?}: {}", path, e))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 执行计划不会带来任何变化: 所有文件内容与磁盘一致, 待删除目录中也没有计划外的文件
+    pub fn is_noop(&self) -> bool {
+        let files_unchanged = self
+            .files
+            .iter()
+            .all(|file| {
+                self.file_system
+                    .read(&file.path)
+                    .is_ok_and(|current| current == file.content)
+            });
+        let dirs_clean = self.remove_dirs.iter().all(|dir| {
+            let mut existing = Vec::new();
+            collect_files(&self.file_system, dir, &mut existing);
+            existing
+                .iter()
+                .all(|path| self.files.iter().any(|file| file.path == *path))
+        });
+        files_unchanged
+            && dirs_clean
+            && self
+                .remove_files
+                .iter()
+                .all(|path| !self.file_system.exists(path))
+    }
+
+    /// 各文件相对磁盘现有内容的逐字节改动, 需在执行计划前调用
+    pub fn change_report(&self) -> Vec<FileChanges> {
+        self.files
+            .iter()
+            .map(|file| {
+                let current = self.file_system.read(&file.path).ok();
+                FileChanges::new(
+                    file.path.to_string_lossy().to_string(),
+                    current.as_deref(),
+                    &file.content,
+                )
+            })
+            .collect()
+    }
+
+    /// 生成预览报告, 不修改磁盘
+    pub fn dry_run_report(&self) -> DryRunReport {
+        let files = self
+            .files
+            .iter()
+            .map(|file| {
+                let current = self.file_system.read(&file.path).ok();
+                let already_patched = current.as_ref().is_some_and(|current| {
+                    *current == file.content
+                        || is_patched_content(current)
+                });
+                DryRunFile {
+                    path: file.path.to_string_lossy().to_string(),
+                    exists: current.is_some(),
+                    size_before: current.as_ref().map(|c| c.len() as u64).unwrap_or(0),
+                    size_after: file.content.len() as u64,
+                    already_patched,
+                    blocks: changed_blocks(current.as_deref().unwrap_or_default(), &file.content),
+                }
+            })
+            .collect();
+
+        DryRunReport {
+            files,
+            removed_dirs: self
+                .remove_dirs
+                .iter()
+                .filter(|dir| self.file_system.exists(dir))
+                .map(|dir| dir.to_string_lossy().to_string())
+                .collect(),
+            skipped: Vec::new(),
+        }
+    }
+}
+
+/// 被移走待删除的旧目录后缀
+const ASIDE_SUFFIX: &str = ".apply-old";
+
+/// 执行中途停止的原因
+enum Interrupted {
+    Failed(String),
+    Cancelled,
+}
+
+impl From<String> for Interrupted {
+    fn from(error: String) -> Self {
+        Interrupted::Failed(error)
+    }
+}
+
+/// 执行计划失败
+#[derive(Debug)]
+pub enum ApplyError {
+    /// 写入前的检查未通过, 磁盘未被修改
+    BeforeWrite(PatchError),
+    /// 写入中途失败, 已撤销之前的改动; rollback_errors 为撤销时遇到的错误
+    RolledBack {
+        error: String,
+        rollback_errors: Vec<String>,
+    },
+    /// 写入中途被取消, 已撤销之前的改动
+    Cancelled { rollback_errors: Vec<String> },
+}
+
+/// 执行过程中的改动记录, 用于失败时回滚
+struct Journal<'a, F: FileSystem> {
+    file_system: &'a F,
+    /// 被移到一旁的目录: (原路径, 临时路径)
+    moved_dirs: Vec<(PathBuf, PathBuf)>,
+    /// 被改写或删除的文件及其原内容 (None 表示原先不存在)
+    touched_files: Vec<(PathBuf, Option<Vec<u8>>)>,
+    /// 执行过程中新建的目录
+    created_dirs: Vec<PathBuf>,
+}
+
+impl<'a, F: FileSystem> Journal<'a, F> {
+    fn new(file_system: &'a F) -> Self {
+        Self {
+            file_system,
+            moved_dirs: Vec::new(),
+            touched_files: Vec::new(),
+            created_dirs: Vec::new(),
+        }
+    }
+
+    /// 记录文件改动前的内容 (同一文件只记录第一次)
+    fn snapshot(&mut self, path: &Path) -> Result<(), String> {
+        if self.touched_files.iter().any(|(touched, _)| touched == path) {
+            return Ok(());
+        }
+        let original = if self.file_system.exists(path) {
+            Some(self.file_system.read(path).map_err(|e| format!("读取文件失败 {:?}: {}", path, e))?)
+        } else {
+            None
+        };
+        self.touched_files.push((path.to_path_buf(), original));
+        Ok(())
+    }
+
+    /// 创建目录并记录新建的每一级
+    fn create_dir_all(&mut self, dir: &Path) -> Result<(), String> {
+        let missing: Vec<PathBuf> = dir
+            .ancestors()
+            .take_while(|ancestor| !self.file_system.exists(ancestor))
+            .map(Path::to_path_buf)
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        self.file_system
+            .create_dir_all(dir)
+            .map_err(|e| format!("创建目录失败 {:?}: {}", dir, e))?;
+        // 由外到内记录, 回滚时倒序删除
+        self.created_dirs.extend(missing.into_iter().rev());
+        Ok(())
+    }
+
+    /// 执行成功: 删除移到一旁的旧目录 (失败不影响结果)
+    fn commit(self) {
+        for (_, aside) in self.moved_dirs {
+            let _ = self.file_system.remove_dir_all(&aside);
+        }
+    }
+
+    /// 撤销所有改动, 返回撤销过程中遇到的错误
+    fn rollback(self) -> Vec<String> {
+        let file_system = self.file_system;
+        let mut errors = Vec::new();
+
+        for (path, original) in self.touched_files.into_iter().rev() {
+            let result = match original {
+                Some(content) => write_with(file_system, &path, &content),
+                None if file_system.exists(&path) => file_system
+                    .remove_file(&path)
+                    .map_err(|e| format!("删除文件失败 {:?}: {}", path, e)),
+                None => Ok(()),
+            };
+            if let Err(error) = result {
+                errors.push(error);
+            }
+        }
+
+        for dir in self.created_dirs.into_iter().rev() {
+            let _ = file_system.remove_dir(&dir);
+        }
+
+        for (dir, aside) in self.moved_dirs.into_iter().rev() {
+            if file_system.exists(&dir) {
+                if let Err(e) = file_system.remove_dir_all(&dir) {
+                    errors.push(format!("删除目录失败 {:?}: {}", dir, e));
+                    continue;
+                }
+            }
+            if let Err(e) = file_system.rename(&aside, &dir) {
+                errors.push(format!("恢复目录失败 {:?}: {}", dir, e));
+            }
+        }
+
+        errors
+    }
+}
+
+/// 递归收集目录下的所有文件
+fn collect_files(file_system: &impl FileSystem, dir: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = file_system.read_dir(dir) else {
+        return;
+    };
+    for path in entries {
+        if file_system.is_dir(&path) {
+            collect_files(file_system, &path, out);
+        } else {
+            out.push(path);
+        }
+    }
+}
+
+/// 检查目录可写: 在目录中创建再删除一个探测文件
+/// 只读属性检查不可靠, Windows 上未提权时 Program Files 下创建文件才会返回 ERROR_ACCESS_DENIED
+pub fn check_dir_writable(dir: &Path) -> Result<(), PatchError> {
+    let probe = dir.join(".antigravity-power-pro-write-test");
+    fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&probe)
+        .map_err(|e| PatchError::io(dir, "目录不可写", e))?;
+    let _ = fs::remove_file(&probe);
+    Ok(())
+}
+
+/// 先写临时文件并落盘再替换, 避免进程被杀或断电时目标文件只写了一半
+pub fn write_replacing(path: &Path, content: &[u8]) -> io::Result<()> {
+    DiskFs.write(path, content)
+}
+
+/// 在指定文件系统上整体替换文件内容
+fn write_with(file_system: &impl FileSystem, path: &Path, content: &[u8]) -> Result<(), String> {
+    file_system
+        .write(path, content)
+        .map_err(|e| format!("写入文件失败 {:?}: {}", path, e))
+}
+
+/// 执行文件操作, 遇到共享冲突时稍后重试, 重试用尽后返回最后一次的错误
+///
+/// 即使编辑器已关闭, Windows 上的杀毒软件和搜索索引器也会短暂占用刚写入的文件;
+/// 真正的权限不足 (拒绝访问) 不会重试
+pub fn retry_locked<T>(mut operation: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+    for delay in LOCK_RETRY_DELAYS {
+        match operation() {
+            Err(e) if is_sharing_violation(&e) => thread::sleep(delay),
+            result => return result,
+        }
+    }
+    operation()
+}
+
+/// ERROR_SHARING_VIOLATION (32) 与 ERROR_LOCK_VIOLATION (33): 文件正被其他进程打开或锁定
+#[cfg(target_os = "windows")]
+fn is_sharing_violation(error: &io::Error) -> bool {
+    matches!(error.raw_os_error(), Some(32) | Some(33))
+}
+
+/// 其他平台上打开中的文件不妨碍写入和替换
+#[cfg(not(target_os = "windows"))]
+fn is_sharing_violation(_error: &io::Error) -> bool {
+    false
+}
+
+/// 预览: 单个文件的变化
+#[derive(Debug, Serialize)]
+pub struct DryRunFile {
+    pub path: String,
+    /// 文件当前是否存在
+    pub exists: bool,
+    #[serde(rename = "sizeBefore")]
+    pub size_before: u64,
+    #[serde(rename = "sizeAfter")]
+    pub size_after: u64,
+    /// 当前内容已是补丁内容 (或已包含补丁标记)
+    #[serde(rename = "alreadyPatched")]
+    pub already_patched: bool,
+    /// 写入后相对当前内容的各处改动
+    pub blocks: Vec<ChangedBlock>,
+}
+
+/// 预览报告
+#[derive(Debug, Serialize)]
+pub struct DryRunReport {
+    pub files: Vec<DryRunFile>,
+    /// 写入前会被整体删除的旧补丁目录
+    #[serde(rename = "removedDirs")]
+    pub removed_dirs: Vec<String>,
+    /// 已是预期补丁内容而不会重写的文件 (相对安装目录), 由调用方在跳过后填入
+    pub skipped: Vec<String>,
+}
@@ -0,0 +1,84 @@
+// 启动自检
+// 应用启动时一次性检查各编辑器的安装, 运行状态, 目录权限与配置文件, 在首页直接提示问题
+
+use serde::Serialize;
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+
+use super::config::check_config_readable;
+use super::detect::{detect_in_background, is_read_only_install, DetectionCache};
+use super::editor::Editor;
+use super::network::is_network_path;
+use super::patch::is_path_writable;
+use super::process::is_editor_running;
+
+/// 单个检测到的安装
+#[derive(Debug, Serialize)]
+pub struct InstallPreflight {
+    pub path: String,
+    /// 补丁会写入的目录是否都可写
+    pub writable: bool,
+    /// 位于只读文件系统 (如 Snap), 无法打补丁
+    #[serde(rename = "readOnly")]
+    pub read_only: bool,
+    /// 位于网络路径 (UNC 或映射驱动器) 上, 操作可能较慢
+    #[serde(rename = "slowPath")]
+    pub slow_path: bool,
+}
+
+/// 单个编辑器的检查结果
+#[derive(Debug, Serialize)]
+pub struct EditorPreflight {
+    pub editor: Editor,
+    pub installs: Vec<InstallPreflight>,
+    /// 编辑器正在运行, 需要先关闭才能打补丁
+    pub running: bool,
+}
+
+/// 启动自检报告
+#[derive(Debug, Serialize)]
+pub struct PreflightReport {
+    pub editors: Vec<EditorPreflight>,
+    /// 是否检测到任一编辑器的安装
+    #[serde(rename = "anyInstallFound")]
+    pub any_install_found: bool,
+    #[serde(rename = "configReadable")]
+    pub config_readable: bool,
+    /// 配置文件读取或解析失败的原因
+    #[serde(rename = "configError")]
+    pub config_error: Option<String>,
+}
+
+/// 启动自检: 检测所有编辑器并检查运行状态, 目录权限与配置文件
+#[tauri::command]
+pub async fn preflight_check(app: AppHandle) -> PreflightReport {
+    let cache = app.state::<DetectionCache>();
+    let mut editors = Vec::new();
+    for editor in Editor::ALL {
+        let installs = detect_in_background(&cache, editor)
+            .await
+            .into_iter()
+            .map(|path| InstallPreflight {
+                writable: is_path_writable(path.clone()),
+                read_only: is_read_only_install(Path::new(&path)),
+                slow_path: is_network_path(Path::new(&path)),
+                path,
+            })
+            .collect();
+        editors.push(EditorPreflight {
+            editor,
+            installs,
+            running: is_editor_running(editor),
+        });
+    }
+
+    let config_error = check_config_readable().err();
+    let report = PreflightReport {
+        any_install_found: editors.iter().any(|editor| !editor.installs.is_empty()),
+        editors,
+        config_readable: config_error.is_none(),
+        config_error,
+    };
+    log::info!("启动自检: {:?}", report);
+    report
+}
@@ -0,0 +1,84 @@
+// 编辑器进程检测
+// 编辑器运行时补丁文件可能被占用 (Windows) 或在退出时被覆盖, 安装/卸载前需要确认它已关闭
+
+use super::editor::Editor;
+
+/// 检测编辑器是否正在运行 (按可执行文件名匹配进程)
+pub fn is_editor_running(editor: Editor) -> bool {
+    let process_name = editor.process_name();
+
+    #[cfg(target_os = "windows")]
+    {
+        is_running_windows(&format!("{}.exe", process_name))
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        is_running_macos(process_name)
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        is_running_linux(process_name)
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    {
+        let _ = process_name;
+        false
+    }
+}
+
+/// 通过 tasklist 查询镜像名
+#[cfg(target_os = "windows")]
+fn is_running_windows(image_name: &str) -> bool {
+    use std::os::windows::process::CommandExt;
+    use std::process::Command;
+
+    // 不弹出控制台窗口
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    let output = Command::new("tasklist")
+        .args(["/FI", &format!("IMAGENAME eq {}", image_name), "/NH", "/FO", "CSV"])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output();
+
+    match output {
+        Ok(output) => String::from_utf8_lossy(&output.stdout)
+            .to_lowercase()
+            .contains(&format!("\"{}\"", image_name.to_lowercase())),
+        Err(_) => false,
+    }
+}
+
+/// 通过 pgrep 精确匹配进程名
+#[cfg(target_os = "macos")]
+fn is_running_macos(process_name: &str) -> bool {
+    use std::process::{Command, Stdio};
+
+    Command::new("pgrep")
+        .args(["-x", process_name])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+/// 遍历 /proc/<pid>/comm 匹配进程名
+#[cfg(target_os = "linux")]
+fn is_running_linux(process_name: &str) -> bool {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return false;
+    };
+
+    entries.flatten().any(|entry| {
+        let is_pid = entry
+            .file_name()
+            .to_str()
+            .is_some_and(|name| name.chars().all(|c| c.is_ascii_digit()));
+        is_pid
+            && std::fs::read_to_string(entry.path().join("comm"))
+                .is_ok_and(|comm| comm.trim().eq_ignore_ascii_case(process_name))
+    })
+}
@@ -0,0 +1,41 @@
+// 补丁安装进度
+// 通过 Tauri Channel 把安装各阶段推送给前端, 用于显示进度条
+
+use serde::Serialize;
+use std::path::Path;
+use tauri::ipc::Channel;
+
+/// 写入阶段占用的进度区间, 之前为检测与备份, 之后为校验
+const WRITE_START: usize = 15;
+const WRITE_END: usize = 95;
+
+/// 单条进度事件
+#[derive(Debug, Clone, Serialize)]
+pub struct PatchProgress {
+    /// 0 - 100
+    pub percent: u8,
+    /// 当前步骤说明
+    pub step: String,
+}
+
+/// 发送进度, 前端已不再监听时直接忽略
+pub fn report(channel: &Channel<PatchProgress>, percent: u8, step: impl Into<String>) {
+    let _ = channel.send(PatchProgress {
+        percent: percent.min(100),
+        step: step.into(),
+    });
+}
+
+/// 发送 "写入第 N / M 个文件" 进度
+pub fn report_write(channel: &Channel<PatchProgress>, index: usize, total: usize, path: &Path) {
+    let percent = WRITE_START + (WRITE_END - WRITE_START) * index / total.max(1);
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    report(
+        channel,
+        percent as u8,
+        format!("写入文件 {}/{}: {}", index, total, name),
+    );
+}
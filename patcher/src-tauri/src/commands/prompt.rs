@@ -1,6 +1,12 @@
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::time::Duration;
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+use super::error::{EndpointFailure, PatchError};
+
+/// 接口地址连通性检查的超时
+const ENDPOINT_TIMEOUT: Duration = Duration::from_secs(8);
 
 #[derive(Debug, Deserialize)]
 #[serde(default)]
@@ -92,6 +98,106 @@ pub async fn test_prompt_connection(
     )))
 }
 
+/// 规范化用户填写的接口地址: 去掉首尾空白, 缺少协议时补 https://, 去掉末尾多余的 /
+/// 留空表示未配置, 原样返回空串; 无法解析或不是 http(s) 的地址返回错误原因
+pub fn normalize_api_base(raw: &str) -> Result<String, String> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return Ok(String::new());
+    }
+
+    let with_scheme = if trimmed.contains("://") {
+        trimmed.to_string()
+    } else {
+        format!("https://{}", trimmed)
+    };
+    let url = reqwest::Url::parse(&with_scheme).map_err(|e| format!("地址格式错误: {}", e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("不支持的协议: {}", url.scheme()));
+    }
+    if url.host_str().is_none_or(str::is_empty) {
+        return Err("地址缺少主机名".to_string());
+    }
+
+    // 不用 Url 的序列化结果: 它会给空路径补上 /, 拼接 /chat/completions 时出现 //
+    Ok(with_scheme.trim_end_matches('/').to_string())
+}
+
+/// 接口地址连通性检查结果
+#[derive(Debug, Serialize)]
+pub struct EndpointInfo {
+    /// 收到了 HTTP 响应 (任何状态码都说明网络可达)
+    pub reachable: bool,
+    pub status: u16,
+    /// 从发出请求到收到响应头的耗时
+    #[serde(rename = "latencyMs")]
+    pub latency_ms: u64,
+}
+
+/// 检查接口地址是否可达, 在打补丁前确认提示词增强的 API 地址可用
+/// 代理取自 HTTP_PROXY / HTTPS_PROXY / ALL_PROXY 环境变量
+#[tauri::command]
+pub async fn test_endpoint(url: String) -> Result<EndpointInfo, PatchError> {
+    let url = reqwest::Url::parse(url.trim()).map_err(|e| PatchError::EndpointUnreachable {
+        cause: EndpointFailure::InvalidUrl,
+        message: format!("地址格式错误: {}", e),
+    })?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(PatchError::EndpointUnreachable {
+            cause: EndpointFailure::InvalidUrl,
+            message: format!("不支持的协议: {}", url.scheme()),
+        });
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(ENDPOINT_TIMEOUT)
+        .build()
+        .map_err(|e| PatchError::Io(format!("创建 HTTP 客户端失败: {}", e)))?;
+
+    let started = Instant::now();
+    let response = client.get(url).send().await.map_err(|e| PatchError::EndpointUnreachable {
+        cause: endpoint_failure(&e),
+        message: error_chain(&e),
+    })?;
+
+    Ok(EndpointInfo {
+        reachable: true,
+        status: response.status().as_u16(),
+        latency_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+/// 按错误链判断失败环节; reqwest 不直接区分 DNS 与 TLS 错误, 只能从底层错误信息判断
+fn endpoint_failure(error: &reqwest::Error) -> EndpointFailure {
+    if error.is_timeout() {
+        return EndpointFailure::Timeout;
+    }
+    if error.is_builder() {
+        return EndpointFailure::InvalidUrl;
+    }
+
+    let chain = error_chain(error).to_lowercase();
+    if chain.contains("dns error") || chain.contains("failed to lookup address") {
+        EndpointFailure::Dns
+    } else if chain.contains("certificate") || chain.contains("tls") || chain.contains("handshake") {
+        EndpointFailure::Tls
+    } else {
+        EndpointFailure::Connect
+    }
+}
+
+/// 拼接错误及其全部来源的信息
+fn error_chain(error: &reqwest::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
+
 fn fail(message: &str) -> PromptConnectionResult {
     PromptConnectionResult {
         success: false,
@@ -119,3 +225,28 @@ fn extract_error_message(body: &str) -> String {
 
     body.chars().take(240).collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_scheme_defaults_to_https() {
+        assert_eq!(normalize_api_base("example.com").unwrap(), "https://example.com");
+    }
+
+    #[test]
+    fn surrounding_whitespace_and_trailing_slash_are_removed() {
+        assert_eq!(normalize_api_base(" https://x/ ").unwrap(), "https://x");
+    }
+
+    #[test]
+    fn non_http_scheme_is_rejected() {
+        assert!(normalize_api_base("ftp://bad").is_err());
+    }
+
+    #[test]
+    fn empty_input_means_unset() {
+        assert_eq!(normalize_api_base("  ").unwrap(), "");
+    }
+}
@@ -0,0 +1,306 @@
+// 系统钥匙串
+// 配置中的敏感字段 (如提示词增强的 API Key) 可改存系统钥匙串, 配置文件中只保留引用
+// Windows 为凭据管理器, macOS 为钥匙串, Linux 经 secret-tool 访问 Secret Service; 不可用时由调用方退回明文
+
+/// 钥匙串条目的服务名
+const SERVICE: &str = "Antigravity-Power-Pro";
+
+/// 保存密钥, 已存在时覆盖
+pub fn store(account: &str, secret: &str) -> Result<(), String> {
+    platform::store(account, secret).map_err(|e| format!("写入系统钥匙串失败: {}", e))
+}
+
+/// 读取密钥, 条目不存在时返回 None
+pub fn load(account: &str) -> Result<Option<String>, String> {
+    platform::load(account).map_err(|e| format!("读取系统钥匙串失败: {}", e))
+}
+
+/// 删除密钥, 条目不存在时视为成功
+pub fn delete(account: &str) -> Result<(), String> {
+    platform::delete(account).map_err(|e| format!("删除系统钥匙串条目失败: {}", e))
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::ptr;
+    use windows_sys::Win32::Foundation::{GetLastError, ERROR_NOT_FOUND};
+    use windows_sys::Win32::Security::Credentials::{
+        CredDeleteW, CredFree, CredReadW, CredWriteW, CREDENTIALW, CRED_PERSIST_LOCAL_MACHINE,
+        CRED_TYPE_GENERIC,
+    };
+
+    use super::SERVICE;
+
+    /// 凭据名: <服务名>:<条目名>, 以 0 结尾的 UTF-16
+    fn target_name(account: &str) -> Vec<u16> {
+        format!("{}:{}", SERVICE, account)
+            .encode_utf16()
+            .chain(std::iter::once(0))
+            .collect()
+    }
+
+    pub fn store(account: &str, secret: &str) -> Result<(), String> {
+        let mut target = target_name(account);
+        let mut blob = secret.as_bytes().to_vec();
+        let credential = CREDENTIALW {
+            Type: CRED_TYPE_GENERIC,
+            TargetName: target.as_mut_ptr(),
+            CredentialBlobSize: blob.len() as u32,
+            CredentialBlob: blob.as_mut_ptr(),
+            Persist: CRED_PERSIST_LOCAL_MACHINE,
+            ..Default::default()
+        };
+        // SAFETY: credential 中的指针指向调用期间保持有效的 target 与 blob
+        let ok = unsafe { CredWriteW(&credential, 0) };
+        if ok == 0 {
+            // SAFETY: 无参数, 读取本线程的最后错误
+            return Err(format!("错误码 {}", unsafe { GetLastError() }));
+        }
+        Ok(())
+    }
+
+    pub fn load(account: &str) -> Result<Option<String>, String> {
+        let target = target_name(account);
+        let mut credential: *mut CREDENTIALW = ptr::null_mut();
+        // SAFETY: target 以 0 结尾, credential 只在读取成功后使用并由 CredFree 释放
+        unsafe {
+            if CredReadW(target.as_ptr(), CRED_TYPE_GENERIC, 0, &mut credential) == 0 {
+                let error = GetLastError();
+                return if error == ERROR_NOT_FOUND {
+                    Ok(None)
+                } else {
+                    Err(format!("错误码 {}", error))
+                };
+            }
+            let blob = std::slice::from_raw_parts(
+                (*credential).CredentialBlob,
+                (*credential).CredentialBlobSize as usize,
+            );
+            let secret = String::from_utf8(blob.to_vec()).map_err(|e| e.to_string());
+            CredFree(credential as *const _);
+            secret.map(Some)
+        }
+    }
+
+    pub fn delete(account: &str) -> Result<(), String> {
+        let target = target_name(account);
+        // SAFETY: target 以 0 结尾, 调用期间保持有效
+        unsafe {
+            if CredDeleteW(target.as_ptr(), CRED_TYPE_GENERIC, 0) == 0 {
+                let error = GetLastError();
+                if error != ERROR_NOT_FOUND {
+                    return Err(format!("错误码 {}", error));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::ffi::c_void;
+    use std::ptr;
+
+    use super::SERVICE;
+
+    type OsStatus = i32;
+    type SecKeychainItemRef = *mut c_void;
+
+    /// errSecItemNotFound
+    const ITEM_NOT_FOUND: OsStatus = -25300;
+
+    #[link(name = "Security", kind = "framework")]
+    extern "C" {
+        fn SecKeychainFindGenericPassword(
+            keychain_or_array: *const c_void,
+            service_name_length: u32,
+            service_name: *const u8,
+            account_name_length: u32,
+            account_name: *const u8,
+            password_length: *mut u32,
+            password_data: *mut *mut c_void,
+            item_ref: *mut SecKeychainItemRef,
+        ) -> OsStatus;
+        fn SecKeychainAddGenericPassword(
+            keychain: *mut c_void,
+            service_name_length: u32,
+            service_name: *const u8,
+            account_name_length: u32,
+            account_name: *const u8,
+            password_length: u32,
+            password_data: *const c_void,
+            item_ref: *mut SecKeychainItemRef,
+        ) -> OsStatus;
+        fn SecKeychainItemModifyAttributesAndData(
+            item_ref: SecKeychainItemRef,
+            attr_list: *const c_void,
+            length: u32,
+            data: *const c_void,
+        ) -> OsStatus;
+        fn SecKeychainItemFreeContent(attr_list: *mut c_void, data: *mut c_void) -> OsStatus;
+        fn SecKeychainItemDelete(item_ref: SecKeychainItemRef) -> OsStatus;
+    }
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        fn CFRelease(cf: *const c_void);
+    }
+
+    /// 查找条目; 找到时返回条目引用 (调用方负责 CFRelease), want_data 为 true 时同时返回密钥
+    fn find(account: &str, want_data: bool) -> Result<Option<(SecKeychainItemRef, Vec<u8>)>, String> {
+        let mut length = 0u32;
+        let mut data: *mut c_void = ptr::null_mut();
+        let mut item: SecKeychainItemRef = ptr::null_mut();
+        // SAFETY: 服务名与条目名按长度传入, 输出参数指向有效的局部变量, data 由 SecKeychainItemFreeContent 释放
+        unsafe {
+            let status = SecKeychainFindGenericPassword(
+                ptr::null(),
+                SERVICE.len() as u32,
+                SERVICE.as_ptr(),
+                account.len() as u32,
+                account.as_ptr(),
+                if want_data { &mut length } else { ptr::null_mut() },
+                if want_data { &mut data } else { ptr::null_mut() },
+                &mut item,
+            );
+            match status {
+                0 => {}
+                ITEM_NOT_FOUND => return Ok(None),
+                status => return Err(format!("错误码 {}", status)),
+            }
+            let secret = if data.is_null() {
+                Vec::new()
+            } else {
+                let secret = std::slice::from_raw_parts(data as *const u8, length as usize).to_vec();
+                SecKeychainItemFreeContent(ptr::null_mut(), data);
+                secret
+            };
+            Ok(Some((item, secret)))
+        }
+    }
+
+    pub fn store(account: &str, secret: &str) -> Result<(), String> {
+        // SAFETY: 条目引用来自 find 并在使用后释放, 密钥按长度传入
+        let status = unsafe {
+            match find(account, false)? {
+                Some((item, _)) => {
+                    let status = SecKeychainItemModifyAttributesAndData(
+                        item,
+                        ptr::null(),
+                        secret.len() as u32,
+                        secret.as_ptr() as *const c_void,
+                    );
+                    CFRelease(item);
+                    status
+                }
+                None => SecKeychainAddGenericPassword(
+                    ptr::null_mut(),
+                    SERVICE.len() as u32,
+                    SERVICE.as_ptr(),
+                    account.len() as u32,
+                    account.as_ptr(),
+                    secret.len() as u32,
+                    secret.as_ptr() as *const c_void,
+                    ptr::null_mut(),
+                ),
+            }
+        };
+        if status != 0 {
+            return Err(format!("错误码 {}", status));
+        }
+        Ok(())
+    }
+
+    pub fn load(account: &str) -> Result<Option<String>, String> {
+        let Some((item, secret)) = find(account, true)? else {
+            return Ok(None);
+        };
+        // SAFETY: 条目引用来自 find, 只释放一次
+        unsafe { CFRelease(item) };
+        String::from_utf8(secret).map(Some).map_err(|e| e.to_string())
+    }
+
+    pub fn delete(account: &str) -> Result<(), String> {
+        let Some((item, _)) = find(account, false)? else {
+            return Ok(());
+        };
+        // SAFETY: 条目引用来自 find, 删除后释放
+        let status = unsafe {
+            let status = SecKeychainItemDelete(item);
+            CFRelease(item);
+            status
+        };
+        if status != 0 {
+            return Err(format!("错误码 {}", status));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+mod platform {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    use super::SERVICE;
+
+    /// 经 secret-tool (libsecret) 访问 Secret Service, 条目按 service / account 属性查找
+    fn secret_tool(args: &[&str], account: &str) -> Command {
+        let mut command = Command::new("secret-tool");
+        command
+            .args(args)
+            .args(["service", SERVICE, "account", account])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        command
+    }
+
+    fn failure(output: &std::process::Output) -> String {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        if stderr.is_empty() {
+            format!("secret-tool 退出码 {:?}", output.status.code())
+        } else {
+            stderr
+        }
+    }
+
+    pub fn store(account: &str, secret: &str) -> Result<(), String> {
+        let label = format!("--label={} {}", SERVICE, account);
+        // 密钥经 stdin 传入, 不出现在进程参数中
+        let mut child = secret_tool(&["store", &label], account)
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("无法运行 secret-tool: {}", e))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(secret.as_bytes()).map_err(|e| e.to_string())?;
+        }
+        let output = child.wait_with_output().map_err(|e| e.to_string())?;
+        if !output.status.success() {
+            return Err(failure(&output));
+        }
+        Ok(())
+    }
+
+    pub fn load(account: &str) -> Result<Option<String>, String> {
+        let output = secret_tool(&["lookup"], account)
+            .output()
+            .map_err(|e| format!("无法运行 secret-tool: {}", e))?;
+        // 条目不存在时 secret-tool 以非 0 退出且没有输出
+        if !output.status.success() {
+            return if output.stderr.is_empty() { Ok(None) } else { Err(failure(&output)) };
+        }
+        String::from_utf8(output.stdout).map(Some).map_err(|e| e.to_string())
+    }
+
+    pub fn delete(account: &str) -> Result<(), String> {
+        let output = secret_tool(&["clear"], account)
+            .output()
+            .map_err(|e| format!("无法运行 secret-tool: {}", e))?;
+        if !output.status.success() && !output.stderr.is_empty() {
+            return Err(failure(&output));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,221 @@
+// 补丁自检
+// 补丁状态只看标记是否存在, 自检逐项确认补丁能正常工作: 备份清单, 文件摘要, 配置键, 注入锚点与编辑器版本
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+use crate::embedded;
+use super::backup::verify_manifest;
+use super::editor::Editor;
+use super::integrity::verify_file_hashes;
+use super::patch::{
+    installed_anchor_checks, is_patch_present, stale_config_keys, stored_file_hashes,
+};
+use super::detect::DetectionCache;
+use super::user_data::first_install;
+use super::version::read_app_version;
+
+/// 自检项
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SelfTestItem {
+    /// 安装中存在补丁
+    Installed,
+    /// 备份清单与备份文件一致, 卸载时能还原原版
+    BackupManifest,
+    /// 补丁文件与安装时记录的摘要一致
+    FileChecksums,
+    /// 配置包含补丁内容需要的全部键
+    ConfigKeys,
+    /// workbench.html 中的注入锚点仍然存在, 编辑器更新后可以重新安装
+    Anchors,
+    /// 内置补丁支持当前编辑器版本
+    EditorVersion,
+}
+
+/// 单项结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CheckStatus {
+    Passed,
+    Failed,
+    /// 不适用于该编辑器, 或前面的检查未通过
+    Skipped,
+}
+
+/// 单项检查
+#[derive(Debug, Serialize)]
+pub struct SelfTestCheck {
+    pub item: SelfTestItem,
+    pub status: CheckStatus,
+    pub message: String,
+}
+
+impl SelfTestCheck {
+    fn new(item: SelfTestItem, result: Result<String, String>) -> Self {
+        let (status, message) = match result {
+            Ok(message) => (CheckStatus::Passed, message),
+            Err(message) => (CheckStatus::Failed, message),
+        };
+        Self { item, status, message }
+    }
+
+    fn skipped(item: SelfTestItem, message: &str) -> Self {
+        Self {
+            item,
+            status: CheckStatus::Skipped,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// 自检报告
+#[derive(Debug, Serialize)]
+pub struct SelfTestReport {
+    pub editor: Editor,
+    /// 自检的安装目录, 未检测到安装时为空
+    pub path: Option<String>,
+    /// 没有任何一项未通过
+    pub healthy: bool,
+    pub checks: Vec<SelfTestCheck>,
+}
+
+/// 对安装逐项自检; path 为空时使用检测到的第一个安装
+#[tauri::command]
+pub async fn run_patch_self_test(
+    app: AppHandle,
+    editor: Editor,
+    path: Option<String>,
+) -> SelfTestReport {
+    let cache = app.state::<DetectionCache>();
+    let install_path = match path {
+        Some(path) => Some(PathBuf::from(path)),
+        None => first_install(&cache, editor).await,
+    };
+    let Some(install_path) = install_path else {
+        let failed = SelfTestCheck::new(
+            SelfTestItem::Installed,
+            Err(format!("未检测到 {} 安装", editor.name())),
+        );
+        return report(editor, None, vec![failed]);
+    };
+
+    let checked_path = install_path.clone();
+    let checks =
+        tauri::async_runtime::spawn_blocking(move || run_checks(editor, &checked_path))
+            .await
+            .unwrap_or_default();
+    log::info!("{} 补丁自检: {:?}", editor.name(), checks);
+    report(editor, Some(install_path), checks)
+}
+
+fn report(editor: Editor, path: Option<PathBuf>, checks: Vec<SelfTestCheck>) -> SelfTestReport {
+    SelfTestReport {
+        editor,
+        path: path.map(|path| path.to_string_lossy().to_string()),
+        healthy: checks.iter().all(|check| check.status != CheckStatus::Failed),
+        checks,
+    }
+}
+
+fn run_checks(editor: Editor, install_path: &Path) -> Vec<SelfTestCheck> {
+    const LATER_ITEMS: [SelfTestItem; 5] = [
+        SelfTestItem::BackupManifest,
+        SelfTestItem::FileChecksums,
+        SelfTestItem::ConfigKeys,
+        SelfTestItem::Anchors,
+        SelfTestItem::EditorVersion,
+    ];
+
+    if !is_patch_present(editor, install_path) {
+        let mut checks = vec![SelfTestCheck::new(
+            SelfTestItem::Installed,
+            Err("补丁未安装".to_string()),
+        )];
+        checks.extend(LATER_ITEMS.map(|item| SelfTestCheck::skipped(item, "补丁未安装")));
+        return checks;
+    }
+
+    let is_panel = editor != Editor::Antigravity;
+    vec![
+        SelfTestCheck::new(SelfTestItem::Installed, Ok("补丁已安装".to_string())),
+        SelfTestCheck::new(SelfTestItem::BackupManifest, check_backup(install_path)),
+        if is_panel {
+            SelfTestCheck::skipped(SelfTestItem::FileChecksums, "面板补丁不记录文件摘要")
+        } else {
+            SelfTestCheck::new(SelfTestItem::FileChecksums, check_checksums(install_path))
+        },
+        if is_panel {
+            SelfTestCheck::skipped(SelfTestItem::ConfigKeys, "面板补丁的配置不区分补丁内容版本")
+        } else {
+            SelfTestCheck::new(SelfTestItem::ConfigKeys, check_config_keys(install_path))
+        },
+        SelfTestCheck::new(SelfTestItem::Anchors, check_anchors(editor, install_path)),
+        if is_panel {
+            SelfTestCheck::skipped(SelfTestItem::EditorVersion, "面板补丁不区分编辑器版本")
+        } else {
+            SelfTestCheck::new(SelfTestItem::EditorVersion, check_version(install_path))
+        },
+    ]
+}
+
+fn check_backup(install_path: &Path) -> Result<String, String> {
+    verify_manifest(install_path).map(|count| format!("备份清单完整 ({} 个文件)", count))
+}
+
+fn check_checksums(install_path: &Path) -> Result<String, String> {
+    let file_hashes = stored_file_hashes(&install_path.to_string_lossy())?;
+    if file_hashes.is_empty() {
+        return Err("未记录文件摘要, 请重新安装补丁".to_string());
+    }
+    let changed: Vec<String> = verify_file_hashes(install_path, &file_hashes)
+        .into_iter()
+        .filter(|file| !file.matches)
+        .map(|file| file.path)
+        .collect();
+    if changed.is_empty() {
+        Ok(format!("{} 个补丁文件与安装时一致", file_hashes.len()))
+    } else {
+        Err(format!("补丁文件在安装后被改动: {}", changed.join(", ")))
+    }
+}
+
+fn check_config_keys(install_path: &Path) -> Result<String, String> {
+    let missing = stale_config_keys(install_path);
+    if missing.is_empty() {
+        Ok("配置包含补丁需要的全部键".to_string())
+    } else {
+        Err(format!("配置缺少以下键, 请重新保存配置: {}", missing.join(", ")))
+    }
+}
+
+fn check_anchors(editor: Editor, install_path: &Path) -> Result<String, String> {
+    let anchors = installed_anchor_checks(editor, install_path);
+    if anchors.is_empty() {
+        return Err("没有适用于当前编辑器版本的注入锚点".to_string());
+    }
+    let missing: Vec<String> = anchors
+        .iter()
+        .filter(|check| !check.present)
+        .map(|check| check.anchor.clone())
+        .collect();
+    if missing.is_empty() {
+        Ok(format!("{} 个注入锚点都存在", anchors.len()))
+    } else {
+        Err(format!("workbench.html 中缺少注入锚点: {}", missing.join(", ")))
+    }
+}
+
+fn check_version(install_path: &Path) -> Result<String, String> {
+    let version = read_app_version(install_path);
+    match (version, embedded::select_payload(version)) {
+        (Some(version), Some(_)) => Ok(format!("编辑器版本 {} 受支持", version)),
+        (None, Some(_)) => Ok("读取不到编辑器版本, 按最新补丁内容处理".to_string()),
+        (version, None) => Err(format!(
+            "编辑器版本 {} 不受支持 (支持: {})",
+            version.map(|version| version.to_string()).unwrap_or_default(),
+            embedded::supported_versions().join("; ")
+        )),
+    }
+}
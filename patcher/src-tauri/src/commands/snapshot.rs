@@ -0,0 +1,91 @@
+// 安装目录快照
+// 跨多个补丁计划的操作 (如先卸载再安装) 无法靠单个计划回滚, 先把涉及的文件读入内存, 失败时整体写回
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 快照中的单个路径: 文件内容, 目录下的全部文件, 或原先不存在
+enum Entry {
+    File(Vec<u8>),
+    Dir(Vec<(PathBuf, Vec<u8>)>),
+    Missing,
+}
+
+/// 一组文件和目录在某一时刻的内容
+pub struct Snapshot {
+    entries: Vec<(PathBuf, Entry)>,
+}
+
+impl Snapshot {
+    /// 读取各路径当前的内容, 目录会递归读取其中所有文件
+    pub fn capture(paths: &[PathBuf]) -> Result<Self, String> {
+        let mut entries = Vec::new();
+        for path in paths {
+            let entry = if path.is_dir() {
+                let mut files = Vec::new();
+                read_dir_files(path, &mut files)?;
+                Entry::Dir(files)
+            } else if path.exists() {
+                Entry::File(read(path)?)
+            } else {
+                Entry::Missing
+            };
+            entries.push((path.clone(), entry));
+        }
+        Ok(Self { entries })
+    }
+
+    /// 把所有路径恢复到快照时的内容, 返回恢复失败的项 (尽量恢复其余路径)
+    pub fn restore(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        for (path, entry) in &self.entries {
+            if let Err(error) = restore_entry(path, entry) {
+                errors.push(error);
+            }
+        }
+        errors
+    }
+}
+
+fn restore_entry(path: &Path, entry: &Entry) -> Result<(), String> {
+    if path.is_dir() {
+        fs::remove_dir_all(path).map_err(|e| format!("删除目录失败 {:?}: {}", path, e))?;
+    }
+
+    match entry {
+        Entry::File(content) => write(path, content),
+        Entry::Dir(files) => {
+            fs::create_dir_all(path).map_err(|e| format!("创建目录失败 {:?}: {}", path, e))?;
+            files.iter().try_for_each(|(file, content)| write(file, content))
+        }
+        Entry::Missing if path.exists() => {
+            fs::remove_file(path).map_err(|e| format!("删除文件失败 {:?}: {}", path, e))
+        }
+        Entry::Missing => Ok(()),
+    }
+}
+
+fn read_dir_files(dir: &Path, files: &mut Vec<(PathBuf, Vec<u8>)>) -> Result<(), String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("读取目录失败 {:?}: {}", dir, e))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            read_dir_files(&path, files)?;
+        } else {
+            let content = read(&path)?;
+            files.push((path, content));
+        }
+    }
+    Ok(())
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|e| format!("读取文件失败 {:?}: {}", path, e))
+}
+
+fn write(path: &Path, content: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建目录失败 {:?}: {}", parent, e))?;
+    }
+    fs::write(path, content).map_err(|e| format!("写入文件失败 {:?}: {}", path, e))
+}
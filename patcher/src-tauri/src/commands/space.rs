@@ -0,0 +1,70 @@
+// 磁盘空间检查
+// 备份与写入补丁前确认目标卷剩余空间足够, 避免快满的磁盘上备份复制到一半失败
+
+use std::fs;
+use std::path::Path;
+
+use super::error::PatchError;
+
+/// 预留的余量, 补丁文件本身与配置写入也需要少量空间
+const SPACE_MARGIN: u64 = 16 * 1024 * 1024;
+
+/// 确认各目录所在卷都能再写入 files 的总大小 (加余量)
+/// 无法查询剩余空间时不阻止操作
+pub fn ensure_space_for(files: &[&Path], target_dirs: &[&Path]) -> Result<(), PatchError> {
+    let needed = files
+        .iter()
+        .filter_map(|file| fs::metadata(file).ok())
+        .map(|metadata| metadata.len())
+        .sum::<u64>()
+        + SPACE_MARGIN;
+
+    for dir in target_dirs {
+        if let Some(available) = available_space(dir) {
+            if available < needed {
+                return Err(PatchError::InsufficientSpace { needed, available });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 路径所在卷对当前用户可用的剩余空间; 路径不存在时取最近的已存在上级目录
+fn available_space(path: &Path) -> Option<u64> {
+    let existing = path.ancestors().find(|ancestor| ancestor.exists())?;
+    query_available_space(existing)
+}
+
+#[cfg(target_os = "windows")]
+fn query_available_space(path: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut available = 0u64;
+    // SAFETY: wide 以 0 结尾, 输出参数指向有效的 u64, 其余输出参数允许为空
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    (ok != 0).then_some(available)
+}
+
+#[cfg(unix)]
+fn query_available_space(path: &Path) -> Option<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: c_path 以 0 结尾, stat 为有效的输出缓冲区
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    #[allow(clippy::unnecessary_cast)]
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
@@ -0,0 +1,106 @@
+// 补丁状态 (逐文件)
+// 编辑器更新可能只还原部分文件, 逐个文件报告便于排查半安装状态
+
+use serde::Serialize;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+use super::backup::resolve_relative;
+use super::marker::is_patched_content;
+
+/// 补丁整体状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PatchState {
+    /// 没有任何补丁痕迹
+    NotPatched,
+    /// 只有部分文件带有补丁 (例如编辑器更新还原了其中一些)
+    Partial,
+    /// 已安装组件的文件全部就绪
+    Patched,
+    /// 文件全部就绪, 但由更旧的补丁器安装, 卸载前建议先重新安装
+    PatchedByOlderVersion,
+    /// 补丁文件在安装后被改动 (通常是编辑器自动更新还原了补丁), 需要重新安装
+    RevertedByUpdate,
+    /// 文件全部就绪, 但配置缺少安装的补丁内容需要的键 (补丁内容升级后配置未重新写入), 保存一次配置即可修复
+    ConfigPayloadMismatch,
+}
+
+/// 单个文件的补丁状态
+#[derive(Debug, Serialize)]
+pub struct FilePatchState {
+    /// 相对安装目录的路径
+    pub path: String,
+    pub exists: bool,
+    /// 文件中是否带有补丁标记 (补丁自带的文件存在即视为带标记)
+    #[serde(rename = "markerPresent")]
+    pub marker_present: bool,
+}
+
+/// 补丁状态报告
+#[derive(Debug, Serialize)]
+pub struct PatchStatus {
+    pub overall: PatchState,
+    pub files: Vec<FilePatchState>,
+    /// 内容与安装时记录的摘要不一致的文件 (相对安装目录)
+    #[serde(rename = "changedFiles")]
+    pub changed_files: Vec<String>,
+    /// 配置中缺少的、安装的补丁内容需要的键 (嵌套键用 . 分隔)
+    #[serde(rename = "missingConfigKeys")]
+    pub missing_config_keys: Vec<String>,
+    /// 配置中是否启用了该编辑器的补丁, 停用时注入保留但功能全部关闭
+    pub enabled: bool,
+    /// 按配置语言生成的整体状态说明
+    #[serde(rename = "statusSummary")]
+    pub status_summary: String,
+}
+
+/// 检查单个文件: HTML 按补丁标记判断, 其余文件 (补丁自带的配置等) 按是否存在判断
+pub fn file_state(install_path: &Path, relative_path: &str) -> FilePatchState {
+    let path = resolve_relative(install_path, relative_path);
+    let exists = path.exists();
+    let marker_present = if relative_path.ends_with(".html") {
+        fs::read(&path).is_ok_and(|content| is_patched_content(&content))
+    } else {
+        exists
+    };
+
+    FilePatchState {
+        path: relative_path.to_string(),
+        exists,
+        marker_present,
+    }
+}
+
+/// 一组文件共同组成的补丁组件的状态
+pub fn component_state(files: &[&FilePatchState]) -> PatchState {
+    let marked = files.iter().filter(|file| file.marker_present).count();
+    match marked {
+        0 => PatchState::NotPatched,
+        n if n == files.len() => PatchState::Patched,
+        _ => PatchState::Partial,
+    }
+}
+
+/// 配置中缺少的键; required 为 (键, 开始需要该键的补丁内容版本), 只检查配置中 payloadVersion 需要的键
+///
+/// 未记录或无法解析补丁内容版本的配置 (旧版本安装, 开发补丁包) 只检查最初版本就需要的键
+pub fn missing_config_keys(config: &Value, required: &[(&str, u32)]) -> Vec<String> {
+    let payload_version = config
+        .get("payloadVersion")
+        .and_then(Value::as_str)
+        .and_then(|version| version.parse::<u32>().ok())
+        .unwrap_or(1);
+
+    required
+        .iter()
+        .filter(|(_, since)| *since <= payload_version)
+        .filter(|(key, _)| {
+            key.split('.')
+                .try_fold(config, |value, part| value.get(part))
+                .is_none()
+        })
+        .map(|(key, _)| key.to_string())
+        .collect()
+}
@@ -0,0 +1,147 @@
+// 编辑器自动更新设置
+// 编辑器自动更新会覆盖补丁文件, 读取 product.json 与用户设置 (update.mode) 判断自动更新是否开启, 需要时可以关闭
+
+use serde::Serialize;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, State};
+
+use super::config::app_data_dir;
+use super::editor::Editor;
+use super::json_edit::with_root_key;
+use super::plan::write_replacing;
+use super::detect::DetectionCache;
+use super::user_data::{first_install, read_json, read_product, settings_path};
+
+/// 用户设置中控制自动更新的键
+const UPDATE_MODE_KEY: &str = "update.mode";
+
+/// 关闭自动更新时的提示
+const DISABLE_WARNING: &str = "已关闭自动更新: 编辑器不会再自动获取新版本与安全修复, 请定期手动检查更新";
+
+/// 编辑器的自动更新设置
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum UpdateSetting {
+    /// 会自动检查并安装更新; mode 为 update.mode 的值, 未设置时为 default
+    Enabled { mode: String, channel: Option<String> },
+    /// 用户设置中关闭了自动更新 (update.mode 为 none 或 manual)
+    Disabled { mode: String, channel: Option<String> },
+    /// 编辑器构建不带更新服务 (product.json 中没有 updateUrl)
+    Unsupported,
+    /// 找不到安装或无法读取设置
+    Unknown { reason: String },
+}
+
+/// 修改自动更新设置的结果
+#[derive(Debug, Serialize)]
+pub struct UpdateSettingChange {
+    #[serde(rename = "settingsPath")]
+    pub settings_path: String,
+    /// 修改前的设置文件备份, 设置文件原本不存在时为空
+    #[serde(rename = "backupPath")]
+    pub backup_path: Option<String>,
+    /// 关闭自动更新时的提示
+    pub warning: Option<String>,
+}
+
+/// 读取检测到的安装的自动更新设置, 无法判断时返回 Unknown
+#[tauri::command]
+pub async fn get_editor_update_setting(app: AppHandle, editor: Editor) -> UpdateSetting {
+    let cache = app.state::<DetectionCache>();
+    let Some(install_path) = first_install(&cache, editor).await else {
+        return UpdateSetting::Unknown {
+            reason: format!("未检测到 {} 安装", editor.name()),
+        };
+    };
+    let Some(product) = read_product(&install_path) else {
+        return UpdateSetting::Unknown {
+            reason: "无法读取 product.json".to_string(),
+        };
+    };
+    if product.get("updateUrl").and_then(Value::as_str).is_none_or(str::is_empty) {
+        return UpdateSetting::Unsupported;
+    }
+
+    let channel = product.get("quality").and_then(Value::as_str).map(String::from);
+    let Some(settings_path) = settings_path(editor, &install_path, &product) else {
+        return UpdateSetting::Unknown {
+            reason: "无法确定用户设置目录".to_string(),
+        };
+    };
+    // 设置文件不存在说明从未改过设置, 使用默认值
+    let mode = if settings_path.exists() {
+        let Some(settings) = read_json(&settings_path) else {
+            return UpdateSetting::Unknown {
+                reason: format!("无法解析用户设置 (可能含有注释): {}", settings_path.display()),
+            };
+        };
+        settings
+            .get(UPDATE_MODE_KEY)
+            .and_then(Value::as_str)
+            .unwrap_or("default")
+            .to_string()
+    } else {
+        "default".to_string()
+    };
+
+    if matches!(mode.as_str(), "none" | "manual") {
+        UpdateSetting::Disabled { mode, channel }
+    } else {
+        UpdateSetting::Enabled { mode, channel }
+    }
+}
+
+/// 修改检测到的安装的自动更新设置: enabled 为 false 时把 update.mode 设为 none, 为 true 时恢复为 default
+///
+/// 修改前把原设置文件备份到应用数据目录; 含注释等无法按 JSON 解析的设置文件不做修改
+#[tauri::command]
+pub async fn set_editor_update_setting(
+    cache: State<'_, DetectionCache>,
+    editor: Editor,
+    enabled: bool,
+) -> Result<UpdateSettingChange, String> {
+    let install_path = first_install(&cache, editor)
+        .await
+        .ok_or_else(|| format!("未检测到 {} 安装", editor.name()))?;
+    let product = read_product(&install_path)
+        .ok_or_else(|| "无法读取 product.json".to_string())?;
+    let settings_path = settings_path(editor, &install_path, &product)
+        .ok_or_else(|| "无法确定用户设置目录".to_string())?;
+
+    let mode = if enabled { "default" } else { "none" };
+    let content = with_root_key(&settings_path, UPDATE_MODE_KEY, Value::from(mode))?;
+    let backup_path = backup_settings(editor, &settings_path)?;
+
+    if let Some(parent) = settings_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建设置目录失败: {}", e))?;
+    }
+    write_replacing(&settings_path, content.as_bytes())
+        .map_err(|e| format!("写入用户设置失败: {}", e))?;
+    log::info!("{} 自动更新设置为 {}: {:?}", editor.name(), mode, settings_path);
+
+    Ok(UpdateSettingChange {
+        settings_path: settings_path.to_string_lossy().to_string(),
+        backup_path: backup_path.map(|path| path.to_string_lossy().to_string()),
+        warning: (!enabled).then(|| DISABLE_WARNING.to_string()),
+    })
+}
+
+/// 把设置文件复制到 <应用数据目录>/backups/settings, 文件不存在时无需备份
+fn backup_settings(editor: Editor, settings_path: &Path) -> Result<Option<PathBuf>, String> {
+    if !settings_path.exists() {
+        return Ok(None);
+    }
+
+    let dir = app_data_dir().join("backups").join("settings");
+    fs::create_dir_all(&dir).map_err(|e| format!("创建备份目录失败: {}", e))?;
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let backup_path = dir.join(format!("{}-{}.json", editor.name().to_lowercase(), timestamp));
+    fs::copy(settings_path, &backup_path).map_err(|e| format!("备份用户设置失败: {}", e))?;
+    Ok(Some(backup_path))
+}
@@ -0,0 +1,53 @@
+// 编辑器用户数据目录
+// VS Code 系编辑器的用户数据 (设置, 全局存储) 位于 <系统配置目录>/<nameShort>, 便携版位于安装目录下的 data/user-data
+
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::detect::{detect_in_background, DetectionCache};
+use super::editor::Editor;
+use super::json_edit::strip_bom;
+use super::patch::PRODUCT_JSON;
+
+/// 检测到的第一个安装
+pub async fn first_install(cache: &DetectionCache, editor: Editor) -> Option<PathBuf> {
+    detect_in_background(cache, editor)
+        .await
+        .into_iter()
+        .next()
+        .map(PathBuf::from)
+}
+
+/// 读取安装的 product.json
+pub fn read_product(install_path: &Path) -> Option<Value> {
+    read_json(&install_path.join(PRODUCT_JSON))
+}
+
+/// 读取 JSON 文件, 不存在或无法解析时返回 None
+pub fn read_json(path: &Path) -> Option<Value> {
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(strip_bom(&content)).ok()
+}
+
+/// 用户数据目录: 便携版优先, 否则取系统配置目录下以 nameShort 命名的目录
+pub fn user_data_dir(editor: Editor, install_path: &Path, product: &Value) -> Option<PathBuf> {
+    let portable = install_path.join("data").join("user-data");
+    if portable.is_dir() {
+        return Some(portable);
+    }
+    let name = product
+        .get("nameShort")
+        .and_then(Value::as_str)
+        .unwrap_or(editor.name());
+    Some(dirs::config_dir()?.join(name))
+}
+
+/// 用户设置文件 (User/settings.json)
+pub fn settings_path(editor: Editor, install_path: &Path, product: &Value) -> Option<PathBuf> {
+    Some(
+        user_data_dir(editor, install_path, product)?
+            .join("User")
+            .join("settings.json"),
+    )
+}
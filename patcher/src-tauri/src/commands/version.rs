@@ -0,0 +1,60 @@
+// 版本号解析与比较
+
+use serde::Serialize;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use super::json_edit::strip_bom;
+
+/// 语义化版本号 (major.minor.patch)
+/// 预发布/构建后缀 (如 `-nightly`, `+abc`) 在解析时忽略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl Version {
+    /// 解析 `1.2.3` / `v1.2` / `1.2.3-beta` 形式的版本号, 缺省的段按 0 处理
+    pub fn parse(input: &str) -> Option<Self> {
+        let core = input
+            .trim()
+            .trim_start_matches(['v', 'V'])
+            .split(['-', '+'])
+            .next()?;
+
+        let mut parts = core.split('.');
+        let major = parts.next()?.parse().ok()?;
+        let minor = match parts.next() {
+            Some(part) => part.parse().ok()?,
+            None => 0,
+        };
+        let patch = match parts.next() {
+            Some(part) => part.parse().ok()?,
+            None => 0,
+        };
+
+        Some(Self { major, minor, patch })
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// 读取编辑器安装目录下 resources/app/package.json 的 version 字段
+pub fn read_app_version(install_path: &Path) -> Option<Version> {
+    let package_json = install_path
+        .join("resources")
+        .join("app")
+        .join("package.json");
+
+    let content = fs::read_to_string(package_json).ok()?;
+    let value: serde_json::Value =
+        serde_json::from_str(strip_bom(&content)).ok()?;
+    Version::parse(value.get("version")?.as_str()?)
+}
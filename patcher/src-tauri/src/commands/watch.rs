@@ -0,0 +1,153 @@
+// 补丁状态监视
+// 编辑器自动更新会覆盖补丁文件, 监视补丁相关文件, 有变化时把最新的补丁状态推送给前端
+// 使用系统的文件通知 (inotify / FSEvents / ReadDirectoryChangesW) 监视补丁文件所在目录, 不轮询磁盘
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tauri::ipc::Channel;
+
+use super::backup::resolve_relative;
+use super::patch::{check_patch_status, STATUS_FILES};
+use super::status::PatchStatus;
+
+/// 最后一次文件变化后等待的时间: 安装补丁或编辑器更新会连续写入多个文件, 写完后只推送一次状态
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// 当前的监视线程 (同一时间只监视一个安装目录)
+static WATCHER: Mutex<Option<Watcher>> = Mutex::new(None);
+
+struct Watcher {
+    stop: Sender<Message>,
+    handle: JoinHandle<()>,
+}
+
+/// 监视线程收到的消息
+enum Message {
+    Changed(notify::Result<Event>),
+    Stop,
+}
+
+/// 开始监视安装目录的补丁状态, 立即推送一次当前状态, 之后每次变化时推送
+/// 已在监视其他目录时先停止旧的监视
+#[tauri::command]
+pub fn watch_patch_status(path: String, on_status: Channel<PatchStatus>) -> Result<(), String> {
+    stop_watching();
+
+    let install_path = PathBuf::from(&path);
+    if !install_path.is_dir() {
+        return Err(format!("安装目录不存在: {}", path));
+    }
+    let watched: Vec<PathBuf> = STATUS_FILES
+        .iter()
+        .map(|relative_path| resolve_relative(&install_path, relative_path))
+        .collect();
+
+    let (sender, messages) = mpsc::channel::<Message>();
+    let events = sender.clone();
+    let mut notifier = notify::recommended_watcher(move |event| {
+        let _ = events.send(Message::Changed(event));
+    })
+    .map_err(|e| format!("创建文件监视失败: {}", e))?;
+    let mut watched_dirs = Vec::new();
+    watch_dirs(&mut notifier, &watched, &mut watched_dirs);
+
+    let handle = thread::spawn(move || {
+        // 前端已关闭 Channel 时退出
+        let send_status = || {
+            check_patch_status(path.clone()).map_or(true, |status| on_status.send(status).is_ok())
+        };
+        if !send_status() {
+            return;
+        }
+
+        // 有未推送的变化时, 到这个时间推送
+        let mut pending: Option<Instant> = None;
+        loop {
+            let message = match pending {
+                Some(deadline) => {
+                    messages.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                }
+                None => messages.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match message {
+                Ok(Message::Changed(Ok(event))) => {
+                    if is_relevant(&event, &watched) {
+                        pending = Some(Instant::now() + DEBOUNCE);
+                    }
+                }
+                Ok(Message::Changed(Err(e))) => log::warn!("文件监视出错: {}", e),
+                Err(RecvTimeoutError::Timeout) => {
+                    pending = None;
+                    // 补丁目录可能刚被创建或删除, 重新确定要监视的目录
+                    watch_dirs(&mut notifier, &watched, &mut watched_dirs);
+                    if !send_status() {
+                        break;
+                    }
+                }
+                Ok(Message::Stop) | Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+    });
+
+    log::info!("开始监视补丁状态: {:?}", install_path);
+    if let Ok(mut watcher) = WATCHER.lock() {
+        *watcher = Some(Watcher { stop: sender, handle });
+    }
+    Ok(())
+}
+
+/// 停止监视补丁状态
+#[tauri::command]
+pub fn stop_watching_patch_status() {
+    stop_watching();
+}
+
+/// 停止监视线程并等待其退出, 应用退出时也会调用
+pub fn stop_watching() {
+    let watcher = WATCHER.lock().ok().and_then(|mut watcher| watcher.take());
+    if let Some(Watcher { stop, handle }) = watcher {
+        let _ = stop.send(Message::Stop);
+        let _ = handle.join();
+    }
+}
+
+/// 监视每个文件所在的目录 (只看目录本身, 不递归)
+/// 目录还不存在时 (如补丁未安装时的 cascade-panel) 监视最近的已存在上级目录, 目录创建后再监视它;
+/// 已删除的目录从列表中移除, 重新创建时重新监视
+fn watch_dirs(
+    notifier: &mut RecommendedWatcher,
+    files: &[PathBuf],
+    watched_dirs: &mut Vec<PathBuf>,
+) {
+    watched_dirs.retain(|dir| {
+        let exists = dir.is_dir();
+        if !exists {
+            let _ = notifier.unwatch(dir);
+        }
+        exists
+    });
+    for file in files {
+        let Some(dir) = file.ancestors().skip(1).find(|dir| dir.is_dir()) else {
+            continue;
+        };
+        if watched_dirs.iter().any(|watched| watched == dir) {
+            continue;
+        }
+        match notifier.watch(dir, RecursiveMode::NonRecursive) {
+            Ok(()) => watched_dirs.push(dir.to_path_buf()),
+            Err(e) => log::warn!("监视目录失败 {:?}: {}", dir, e),
+        }
+    }
+}
+
+/// 事件涉及监视的文件, 或它们的上级目录 (目录被创建或删除)
+fn is_relevant(event: &Event, files: &[PathBuf]) -> bool {
+    if matches!(event.kind, EventKind::Access(_)) {
+        return false;
+    }
+    event.paths.iter().any(|path| files.iter().any(|file| file.starts_with(path)))
+}
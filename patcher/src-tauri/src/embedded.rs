@@ -1,10 +1,154 @@
 // 嵌入的补丁资源
 // 使用 include_str! 将文件内容在编译时嵌入到二进制中
+use sha2::{Digest, Sha256};
+use std::fmt;
 use std::fs;
 use std::path::PathBuf;
 
+use crate::commands::Version;
+
 include!(concat!(env!("OUT_DIR"), "/embedded_patches.rs"));
 
+/// 补丁内容版本, patches 目录下的补丁文件有改动时递增
+const PATCH_VERSION: &str = "1";
+
+/// 补丁内容更新说明 (最新在前)
+const PATCH_CHANGELOG: &str = "v1: 侧边栏 (cascade-panel), Manager 窗口 (manager-panel) 与 Windsurf/Cursor 面板 (windsurf-panel)";
+
+/// 补丁内容插在锚点的哪一侧
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnchorPosition {
+    Before,
+    After,
+}
+
+/// 注入锚点: 目标文件中在各版本间稳定存在的片段, 补丁内容相对它插入
+/// 锚点随补丁内容一起维护, 目标文件结构变化时只需在这里调整
+#[derive(Debug, Clone, Copy)]
+pub struct InjectionAnchor {
+    pub token: &'static str,
+    pub position: AnchorPosition,
+}
+
+/// 侧边栏补丁: TrustedTypes bypass 脚本紧跟 <head>
+pub const CASCADE_BYPASS_ANCHOR: InjectionAnchor =
+    InjectionAnchor { token: "<head>", position: AnchorPosition::After };
+/// 侧边栏补丁: 样式表放在 </head> 前
+pub const CASCADE_CSS_ANCHOR: InjectionAnchor =
+    InjectionAnchor { token: "</head>", position: AnchorPosition::Before };
+/// 侧边栏补丁: 脚本放在 </body> 前
+pub const CASCADE_JS_ANCHOR: InjectionAnchor =
+    InjectionAnchor { token: "</body>", position: AnchorPosition::Before };
+/// 侧边栏补丁在 workbench.html 中的注入锚点
+#[derive(Debug, Clone, Copy)]
+pub struct CascadeAnchors {
+    pub bypass: InjectionAnchor,
+    pub css: InjectionAnchor,
+    pub js: InjectionAnchor,
+}
+
+impl CascadeAnchors {
+    pub fn all(&self) -> [InjectionAnchor; 3] {
+        [self.bypass, self.css, self.js]
+    }
+}
+
+pub const CASCADE_ANCHORS: CascadeAnchors = CascadeAnchors {
+    bypass: CASCADE_BYPASS_ANCHOR,
+    css: CASCADE_CSS_ANCHOR,
+    js: CASCADE_JS_ANCHOR,
+};
+/// Windsurf / Cursor 面板补丁: 样式表放在 </head> 前
+pub const PANEL_CSS_ANCHOR: InjectionAnchor =
+    InjectionAnchor { token: "</head>", position: AnchorPosition::Before };
+/// Windsurf / Cursor 面板补丁: 脚本放在 </html> 前
+pub const PANEL_JS_ANCHOR: InjectionAnchor =
+    InjectionAnchor { token: "</html>", position: AnchorPosition::Before };
+
+/// 编辑器版本范围 [min, max), 端点为 None 时不限
+#[derive(Debug, Clone, Copy)]
+pub struct VersionRange {
+    pub min: Option<Version>,
+    pub max: Option<Version>,
+}
+
+impl VersionRange {
+    pub fn contains(&self, version: Version) -> bool {
+        self.min.is_none_or(|min| version >= min) && self.max.is_none_or(|max| version < max)
+    }
+}
+
+impl fmt::Display for VersionRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.min, self.max) {
+            (Some(min), Some(max)) => write!(f, ">={}, <{}", min, max),
+            (Some(min), None) => write!(f, ">={}", min),
+            (None, Some(max)) => write!(f, "<{}", max),
+            (None, None) => write!(f, "全部版本"),
+        }
+    }
+}
+
+/// 一份补丁内容: 适用的编辑器版本范围与对应的注入锚点
+/// 补丁文件各版本共用, 锚点在版本间移动时新增一项并收窄旧项的范围
+#[derive(Debug, Clone, Copy)]
+pub struct Payload {
+    pub versions: VersionRange,
+    pub cascade_anchors: CascadeAnchors,
+}
+
+/// 内置的全部补丁内容, 按适用版本从旧到新排列
+const PAYLOADS: &[Payload] = &[Payload {
+    versions: VersionRange { min: None, max: None },
+    cascade_anchors: CASCADE_ANCHORS,
+}];
+
+/// 选出适用于编辑器版本的补丁内容, 没有匹配项时返回 None
+///
+/// 读取不到编辑器版本时使用最新的一份
+pub fn select_payload(version: Option<Version>) -> Option<&'static Payload> {
+    match version {
+        Some(version) => PAYLOADS.iter().find(|payload| payload.versions.contains(version)),
+        None => PAYLOADS.last(),
+    }
+}
+
+/// 内置补丁支持的编辑器版本范围
+pub fn supported_versions() -> Vec<String> {
+    PAYLOADS.iter().map(|payload| payload.versions.to_string()).collect()
+}
+
+/// 当前嵌入的补丁内容版本
+pub fn patch_version() -> &'static str {
+    PATCH_VERSION
+}
+
+/// 补丁内容更新说明
+pub fn patch_changelog() -> &'static str {
+    PATCH_CHANGELOG
+}
+
+/// 编译时记录的内置补丁摘要
+pub fn expected_checksum() -> &'static str {
+    EMBEDDED_CHECKSUM
+}
+
+/// 计算内置补丁当前的摘要, 与 build.rs 的算法一致: 依次对每个文件计算 路径 + NUL + 内容 + NUL
+pub fn compute_checksum() -> String {
+    let mut hasher = Sha256::new();
+    for (key, content) in EMBEDDED_FILES {
+        hasher.update(key.as_bytes());
+        hasher.update([0]);
+        hasher.update(content.as_bytes());
+        hasher.update([0]);
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
 /// 所有需要写入的文件列表
 pub fn get_all_files() -> Vec<(&'static str, &'static str)> {
     EMBEDDED_FILES.to_vec()
@@ -1,41 +1,136 @@
 // Antigravity-Power-Pro Patcher Rust 后端.
 // 负责路径检测, 补丁安装/卸载, 配置读写.
 
+mod cli;
 mod commands;
 mod embedded;
 
 use commands::{
-    detect_antigravity_path, install_patch, uninstall_patch, update_config,
-    check_patch_status, read_patch_config, read_manager_patch_config,
-    get_config, save_config,
-    detect_windsurf_path, install_windsurf_patch, uninstall_windsurf_patch,
+    detect_antigravity_path, detect_antigravity_path_verbose, detect_all_antigravity_paths,
+    detect_antigravity_from_hint, detect_in_root, DetectionCache,
+    detect_antigravity_with_version, detect_antigravity_installs,
+    install_patch, install_patch_dry_run, uninstall_patch, uninstall_patch_dry_run, uninstall_all,
+    repatch,
+    update_config,
+    check_patch_status, verify_patch_integrity, check_patch_compatibility,
+    is_path_writable, read_patch_config, get_patch_config_path, reveal_install_dir,
+    open_editor_config, backup_now,
+    read_manager_patch_config,
+    get_embedded_patch_version, verify_embedded_payload,
+    get_config, save_config, export_config, import_config, reset_config, open_config_file,
+    list_profiles, save_profile, load_profile, delete_profile, set_manual_path,
+    detect_windsurf_path, detect_windsurf_with_channel,
+    install_windsurf_patch, uninstall_windsurf_patch,
     update_windsurf_config, check_windsurf_patch_status, read_windsurf_patch_config,
-    test_prompt_connection,
+    detect_cursor_path, install_cursor_patch, uninstall_cursor_patch, update_cursor_config,
+    check_cursor_patch_status, supported_editors, refresh_detection,
+    is_bundle_signed, list_backups, restore_backup, verify_backup, cancel_operation,
+    watch_patch_status, stop_watching_patch_status,
+    get_log_path, open_log, collect_diagnostics, save_diagnostics, preflight_check,
+    test_prompt_connection, test_endpoint,
+    get_editor_update_setting, set_editor_update_setting, read_editor_identity,
+    run_patch_self_test, detect_legacy_patch, migrate_from_legacy_patch, compare_installs,
+    forget_install,
 };
+#[cfg(feature = "dev")]
+use commands::install_patch_from_file;
+
+/// 带子命令启动时以命令行模式执行并返回退出码, 否则返回 None
+pub fn run_cli() -> Option<i32> {
+    cli::run(std::env::args().skip(1).collect())
+}
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    commands::init_logging();
+
     tauri::Builder::default()
+        .manage(DetectionCache::default())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .invoke_handler(tauri::generate_handler![
             detect_antigravity_path,
+            detect_antigravity_path_verbose,
+            detect_all_antigravity_paths,
+            detect_antigravity_from_hint,
+            detect_in_root,
+            detect_antigravity_with_version,
+            detect_antigravity_installs,
             install_patch,
+            install_patch_dry_run,
+            #[cfg(feature = "dev")]
+            install_patch_from_file,
             uninstall_patch,
+            uninstall_patch_dry_run,
+            uninstall_all,
+            repatch,
             update_config,
             check_patch_status,
+            verify_patch_integrity,
+            check_patch_compatibility,
+            is_path_writable,
             read_patch_config,
+            get_patch_config_path,
+            reveal_install_dir,
+            backup_now,
             read_manager_patch_config,
+            get_embedded_patch_version,
+            verify_embedded_payload,
             get_config,
             save_config,
+            export_config,
+            import_config,
+            reset_config,
+            list_profiles,
+            save_profile,
+            load_profile,
+            delete_profile,
+            set_manual_path,
             detect_windsurf_path,
+            detect_windsurf_with_channel,
             install_windsurf_patch,
             uninstall_windsurf_patch,
             update_windsurf_config,
             check_windsurf_patch_status,
             read_windsurf_patch_config,
-            test_prompt_connection
+            detect_cursor_path,
+            supported_editors,
+            refresh_detection,
+            install_cursor_patch,
+            uninstall_cursor_patch,
+            update_cursor_config,
+            check_cursor_patch_status,
+            is_bundle_signed,
+            list_backups,
+            restore_backup,
+            verify_backup,
+            cancel_operation,
+            watch_patch_status,
+            stop_watching_patch_status,
+            get_log_path,
+            open_log,
+            collect_diagnostics,
+            save_diagnostics,
+            preflight_check,
+            test_prompt_connection,
+            test_endpoint,
+            get_editor_update_setting,
+            set_editor_update_setting,
+            read_editor_identity,
+            open_config_file,
+            open_editor_config,
+            run_patch_self_test,
+            detect_legacy_patch,
+            migrate_from_legacy_patch,
+            compare_installs,
+            forget_install
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|_app, event| {
+            // 退出前停止补丁状态监视线程
+            if let tauri::RunEvent::Exit = event {
+                commands::stop_watching();
+            }
+        });
 }
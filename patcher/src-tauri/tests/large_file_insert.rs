@@ -0,0 +1,74 @@
+// 大文件注入的内存占用
+// 统计分配需要替换全局分配器, 放在独立的测试程序中, 不影响库的单元测试
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+#[allow(dead_code)]
+#[path = "../src/commands/bytes.rs"]
+mod bytes;
+
+/// 按线程统计已分配字节与峰值, 测试并行运行时互不干扰
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATED: Cell<isize> = const { Cell::new(0) };
+    static PEAK: Cell<isize> = const { Cell::new(0) };
+}
+
+fn track(delta: isize) {
+    let _ = ALLOCATED.try_with(|allocated| {
+        allocated.set(allocated.get() + delta);
+        let _ = PEAK.try_with(|peak| peak.set(peak.get().max(allocated.get())));
+    });
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        track(layout.size() as isize);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        track(-(layout.size() as isize));
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        // 扩容时新旧两块内存同时存在
+        track(new_size as isize);
+        let result = unsafe { System.realloc(ptr, layout, new_size) };
+        track(-(layout.size() as isize));
+        result
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAlloc = CountingAlloc;
+
+/// 执行 operation 期间相对开始时多占用的内存峰值
+fn peak_extra_memory(operation: impl FnOnce()) -> usize {
+    let start = ALLOCATED.with(Cell::get);
+    PEAK.with(|peak| peak.set(start));
+    operation();
+    (PEAK.with(Cell::get) - start) as usize
+}
+
+#[test]
+fn insertion_into_large_file_copies_it_once() {
+    let line = b"<div class=\"bundle\">\xff padding padding padding</div>\r\n";
+    let mut content = line.repeat(64 * 1024 * 1024 / line.len());
+    content.extend_from_slice(b"</head>\r\n</html>\r\n");
+    // 与 fs::read 读入的内容一样没有多余容量
+    content.shrink_to_fit();
+    let size = content.len();
+    let index = bytes::rfind(&content, b"</head>").unwrap();
+    let text = "<script src=\"./cascade-panel/cascade-panel.js\"></script>\n";
+
+    let extra = peak_extra_memory(|| bytes::insert_text(&mut content, index, text));
+
+    // 文件整体在内存中, 插入只多一份结果缓冲区; 原地插入扩容会让峰值达到文件大小的两倍
+    assert!(extra < size + 64 * 1024, "extra {} for file of {}", extra, size);
+    assert_eq!(content.len(), size + text.len() + 1);
+    assert!(content.ends_with(b"</script>\r\n</head>\r\n</html>\r\n"));
+}